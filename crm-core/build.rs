@@ -0,0 +1,50 @@
+use std::{env, fs, path::PathBuf};
+
+fn main() -> anyhow::Result<()> {
+    fs::create_dir_all("src/pb")?;
+    let descriptor = PathBuf::from(env::var("OUT_DIR")?).join("crm_core_descriptor.bin");
+
+    tonic_build::configure()
+        .out_dir("src/pb")
+        .file_descriptor_set_path(&descriptor)
+        .compile_protos(
+            &[
+                "../protos/grpc/health/v1/health.proto",
+                "../protos/grpc/reflection/v1/reflection.proto",
+            ],
+            &["../protos"],
+        )?;
+
+    // v1alpha is wire compatible with v1, older clients (e.g. grpcurl < 1.8.8) only speak it
+    tonic_build::configure()
+        .out_dir("src/pb")
+        .build_client(false)
+        .extern_path(
+            ".grpc.reflection.v1alpha",
+            "crate::pb::grpc::reflection::v1",
+        )
+        .compile_protos(
+            &["../protos/grpc/reflection/v1alpha/reflection.proto"],
+            &["../protos"],
+        )?;
+
+    // error details sent along with the statuses, see `crate::error`
+    tonic_build::configure().out_dir("src/pb").compile_protos(
+        &[
+            "../protos/google/rpc/status.proto",
+            "../protos/google/rpc/error_details.proto",
+        ],
+        &["../protos"],
+    )?;
+
+    // OTLP export only, the collector is the server
+    tonic_build::configure()
+        .out_dir("src/pb")
+        .build_server(false)
+        .compile_protos(
+            &["../protos/opentelemetry/proto/collector/trace/v1/trace_service.proto"],
+            &["../protos"],
+        )?;
+
+    Ok(())
+}
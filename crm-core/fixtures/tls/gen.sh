@@ -0,0 +1,24 @@
+#!/bin/sh
+# Regenerate the test CA and the certificates it signs:
+# `server` for localhost, `crm` and `intruder` as client identities.
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey ec -pkeyopt ec_paramgen_curve:P-256 -nodes -days 36500 \
+    -subj "/CN=crm test ca" -keyout ca.key -out ca.pem
+
+issue() {
+    name=$1
+    san=$2
+    openssl req -newkey ec -pkeyopt ec_paramgen_curve:P-256 -nodes \
+        -subj "/CN=$name" -keyout "$name.key" -out "$name.csr"
+    printf "subjectAltName=%s\nbasicConstraints=CA:FALSE\n" "$san" > "$name.ext"
+    openssl x509 -req -in "$name.csr" -CA ca.pem -CAkey ca.key -CAcreateserial \
+        -days 36500 -extfile "$name.ext" -out "$name.pem"
+    rm "$name.csr" "$name.ext"
+}
+
+issue server "DNS:localhost,IP:127.0.0.1,IP:::1"
+issue crm "DNS:crm"
+issue intruder "DNS:intruder"
+rm -f ca.key ca.srl
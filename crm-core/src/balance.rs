@@ -0,0 +1,335 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    net::lookup_host,
+    sync::mpsc::Sender,
+    task::JoinSet,
+    time::{self, Instant},
+};
+use tonic::transport::{Channel, Endpoint};
+use tower::discover::Change;
+use tracing::{info, warn};
+use url::Url;
+
+use crate::{config::Checks, health::check_remote};
+
+/// changes queued for the balance channel
+const CHANGES: usize = 64;
+
+/// Where the replicas of a downstream service are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Upstream {
+    /// a single url, e.g. http://localhost:50001
+    Url(String),
+    /// the url of every replica, calls are balanced across the healthy ones
+    Urls(Vec<String>),
+    /// a url whose host is resolved periodically, calls are balanced across
+    /// the healthy addresses, e.g. `dns: http://user-stat.crm.svc:50001`
+    Dns { dns: String },
+}
+
+impl Upstream {
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        let schemes = &["http", "https"];
+        match self {
+            Upstream::Url(url) => checks.url(path, url, schemes),
+            Upstream::Urls(urls) => {
+                checks.check(path, !urls.is_empty(), "must not be empty");
+                for (i, url) in urls.iter().enumerate() {
+                    checks.url(&format!("{}.{}", path, i), url, schemes);
+                }
+            }
+            Upstream::Dns { dns } => checks.url(&format!("{}.dns", path), dns, schemes),
+        }
+    }
+}
+
+/// Endpoint of a target url, with the TLS domain to verify if not its host
+pub(crate) type MakeEndpoint = Box<dyn Fn(&str, Option<&str>) -> Result<Endpoint> + Send + Sync>;
+
+/// Targets of a balanced channel, probed every `interval`
+pub(crate) struct Discovery {
+    pub upstream: Upstream,
+    /// with the client settings applied
+    pub endpoint: MakeEndpoint,
+    pub interval: Duration,
+    pub resolve_every: Duration,
+}
+
+impl Discovery {
+    /// Channel balanced across the healthy targets, kept up to date by a task
+    /// which stops once the channel is dropped
+    pub fn spawn(self) -> Channel {
+        let (channel, tx) = Channel::balance_channel(CHANGES);
+        tokio::spawn(self.run(tx));
+        channel
+    }
+
+    async fn run(self, tx: Sender<Change<String, Endpoint>>) {
+        let mut targets = BTreeMap::new();
+        let mut active = BTreeSet::new();
+        let mut resolved_at: Option<Instant> = None;
+        let mut ticker = time::interval(self.interval);
+        loop {
+            ticker.tick().await;
+            if tx.is_closed() {
+                return;
+            }
+            if resolved_at.is_none_or(|at| at.elapsed() >= self.resolve_every) {
+                match self.targets().await {
+                    Ok(found) => {
+                        resolved_at = Some(Instant::now());
+                        // keep the probe channels of the known targets
+                        targets.retain(|url, _| found.contains_key(url));
+                        for (url, endpoint) in found {
+                            targets.entry(url).or_insert_with(|| Target::new(endpoint));
+                        }
+                    }
+                    Err(e) => warn!("failed to resolve {:?}: {:#}", self.upstream, e),
+                }
+            }
+
+            let healthy = probe(&targets, self.interval).await;
+            // with no healthy target calls fail fast rather than wait for one
+            let wanted = if healthy.is_empty() {
+                targets.keys().cloned().collect()
+            } else {
+                healthy
+            };
+            for url in active.difference(&wanted) {
+                info!("removing {} from the balanced targets", url);
+                if tx.send(Change::Remove(url.clone())).await.is_err() {
+                    return;
+                }
+            }
+            for url in wanted.difference(&active) {
+                info!("adding {} to the balanced targets", url);
+                let endpoint = targets[url].endpoint.clone();
+                if tx
+                    .send(Change::Insert(url.clone(), endpoint))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+            active = wanted;
+        }
+    }
+
+    /// Current targets by url
+    async fn targets(&self) -> Result<BTreeMap<String, Endpoint>> {
+        let urls = match &self.upstream {
+            Upstream::Url(url) => vec![(url.clone(), None)],
+            Upstream::Urls(urls) => urls.iter().map(|url| (url.clone(), None)).collect(),
+            Upstream::Dns { dns } => resolve(dns).await?,
+        };
+        urls.into_iter()
+            .map(|(url, domain)| {
+                let endpoint = (self.endpoint)(&url, domain.as_deref())?;
+                Ok((url, endpoint))
+            })
+            .collect()
+    }
+}
+
+struct Target {
+    endpoint: Endpoint,
+    /// own connection for the health checks, outside of the balancer
+    probe: Channel,
+}
+
+impl Target {
+    fn new(endpoint: Endpoint) -> Self {
+        Self {
+            probe: endpoint.connect_lazy(),
+            endpoint,
+        }
+    }
+}
+
+/// Urls of the targets whose health service reports serving within `timeout`
+async fn probe(targets: &BTreeMap<String, Target>, timeout: Duration) -> BTreeSet<String> {
+    let mut probes = JoinSet::new();
+    for (url, target) in targets {
+        let url = url.clone();
+        let check = check_remote(target.probe.clone());
+        probes.spawn(async move {
+            match time::timeout(timeout, check).await {
+                Ok(Ok(())) => Some(url),
+                Ok(Err(e)) => {
+                    warn!("{} failed its health check: {}", url, e);
+                    None
+                }
+                Err(_) => {
+                    warn!("{} failed its health check: timed out", url);
+                    None
+                }
+            }
+        });
+    }
+    let mut healthy = BTreeSet::new();
+    while let Some(ret) = probes.join_next().await {
+        healthy.extend(ret.ok().flatten());
+    }
+    healthy
+}
+
+/// One url per address of the host of `dns`, along with the host to verify
+/// the TLS certificates against
+async fn resolve(dns: &str) -> Result<Vec<(String, Option<String>)>> {
+    let url = Url::parse(dns)?;
+    let host = url.host_str().context("host is required")?;
+    let port = url.port_or_known_default().context("port is required")?;
+    let addrs = lookup_host((host, port)).await?;
+    let domain = host.trim_matches(|c| c == '[' || c == ']').to_string();
+    let mut urls = addrs
+        .map(|addr| (format!("{}://{}", url.scheme(), addr), Some(domain.clone())))
+        .collect::<Vec<_>>();
+    urls.sort();
+    urls.dedup();
+    Ok(urls)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+    };
+
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{transport::Server, Code};
+
+    use super::*;
+    use crate::{
+        pb::grpc::health::v1::{health_client::HealthClient, HealthCheckRequest},
+        HealthService,
+    };
+
+    const INTERVAL: Duration = Duration::from_millis(50);
+
+    /// Health server knowing `service`, serving while `up`
+    async fn serve(service: &'static str, up: Arc<AtomicBool>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let health = HealthService::builder()
+            .service(service)
+            .probe("up", move || {
+                let up = up.load(Ordering::SeqCst);
+                async move { up.then_some(()).ok_or_else(|| "down".to_string()) }
+            })
+            .interval(Duration::from_millis(10))
+            .build();
+        let server = Server::builder().add_service(health.into_server());
+        tokio::spawn(server.serve_with_incoming(TcpListenerStream::new(listener)));
+        addr
+    }
+
+    fn channel(upstream: Upstream) -> Channel {
+        Discovery {
+            upstream,
+            endpoint: Box::new(|url, _| Ok(Endpoint::from_shared(url.to_string())?)),
+            interval: INTERVAL,
+            resolve_every: INTERVAL,
+        }
+        .spawn()
+    }
+
+    /// Services answering 20 checks of `service`, i.e. those knowing it
+    async fn answered(channel: &Channel, service: &str) -> Vec<Code> {
+        let mut client = HealthClient::new(channel.clone());
+        let mut codes = Vec::new();
+        for _ in 0..20 {
+            let req = HealthCheckRequest {
+                service: service.to_string(),
+            };
+            let code = match client.check(req).await {
+                Ok(_) => Code::Ok,
+                Err(status) => status.code(),
+            };
+            codes.push(code);
+        }
+        codes
+    }
+
+    #[tokio::test]
+    async fn balanced_channel_should_skip_unhealthy_targets() {
+        let b_up = Arc::new(AtomicBool::new(true));
+        let a = serve("a", Arc::new(AtomicBool::new(true))).await;
+        let b = serve("b", b_up.clone()).await;
+        let channel = channel(Upstream::Urls(vec![
+            format!("http://{}", a),
+            format!("http://{}", b),
+        ]));
+        time::sleep(INTERVAL * 3).await;
+        // "a" is unknown to b, both get calls
+        let codes = answered(&channel, "a").await;
+        assert!(codes.contains(&Code::Ok), "{:?}", codes);
+        assert!(codes.contains(&Code::NotFound), "{:?}", codes);
+
+        b_up.store(false, Ordering::SeqCst);
+        time::sleep(INTERVAL * 4).await;
+        assert_eq!(answered(&channel, "a").await, vec![Code::Ok; 20]);
+
+        b_up.store(true, Ordering::SeqCst);
+        time::sleep(INTERVAL * 4).await;
+        assert!(answered(&channel, "a").await.contains(&Code::NotFound));
+    }
+
+    #[tokio::test]
+    async fn dns_upstream_should_resolve_the_host() {
+        let addr = serve("a", Arc::new(AtomicBool::new(true))).await;
+        let dns = format!("http://localhost:{}", addr.port());
+        let urls = resolve(&dns).await.unwrap();
+        let url = format!("http://{}", addr);
+        assert!(
+            urls.contains(&(url, Some("localhost".to_string()))),
+            "{:?}",
+            urls
+        );
+
+        // localhost may also resolve to ::1, where nothing listens
+        let channel = channel(Upstream::Dns { dns });
+        time::sleep(INTERVAL * 3).await;
+        assert_eq!(answered(&channel, "a").await, vec![Code::Ok; 20]);
+    }
+
+    #[test]
+    fn upstream_should_deserialize_from_any_form() {
+        let upstreams: Vec<Upstream> =
+            serde_yaml::from_str("- http://a:1\n- [http://a:1, http://b:1]\n- dns: http://a:1\n")
+                .unwrap();
+        assert!(matches!(&upstreams[0], Upstream::Url(url) if url == "http://a:1"));
+        assert!(matches!(&upstreams[1], Upstream::Urls(urls) if urls.len() == 2));
+        assert!(matches!(&upstreams[2], Upstream::Dns { dns } if dns == "http://a:1"));
+
+        let mut checks = Checks::default();
+        Upstream::Urls(vec![]).validate("server.metadata", &mut checks);
+        Upstream::Dns {
+            dns: "a:1".to_string(),
+        }
+        .validate("server.user_stats", &mut checks);
+        let err = checks.into_result().unwrap_err().to_string();
+        assert!(
+            err.contains("server.metadata: must not be empty"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("server.user_stats.dns: unsupported scheme a"),
+            "{}",
+            err
+        );
+    }
+}
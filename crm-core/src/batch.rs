@@ -0,0 +1,87 @@
+use std::{
+    mem,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+use tokio_stream::Stream;
+
+/// Group the items of a client stream into batches of `size`, e.g. to write
+/// them in one transaction each. A `size` of 0 counts as 1.
+pub fn batches<S, T, E>(stream: S, size: usize) -> Batches<S, T>
+where
+    S: Stream<Item = Result<T, E>>,
+{
+    let size = size.max(1);
+    Batches {
+        stream,
+        size,
+        batch: Vec::with_capacity(size),
+        done: false,
+    }
+}
+
+pin_project! {
+    /// Full batches as soon as they are, the last one possibly smaller once
+    /// the stream ends. An error of the stream is passed on right away, the
+    /// items batched so far stay for the next batch.
+    pub struct Batches<S, T> {
+        #[pin]
+        stream: S,
+        size: usize,
+        batch: Vec<T>,
+        done: bool,
+    }
+}
+
+impl<S, T, E> Stream for Batches<S, T>
+where
+    S: Stream<Item = Result<T, E>>,
+{
+    type Item = Result<Vec<T>, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            match ready!(this.stream.as_mut().poll_next(cx)) {
+                Some(Ok(item)) => {
+                    this.batch.push(item);
+                    if this.batch.len() >= *this.size {
+                        let next = Vec::with_capacity(*this.size);
+                        return Poll::Ready(Some(Ok(mem::replace(this.batch, next))));
+                    }
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    *this.done = true;
+                    if this.batch.is_empty() {
+                        return Poll::Ready(None);
+                    }
+                    return Poll::Ready(Some(Ok(mem::take(this.batch))));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn batches_should_flush_the_rest_at_the_end() {
+        let items = tokio_stream::iter((1..=5).map(Ok::<_, ()>));
+        let batched: Vec<_> = batches(items, 2).collect().await;
+        assert_eq!(batched, vec![Ok(vec![1, 2]), Ok(vec![3, 4]), Ok(vec![5])]);
+
+        let items = tokio_stream::iter(vec![Ok(1), Err("broken"), Ok(2)]);
+        let batched: Vec<_> = batches(items, 0).collect().await;
+        assert_eq!(batched, vec![Ok(vec![1]), Err("broken"), Ok(vec![2])]);
+    }
+}
@@ -0,0 +1,385 @@
+use std::{
+    future::Future,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tokio::time::{self, Instant};
+use tonic::{
+    transport::{Channel, Endpoint},
+    Code, Response, Status,
+};
+use tracing::warn;
+
+use crate::{
+    balance::{Discovery, Upstream},
+    config::Checks,
+    metrics::{self, Counter},
+    TlsConfig,
+};
+
+static RETRIES: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "grpc_client_retries_total",
+        "Calls to a downstream service attempted again after failing",
+        &["downstream"],
+    )
+});
+static REJECTED: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "grpc_client_rejected_total",
+        "Calls to a downstream service rejected by its open circuit breaker",
+        &["downstream"],
+    )
+});
+
+/// How the calls to a downstream service are made, unset fields keep their default
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    /// deadline of each attempt, in milliseconds
+    pub timeout_ms: u64,
+    /// deadline to establish a connection, in milliseconds
+    pub connect_timeout_ms: u64,
+    /// attempts after the first one of an idempotent call that failed with
+    /// unavailable or deadline exceeded
+    pub retries: u32,
+    /// delay before the first retry in milliseconds, doubled on each retry
+    pub backoff_ms: u64,
+    /// consecutive failures opening the circuit breaker
+    pub failure_threshold: u32,
+    /// seconds an open breaker rejects calls before letting one through
+    pub open_secs: u64,
+    /// seconds between the health checks of the replicas of a balanced upstream
+    pub health_check_secs: u64,
+    /// seconds between the resolutions of a dns upstream
+    pub resolve_secs: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 10_000,
+            connect_timeout_ms: 1_000,
+            retries: 2,
+            backoff_ms: 100,
+            failure_threshold: 5,
+            open_secs: 10,
+            health_check_secs: 5,
+            resolve_secs: 30,
+        }
+    }
+}
+
+impl ClientConfig {
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        for (field, value) in [
+            ("timeout_ms", self.timeout_ms),
+            ("connect_timeout_ms", self.connect_timeout_ms),
+            ("failure_threshold", self.failure_threshold as u64),
+            ("health_check_secs", self.health_check_secs),
+            ("resolve_secs", self.resolve_secs),
+        ] {
+            checks.check(&format!("{}.{}", path, field), value > 0, "must not be 0");
+        }
+    }
+
+    /// Channel to `upstream`, connected on first use so the service starts
+    /// even when the downstream one is down. Calls are balanced across the
+    /// replicas of an upstream with several urls or a dns name, skipping the
+    /// ones failing their health checks.
+    pub fn channel(&self, upstream: &Upstream, tls: Option<&TlsConfig>) -> anyhow::Result<Channel> {
+        if let Upstream::Url(url) = upstream {
+            return Ok(self.endpoint(url, tls, None)?.connect_lazy());
+        }
+        let (config, tls) = (self.clone(), tls.cloned());
+        let discovery = Discovery {
+            upstream: upstream.clone(),
+            endpoint: Box::new(move |url, domain| config.endpoint(url, tls.as_ref(), domain)),
+            interval: Duration::from_secs(self.health_check_secs),
+            resolve_every: Duration::from_secs(self.resolve_secs),
+        };
+        Ok(discovery.spawn())
+    }
+
+    /// Endpoint of `url`, its certificate checked against `domain` rather than
+    /// the host of `url` when set
+    fn endpoint(
+        &self,
+        url: &str,
+        tls: Option<&TlsConfig>,
+        domain: Option<&str>,
+    ) -> anyhow::Result<Endpoint> {
+        let mut endpoint = Endpoint::from_shared(url.to_string())?
+            .connect_timeout(Duration::from_millis(self.connect_timeout_ms));
+        if let Some(tls) = tls {
+            let mut tls = tls.client_config()?;
+            if let Some(domain) = domain {
+                tls = tls.domain_name(domain);
+            }
+            endpoint = endpoint.tls_config(tls)?;
+        }
+        Ok(endpoint)
+    }
+}
+
+/// Client of a downstream service, failing fast with `unavailable` while the
+/// service keeps failing instead of piling up calls on it
+#[derive(Debug, Clone)]
+pub struct Downstream<C> {
+    name: &'static str,
+    client: C,
+    config: ClientConfig,
+    breaker: Arc<Breaker>,
+}
+
+impl<C: Clone> Downstream<C> {
+    pub fn new(name: &'static str, client: C, config: &ClientConfig) -> Self {
+        Self {
+            name,
+            client,
+            config: config.clone(),
+            breaker: Arc::new(Breaker::new(
+                config.failure_threshold,
+                Duration::from_secs(config.open_secs),
+            )),
+        }
+    }
+
+    /// Call `f` once, for calls that must not be repeated
+    pub async fn call<R, T, F, Fut>(&self, request: R, f: F) -> Result<Response<T>, Status>
+    where
+        F: FnOnce(C, R) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        if !self.breaker.acquire() {
+            REJECTED.inc(&[self.name]);
+            return Err(Status::unavailable(format!("{} is unavailable", self.name)));
+        }
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let ret = match time::timeout(timeout, f(self.client.clone(), request)).await {
+            Ok(ret) => ret,
+            Err(_) => Err(Status::deadline_exceeded(format!(
+                "{} did not answer in time",
+                self.name
+            ))),
+        };
+        match &ret {
+            Err(status) if is_failure(status.code()) => {
+                if self.breaker.failed() {
+                    warn!("{} circuit opened: {}", self.name, status.message());
+                }
+            }
+            _ => self.breaker.succeeded(),
+        }
+        ret
+    }
+
+    /// Call `f`, again with backoff while it fails with a transient error,
+    /// for idempotent calls only
+    pub async fn retry<R, T, F, Fut>(&self, request: R, f: F) -> Result<Response<T>, Status>
+    where
+        R: Clone,
+        F: Fn(C, R) -> Fut,
+        Fut: Future<Output = Result<Response<T>, Status>>,
+    {
+        let mut backoff = Duration::from_millis(self.config.backoff_ms);
+        for _ in 0..self.config.retries {
+            match self.call(request.clone(), &f).await {
+                Err(status) if is_failure(status.code()) && self.breaker.is_closed() => {
+                    RETRIES.inc(&[self.name]);
+                    // jittered, so that callers failing together don't retry together
+                    let jitter = rand::thread_rng().gen_range(0.5..=1.0);
+                    time::sleep(backoff.mul_f64(jitter)).await;
+                    backoff *= 2;
+                }
+                ret => return ret,
+            }
+        }
+        self.call(request, f).await
+    }
+}
+
+/// Codes telling the service is unreachable or overloaded, rather than
+/// rejecting the request itself
+fn is_failure(code: Code) -> bool {
+    matches!(code, Code::Unavailable | Code::DeadlineExceeded)
+}
+
+/// Opens after `threshold` consecutive failures, then lets one call through
+/// every `open_for` to probe the service, closing again on its success
+#[derive(Debug)]
+struct Breaker {
+    threshold: u32,
+    open_for: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+}
+
+impl Breaker {
+    fn new(threshold: u32, open_for: Duration) -> Self {
+        Self {
+            threshold,
+            open_for,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Whether a call may go through
+    fn acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed { .. } => true,
+            State::Open { until } if Instant::now() >= until => {
+                // the probe, the others keep being rejected until it succeeds
+                *state = State::Open {
+                    until: Instant::now() + self.open_for,
+                };
+                true
+            }
+            State::Open { .. } => false,
+        }
+    }
+
+    fn succeeded(&self) {
+        *self.state.lock().unwrap() = State::Closed { failures: 0 };
+    }
+
+    /// Record a failure, returns whether it opened the breaker
+    fn failed(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            State::Closed { failures } => failures + 1,
+            State::Open { .. } => self.threshold,
+        };
+        let opened = matches!(*state, State::Closed { .. }) && failures >= self.threshold;
+        *state = if failures >= self.threshold {
+            State::Open {
+                until: Instant::now() + self.open_for,
+            }
+        } else {
+            State::Closed { failures }
+        };
+        opened
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), State::Closed { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::pb::grpc::health::v1::{health_client::HealthClient, HealthCheckRequest};
+
+    fn config() -> ClientConfig {
+        ClientConfig {
+            timeout_ms: 50,
+            retries: 2,
+            backoff_ms: 1,
+            failure_threshold: 3,
+            open_secs: 60,
+            ..Default::default()
+        }
+    }
+
+    /// Answers with the next code of `codes`, `Ok` once they are exhausted
+    async fn answer(calls: &AtomicU32, codes: &[Code]) -> Result<Response<u32>, Status> {
+        let n = calls.fetch_add(1, Ordering::SeqCst);
+        match codes.get(n as usize) {
+            Some(code) => Err(Status::new(*code, "failed")),
+            None => Ok(Response::new(n)),
+        }
+    }
+
+    #[tokio::test]
+    async fn retry_should_only_repeat_transient_failures() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let downstream = Downstream::new("test", calls, &config());
+
+        let codes = [Code::Unavailable, Code::DeadlineExceeded];
+        let f =
+            |calls: Arc<AtomicU32>, codes: Vec<Code>| async move { answer(&calls, &codes).await };
+        let res = downstream.retry(codes.to_vec(), f).await.unwrap();
+        assert_eq!(res.into_inner(), 2);
+
+        downstream.client.store(0, Ordering::SeqCst);
+        let err = downstream.retry(vec![Code::NotFound], f).await.unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        assert_eq!(downstream.client.load(Ordering::SeqCst), 1);
+
+        // a single attempt for calls that aren't idempotent
+        downstream.client.store(0, Ordering::SeqCst);
+        let err = downstream
+            .call(vec![Code::Unavailable], f)
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+        assert_eq!(downstream.client.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn call_should_time_out() {
+        let downstream = Downstream::new("test", (), &config());
+        let err = downstream
+            .call((), |_, _| async {
+                time::sleep(Duration::from_secs(1)).await;
+                Ok(Response::new(()))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::DeadlineExceeded);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn breaker_should_open_after_consecutive_failures() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let downstream = Downstream::new("test", calls.clone(), &config());
+        let fail = |calls: Arc<AtomicU32>, _| async move {
+            answer(&calls, &[Code::Unavailable; 10]).await
+        };
+
+        // 3 attempts open the breaker, the rest fails fast without calling
+        let err = downstream.retry((), fail).await.unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        let err = downstream.retry((), fail).await.unwrap_err();
+        assert_eq!(err.message(), "test is unavailable");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // one probe once open_secs elapsed, which closes it on success
+        time::advance(Duration::from_secs(60)).await;
+        let ok = |calls: Arc<AtomicU32>, _| async move { answer(&calls, &[]).await };
+        downstream.call((), ok).await.unwrap();
+        downstream.call((), ok).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn lazy_channel_should_fail_with_unavailable() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let channel = config().channel(&Upstream::Url(url), None).unwrap();
+        let downstream = Downstream::new("health", HealthClient::new(channel), &config());
+        let err = downstream
+            .retry(
+                HealthCheckRequest::default(),
+                |mut client, req| async move { client.check(req).await },
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::Unavailable);
+    }
+}
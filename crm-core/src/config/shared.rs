@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Result;
+use tokio::{task::JoinHandle, time};
+use tracing::{info, warn};
+
+use super::{config_file, resolve, Config, Flags, ARGS};
+
+/// how often `SharedConfig::watch` checks the config file for changes
+pub const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A config swapped as a whole on reload, readers keep the snapshot they got.
+/// Only the fields read per request pick up a new value, e.g. ports and
+/// downstream urls still need a restart.
+#[derive(Debug)]
+pub struct SharedConfig<T> {
+    current: RwLock<Arc<T>>,
+    /// sources the config was first resolved from
+    env: HashMap<String, String>,
+    args: Vec<String>,
+}
+
+impl<T: Config> SharedConfig<T> {
+    /// Share `config`, reloaded from the env and flags of the running binary
+    pub fn new(config: T) -> Self {
+        let args = ARGS.get().cloned().unwrap_or_default();
+        Self::with_sources(config, std::env::vars().collect(), args)
+    }
+
+    /// Share `config`, reloaded from `env` and `args` instead of the ones of the running binary
+    pub fn with_sources(config: T, env: HashMap<String, String>, args: Vec<String>) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(config)),
+            env,
+            args,
+        }
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Resolve the config again, it only replaces the current one when valid
+    pub fn reload(&self) -> Result<()> {
+        let (config, _) = resolve::<T>(self.env.clone(), self.args.clone())?;
+        *self.current.write().unwrap() = Arc::new(config);
+        Ok(())
+    }
+
+    /// Reload whenever the config file changes, checked every `interval`.
+    /// The task stops once the config is dropped.
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()>
+    where
+        T: Send + Sync + 'static,
+    {
+        let shared = Arc::downgrade(self);
+        let mut last = self.modified();
+        tokio::spawn(async move {
+            let mut ticker = time::interval(interval);
+            loop {
+                ticker.tick().await;
+                let Some(shared) = shared.upgrade() else {
+                    return;
+                };
+                let modified = shared.modified();
+                if modified == last {
+                    continue;
+                }
+                last = modified;
+                match shared.reload() {
+                    Ok(()) => info!("{} config reloaded", T::NAME),
+                    Err(e) => warn!(
+                        "{} config not reloaded, keeping the current one: {:#}",
+                        T::NAME,
+                        e
+                    ),
+                }
+            }
+        })
+    }
+
+    fn file(&self) -> Option<PathBuf> {
+        let flags = Flags::parse(self.args.clone()).ok()?;
+        config_file::<T>(&self.env, &flags).ok()?
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        fs::metadata(self.file()?).ok()?.modified().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::tests::{file, TestConfig};
+
+    fn shared(path: &Path) -> Arc<SharedConfig<TestConfig>> {
+        let env = HashMap::from([(
+            "TEST_CONFIG".to_string(),
+            path.to_str().unwrap().to_string(),
+        )]);
+        let (config, _) = resolve::<TestConfig>(env.clone(), vec![]).unwrap();
+        Arc::new(SharedConfig::with_sources(config, env, vec![]))
+    }
+
+    #[test]
+    fn reload_should_keep_the_current_config_when_invalid() {
+        let path = file("port: 2\ndb_url: postgres://localhost/db\n");
+        let shared = shared(&path);
+        let before = shared.get();
+
+        fs::write(&path, "port: 3\ndb_url: postgres://localhost/db\n").unwrap();
+        shared.reload().unwrap();
+        assert_eq!(shared.get().port, 3);
+        // snapshots taken before are left untouched
+        assert_eq!(before.port, 2);
+
+        fs::write(&path, "port: 0\ndb_url: postgres://localhost/db\n").unwrap();
+        assert!(shared.reload().is_err());
+        assert_eq!(shared.get().port, 3);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn watch_should_reload_on_change() {
+        let path = file("port: 2\ndb_url: postgres://localhost/db\n");
+        let shared = shared(&path);
+        let watcher = shared.watch(Duration::from_millis(10));
+
+        fs::write(&path, "port: 4\ndb_url: postgres://localhost/db\n").unwrap();
+        let start = time::Instant::now();
+        while shared.get().port != 4 {
+            assert!(start.elapsed() < Duration::from_secs(2), "not reloaded");
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        drop(shared);
+        time::timeout(Duration::from_secs(1), watcher)
+            .await
+            .expect("watcher should stop with the config")
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+}
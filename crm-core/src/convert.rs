@@ -0,0 +1,59 @@
+use chrono::{DateTime, TimeZone, Utc};
+use prost_types::Timestamp;
+
+/// chrono to protobuf time, for the timestamps of the responses
+pub trait ToTimestamp {
+    fn to_timestamp(&self) -> Timestamp;
+}
+
+/// protobuf to chrono time, None if out of range or the nanos are negative
+pub trait ToUtc {
+    fn to_utc(&self) -> Option<DateTime<Utc>>;
+}
+
+impl<Tz: TimeZone> ToTimestamp for DateTime<Tz> {
+    fn to_timestamp(&self) -> Timestamp {
+        Timestamp {
+            seconds: self.timestamp(),
+            nanos: self.timestamp_subsec_nanos() as i32,
+        }
+    }
+}
+
+impl ToUtc for Timestamp {
+    fn to_utc(&self) -> Option<DateTime<Utc>> {
+        let nanos = u32::try_from(self.nanos).ok()?;
+        Utc.timestamp_opt(self.seconds, nanos).single()
+    }
+}
+
+/// The current time as a protobuf timestamp
+pub fn now() -> Timestamp {
+    Utc::now().to_timestamp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_should_round_trip() {
+        let dt = Utc.with_ymd_and_hms(2024, 5, 7, 12, 30, 0).unwrap()
+            + chrono::Duration::nanoseconds(42);
+        let ts = dt.to_timestamp();
+        assert_eq!(ts.seconds, 1_715_085_000);
+        assert_eq!(ts.nanos, 42);
+        assert_eq!(ts.to_utc(), Some(dt));
+
+        let negative = Timestamp {
+            seconds: 0,
+            nanos: -1,
+        };
+        assert_eq!(negative.to_utc(), None);
+        let huge = Timestamp {
+            seconds: i64::MAX,
+            nanos: 0,
+        };
+        assert_eq!(huge.to_utc(), None);
+    }
+}
@@ -0,0 +1,267 @@
+use std::{collections::HashMap, error::Error as StdError};
+
+use prost::Message;
+use prost_types::Any;
+use tonic::{Code, Request, Status};
+use tracing::warn;
+
+use crate::{
+    pb::google::rpc::{self, BadRequest, ErrorInfo},
+    validate::{self, Validate},
+};
+
+pub use crate::pb::google::rpc::bad_request::FieldViolation;
+
+const ERROR_INFO: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+const BAD_REQUEST: &str = "type.googleapis.com/google.rpc.BadRequest";
+
+/// Error of a service, sent to the caller as a status carrying a
+/// `google.rpc.ErrorInfo`, and a `google.rpc.BadRequest` listing the invalid
+/// fields if any.
+///
+/// The caller only gets the `Display` of the error, the sources of the
+/// internal ones are logged instead.
+pub trait ServiceError: StdError + Sized {
+    /// Service the reasons belong to, e.g. `user-stat`
+    const DOMAIN: &'static str;
+
+    fn code(&self) -> Code;
+
+    /// UPPER_SNAKE_CASE identifier of the error, stable for callers to match on
+    fn reason(&self) -> &'static str;
+
+    /// Invalid fields of the request
+    fn violations(&self) -> Vec<FieldViolation> {
+        Vec::new()
+    }
+
+    /// Context of the error, e.g. the id which wasn't found
+    fn metadata(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Message of `request`, checked before its handler runs
+    #[allow(clippy::result_large_err)]
+    fn valid<T: Validate>(request: Request<T>) -> Result<T, Status> {
+        validate::valid(Self::DOMAIN, request)
+    }
+
+    fn into_status(self) -> Status {
+        let code = self.code();
+        if matches!(code, Code::Internal | Code::Unknown | Code::DataLoss) {
+            warn!("{}: {}", Self::DOMAIN, chain(&self));
+        }
+        let info = ErrorInfo {
+            reason: self.reason().to_string(),
+            domain: Self::DOMAIN.to_string(),
+            metadata: self.metadata(),
+        };
+        status(code, self.to_string(), info, self.violations())
+    }
+}
+
+impl FieldViolation {
+    pub fn new(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// `invalid_argument` for a request of `domain` with invalid fields
+pub fn bad_request(domain: &str, violations: Vec<FieldViolation>) -> Status {
+    let message = violations
+        .iter()
+        .map(|v| format!("{}: {}", v.field, v.description))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let info = ErrorInfo {
+        reason: "INVALID_ARGUMENT".to_string(),
+        domain: domain.to_string(),
+        metadata: HashMap::new(),
+    };
+    status(Code::InvalidArgument, message, info, violations)
+}
+
+/// Status whose details are `info` and the `violations`, if any
+pub fn status(
+    code: Code,
+    message: impl Into<String>,
+    info: ErrorInfo,
+    violations: Vec<FieldViolation>,
+) -> Status {
+    let message = message.into();
+    let mut details = vec![Any {
+        type_url: ERROR_INFO.to_string(),
+        value: info.encode_to_vec(),
+    }];
+    if !violations.is_empty() {
+        let bad_request = BadRequest {
+            field_violations: violations,
+        };
+        details.push(Any {
+            type_url: BAD_REQUEST.to_string(),
+            value: bad_request.encode_to_vec(),
+        });
+    }
+    let encoded = rpc::Status {
+        code: code as i32,
+        message: message.clone(),
+        details,
+    }
+    .encode_to_vec();
+    Status::with_details(code, message, encoded.into())
+}
+
+/// Details of a status sent by one of the services
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ErrorDetails {
+    pub info: Option<ErrorInfo>,
+    pub violations: Vec<FieldViolation>,
+}
+
+impl ErrorDetails {
+    /// Empty if the status has no details, or none of a known type
+    pub fn from_status(status: &Status) -> Self {
+        let mut details = Self::default();
+        let Ok(decoded) = rpc::Status::decode(status.details()) else {
+            return details;
+        };
+        for any in decoded.details {
+            match any.type_url.as_str() {
+                ERROR_INFO => details.info = ErrorInfo::decode(any.value.as_slice()).ok(),
+                BAD_REQUEST => {
+                    if let Ok(bad_request) = BadRequest::decode(any.value.as_slice()) {
+                        details.violations.extend(bad_request.field_violations);
+                    }
+                }
+                _ => {}
+            }
+        }
+        details
+    }
+
+    pub fn reason(&self) -> Option<&str> {
+        self.info.as_ref().map(|info| info.reason.as_str())
+    }
+
+    /// Paths of the invalid fields
+    pub fn fields(&self) -> Vec<&str> {
+        self.violations.iter().map(|v| v.field.as_str()).collect()
+    }
+}
+
+/// `error: source: source of source...`
+fn chain(e: &dyn StdError) -> String {
+    let mut chain = e.to_string();
+    let mut source = e.source();
+    while let Some(e) = source {
+        chain.push_str(": ");
+        chain.push_str(&e.to_string());
+        source = e.source();
+    }
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    enum Error {
+        #[error("name is required")]
+        MissingName,
+        #[error("item {0} not found")]
+        NotFound(u32),
+        #[error("failed to fetch item")]
+        Db(#[source] std::io::Error),
+    }
+
+    impl ServiceError for Error {
+        const DOMAIN: &'static str = "test";
+
+        fn code(&self) -> Code {
+            match self {
+                Error::MissingName => Code::InvalidArgument,
+                Error::NotFound(_) => Code::NotFound,
+                Error::Db(_) => Code::Internal,
+            }
+        }
+
+        fn reason(&self) -> &'static str {
+            match self {
+                Error::MissingName => "INVALID_ARGUMENT",
+                Error::NotFound(_) => "ITEM_NOT_FOUND",
+                Error::Db(_) => "INTERNAL",
+            }
+        }
+
+        fn violations(&self) -> Vec<FieldViolation> {
+            match self {
+                Error::MissingName => vec![FieldViolation::new("name", "is required")],
+                _ => vec![],
+            }
+        }
+
+        fn metadata(&self) -> HashMap<String, String> {
+            match self {
+                Error::NotFound(id) => HashMap::from([("id".to_string(), id.to_string())]),
+                _ => HashMap::new(),
+            }
+        }
+    }
+
+    #[test]
+    fn service_error_should_carry_error_info_and_violations() {
+        let status = Error::MissingName.into_status();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(status.message(), "name is required");
+        let details = ErrorDetails::from_status(&status);
+        let info = details.info.as_ref().unwrap();
+        assert_eq!(info.domain, "test");
+        assert_eq!(details.reason(), Some("INVALID_ARGUMENT"));
+        assert_eq!(
+            details.violations,
+            vec![FieldViolation::new("name", "is required")]
+        );
+
+        let status = Error::NotFound(7).into_status();
+        assert_eq!(status.code(), Code::NotFound);
+        let details = ErrorDetails::from_status(&status);
+        assert_eq!(details.reason(), Some("ITEM_NOT_FOUND"));
+        assert_eq!(details.info.unwrap().metadata["id"], "7");
+        assert!(details.violations.is_empty());
+    }
+
+    #[test]
+    fn internal_error_should_hide_its_source() {
+        let source = std::io::Error::other("SELECT * FROM items");
+        let status = Error::Db(source).into_status();
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "failed to fetch item");
+        assert!(!format!("{:?}", status).contains("SELECT"));
+    }
+
+    #[test]
+    fn bad_request_should_list_the_fields() {
+        let status = bad_request(
+            "test",
+            vec![
+                FieldViolation::new("to", "must not be empty"),
+                FieldViolation::new("from", "invalid email"),
+            ],
+        );
+        assert_eq!(status.code(), Code::InvalidArgument);
+        assert_eq!(
+            status.message(),
+            "to: must not be empty, from: invalid email"
+        );
+        let details = ErrorDetails::from_status(&status);
+        assert_eq!(details.fields(), vec!["to", "from"]);
+        assert_eq!(
+            ErrorDetails::from_status(&Status::internal("x")),
+            ErrorDetails::default()
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::{future::Future, pin::Pin, sync::Arc, time::Duration};
+
+use tokio::{sync::watch, time};
+use tokio_stream::{wrappers::WatchStream, Stream, StreamExt};
+use tonic::{transport::Channel, Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::pb::grpc::health::v1::{
+    health_check_response::ServingStatus,
+    health_client::HealthClient,
+    health_server::{Health, HealthServer},
+    HealthCheckRequest, HealthCheckResponse,
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(2);
+
+type ProbeFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+type Probe = Box<dyn Fn() -> ProbeFuture + Send + Sync>;
+type WatchResponseStream = Pin<Box<dyn Stream<Item = Result<HealthCheckResponse, Status>> + Send>>;
+
+/// `grpc.health.v1.Health` backed by probes of the service's real dependencies.
+/// Every registered service, and the server as a whole (empty service name),
+/// is SERVING only while all probes pass.
+#[derive(Debug, Clone)]
+pub struct HealthService {
+    services: Arc<Vec<String>>,
+    status: watch::Receiver<ServingStatus>,
+}
+
+pub struct HealthBuilder {
+    services: Vec<String>,
+    probes: Vec<(String, Probe)>,
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl HealthService {
+    pub fn builder() -> HealthBuilder {
+        HealthBuilder {
+            services: vec![],
+            probes: vec![],
+            interval: DEFAULT_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn into_server(self) -> HealthServer<Self> {
+        HealthServer::new(self)
+    }
+
+    fn status(&self, service: &str) -> Option<ServingStatus> {
+        if service.is_empty() || self.services.iter().any(|s| s == service) {
+            Some(*self.status.borrow())
+        } else {
+            None
+        }
+    }
+}
+
+impl HealthBuilder {
+    /// Fully qualified name of a served grpc service, e.g. `user_stats.UserStats`
+    pub fn service(mut self, name: impl Into<String>) -> Self {
+        self.services.push(name.into());
+        self
+    }
+
+    /// A dependency the service can't serve without, checked every `interval`
+    pub fn probe<F, Fut>(mut self, name: impl Into<String>, probe: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let probe: Probe = Box::new(move || Box::pin(probe()));
+        self.probes.push((name.into(), probe));
+        self
+    }
+
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Max time a probe may take before it counts as failed
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Start probing in the background, must be called within a tokio runtime.
+    /// The server is NOT_SERVING until the first round of probes passed.
+    pub fn build(self) -> HealthService {
+        let initial = if self.probes.is_empty() {
+            ServingStatus::Serving
+        } else {
+            ServingStatus::NotServing
+        };
+        let (tx, rx) = watch::channel(initial);
+        if !self.probes.is_empty() {
+            tokio::spawn(run_probes(self.probes, self.interval, self.timeout, tx));
+        }
+        HealthService {
+            services: Arc::new(self.services),
+            status: rx,
+        }
+    }
+}
+
+async fn run_probes(
+    probes: Vec<(String, Probe)>,
+    interval: Duration,
+    timeout: Duration,
+    tx: watch::Sender<ServingStatus>,
+) {
+    let mut ticker = time::interval(interval);
+    ticker.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+    // stop once every HealthService clone is dropped
+    while !tx.is_closed() {
+        ticker.tick().await;
+        let mut status = ServingStatus::Serving;
+        for (name, probe) in &probes {
+            let ret = match time::timeout(timeout, probe()).await {
+                Ok(ret) => ret,
+                Err(_) => Err(format!("timed out after {:?}", timeout)),
+            };
+            if let Err(e) = ret {
+                warn!("health probe {} failed: {}", name, e);
+                status = ServingStatus::NotServing;
+            }
+        }
+        tx.send_if_modified(|current| {
+            if *current == status {
+                return false;
+            }
+            info!("health status changed to {:?}", status);
+            *current = status;
+            true
+        });
+    }
+}
+
+/// Probe a downstream grpc server through its own health service
+pub async fn check_remote(channel: Channel) -> Result<(), String> {
+    let req = HealthCheckRequest {
+        service: String::new(),
+    };
+    let res = HealthClient::new(channel)
+        .check(req)
+        .await
+        .map_err(|e| e.message().to_string())?;
+    match res.into_inner().status() {
+        ServingStatus::Serving => Ok(()),
+        status => Err(format!("downstream is {:?}", status)),
+    }
+}
+
+/// Probe the database of a service with a trivial query
+#[cfg(feature = "postgres")]
+pub async fn check_postgres(pool: sqlx::PgPool) -> Result<(), String> {
+    sqlx::query("SELECT 1")
+        .execute(&pool)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[tonic::async_trait]
+impl Health for HealthService {
+    type WatchStream = WatchResponseStream;
+
+    async fn check(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<HealthCheckResponse>, Status> {
+        let service = request.into_inner().service;
+        let Some(status) = self.status(&service) else {
+            return Err(Status::not_found(format!("unknown service {}", service)));
+        };
+        Ok(Response::new(HealthCheckResponse {
+            status: status as i32,
+        }))
+    }
+
+    async fn watch(
+        &self,
+        request: Request<HealthCheckRequest>,
+    ) -> Result<Response<Self::WatchStream>, Status> {
+        let service = request.into_inner().service;
+        let stream: WatchResponseStream = match self.status(&service) {
+            Some(_) => Box::pin(
+                WatchStream::new(self.status.clone())
+                    .map(|status| HealthCheckResponse {
+                        status: status as i32,
+                    })
+                    .map(Ok),
+            ),
+            // per spec the call stays open for an unknown service
+            None => Box::pin(
+                tokio_stream::once(Ok(HealthCheckResponse {
+                    status: ServingStatus::ServiceUnknown as i32,
+                }))
+                .chain(tokio_stream::pending()),
+            ),
+        };
+        Ok(Response::new(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+
+    async fn check(svc: &HealthService, service: &str) -> Result<ServingStatus, Status> {
+        let req = Request::new(HealthCheckRequest {
+            service: service.to_string(),
+        });
+        let res = Health::check(svc, req).await?;
+        Ok(res.into_inner().status())
+    }
+
+    async fn next(stream: &mut WatchResponseStream) -> ServingStatus {
+        time::timeout(Duration::from_secs(1), stream.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn health_without_probes_should_be_serving() {
+        let svc = HealthService::builder()
+            .service("user_stats.UserStats")
+            .build();
+        assert_eq!(check(&svc, "").await.unwrap(), ServingStatus::Serving);
+        assert_eq!(
+            check(&svc, "user_stats.UserStats").await.unwrap(),
+            ServingStatus::Serving
+        );
+        let err = check(&svc, "unknown.Service").await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn health_should_follow_probes() {
+        let up = Arc::new(AtomicBool::new(true));
+        let probe_up = up.clone();
+        let svc = HealthService::builder()
+            .probe("db", move || {
+                let up = probe_up.load(Ordering::SeqCst);
+                async move {
+                    if up {
+                        Ok(())
+                    } else {
+                        Err("connection refused".to_string())
+                    }
+                }
+            })
+            .interval(Duration::from_millis(10))
+            .build();
+        let req = Request::new(HealthCheckRequest::default());
+        let mut stream = Health::watch(&svc, req).await.unwrap().into_inner();
+
+        assert_eq!(next(&mut stream).await, ServingStatus::NotServing);
+        assert_eq!(next(&mut stream).await, ServingStatus::Serving);
+        up.store(false, Ordering::SeqCst);
+        assert_eq!(next(&mut stream).await, ServingStatus::NotServing);
+    }
+
+    #[tokio::test]
+    async fn slow_probe_should_fail() {
+        let svc = HealthService::builder()
+            .probe("slow", || async {
+                time::sleep(Duration::from_secs(10)).await;
+                Ok(())
+            })
+            .timeout(Duration::from_millis(10))
+            .interval(Duration::from_millis(10))
+            .build();
+        time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(check(&svc, "").await.unwrap(), ServingStatus::NotServing);
+    }
+}
@@ -0,0 +1,30 @@
+pub mod balance;
+pub mod batch;
+pub mod client;
+pub mod config;
+pub mod convert;
+pub mod error;
+pub mod health;
+pub mod listen;
+pub mod metrics;
+pub mod pb;
+pub mod reflection;
+pub mod server;
+pub mod shutdown;
+pub mod telemetry;
+pub mod tls;
+pub mod validate;
+
+pub use balance::Upstream;
+pub use client::{ClientConfig, Downstream};
+pub use config::{Checks, Config};
+pub use convert::{ToTimestamp, ToUtc};
+pub use error::{ErrorDetails, FieldViolation, ServiceError};
+pub use health::HealthService;
+pub use listen::{GrpcServer, ListenConfig};
+pub use reflection::ReflectionService;
+pub use server::{AuthConfig, Bootstrap, ServerConfig, ServiceConfig};
+pub use shutdown::Shutdown;
+pub use telemetry::{Telemetry, TelemetryConfig};
+pub use tls::TlsConfig;
+pub use validate::{Validate, Violations};
@@ -0,0 +1,301 @@
+use std::{
+    fs,
+    future::Future,
+    net::{IpAddr, Ipv6Addr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{Context as _, Result};
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, UnixListener};
+use tokio_stream::wrappers::{TcpListenerStream, UnixListenerStream};
+use tonic::{
+    body::BoxBody,
+    codegen::{Bytes, StdError},
+    service::Routes,
+    transport::{server::Router, Server},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::info;
+
+use crate::{
+    config::Checks,
+    tls::{AllowedCallers, TlsConfig},
+};
+
+/// How a grpc server listens and serves its connections, flattened into the
+/// `server` section of each service config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ListenConfig {
+    /// address the grpc and metrics servers bind, `::` or `0.0.0.0` to be
+    /// reachable from other hosts
+    pub host: IpAddr,
+    /// serve grpc on this unix socket instead of `host:port`
+    pub uds: Option<PathBuf>,
+    /// seconds between the HTTP/2 pings sent on idle connections, none when 0
+    pub keepalive_secs: u64,
+    /// seconds a ping gets to be acknowledged before the connection is closed
+    pub keepalive_timeout_secs: u64,
+    /// concurrent streams allowed on each connection, unlimited when unset
+    pub max_concurrent_streams: Option<u32>,
+    /// max size in bytes of a request or response message
+    pub max_message_size: usize,
+    /// accept zstd compressed requests, and compress the responses of the
+    /// clients accepting it
+    pub zstd: bool,
+}
+
+impl Default for ListenConfig {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::V6(Ipv6Addr::LOCALHOST),
+            uds: None,
+            keepalive_secs: 60,
+            keepalive_timeout_secs: 20,
+            max_concurrent_streams: None,
+            max_message_size: 4 * 1024 * 1024,
+            zstd: false,
+        }
+    }
+}
+
+/// Options of the servers generated by tonic, implemented with `grpc_server!`
+pub trait GrpcServer: Sized {
+    /// Apply the message size limit and compression of `listen`
+    fn with_options(self, listen: &ListenConfig) -> Self;
+}
+
+/// Implement `GrpcServer` for a server generated by tonic
+#[macro_export]
+macro_rules! grpc_server {
+    ($($server:ident)::+) => {
+        impl<T> $crate::listen::GrpcServer for $($server)::+<T> {
+            fn with_options(self, listen: &$crate::listen::ListenConfig) -> Self {
+                let server = self
+                    .max_decoding_message_size(listen.max_message_size)
+                    .max_encoding_message_size(listen.max_message_size);
+                if !listen.zstd {
+                    return server;
+                }
+                let zstd = tonic::codec::CompressionEncoding::Zstd;
+                server.accept_compressed(zstd).send_compressed(zstd)
+            }
+        }
+    };
+}
+
+impl ListenConfig {
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        checks.check(
+            &format!("{}.max_message_size", path),
+            self.max_message_size > 0,
+            "must not be 0",
+        );
+        checks.check(
+            &format!("{}.max_concurrent_streams", path),
+            self.max_concurrent_streams != Some(0),
+            "must not be 0",
+        );
+        if let Some(uds) = &self.uds {
+            let dir = uds.parent().filter(|dir| !dir.as_os_str().is_empty());
+            checks.check(
+                &format!("{}.uds", path),
+                dir.is_none_or(|dir| dir.is_dir()),
+                "parent directory does not exist",
+            );
+        }
+    }
+
+    pub fn addr(&self, port: u16) -> SocketAddr {
+        SocketAddr::new(self.host, port)
+    }
+
+    /// Server builder with the connection options, listening with `tls` if
+    /// set, along with the interceptor checking its allowed callers, to be
+    /// layered after the others
+    pub fn server(&self, tls: Option<&TlsConfig>) -> Result<(Server, AllowedCallers)> {
+        let keepalive = (self.keepalive_secs > 0).then(|| Duration::from_secs(self.keepalive_secs));
+        let builder = Server::builder()
+            .http2_keepalive_interval(keepalive)
+            .http2_keepalive_timeout(Some(Duration::from_secs(self.keepalive_timeout_secs)))
+            .max_concurrent_streams(self.max_concurrent_streams);
+        let Some(tls) = tls else {
+            return Ok((builder, AllowedCallers::default()));
+        };
+        let builder = builder.tls_config(tls.server_config()?)?;
+        Ok((builder, tls.allowed_callers()))
+    }
+
+    /// `server` with the message size limit and compression applied
+    pub fn configure<S: GrpcServer>(&self, server: S) -> S {
+        server.with_options(self)
+    }
+
+    /// Serve `router` on the unix socket if set, or on `port` of `host`, until
+    /// `signal` fires
+    pub async fn serve<L, ResBody, F>(&self, router: Router<L>, port: u16, signal: F) -> Result<()>
+    where
+        F: Future<Output = ()>,
+        L: Layer<Routes>,
+        L::Service:
+            Service<Request<BoxBody>, Response = Response<ResBody>> + Clone + Send + 'static,
+        <L::Service as Service<Request<BoxBody>>>::Future: Send + 'static,
+        <L::Service as Service<Request<BoxBody>>>::Error: Into<StdError> + Send,
+        ResBody: http_body::Body<Data = Bytes> + Send + 'static,
+        ResBody::Error: Into<StdError>,
+    {
+        if let Some(path) = &self.uds {
+            // left behind by a previous run
+            if path.exists() {
+                fs::remove_file(path)
+                    .with_context(|| format!("failed to remove {}", path.display()))?;
+            }
+            let listener = UnixListener::bind(path)
+                .with_context(|| format!("failed to bind {}", path.display()))?;
+            info!("Listening on {}", path.display());
+            let incoming = UnixListenerStream::new(listener);
+            router
+                .serve_with_incoming_shutdown(incoming, signal)
+                .await?;
+            let _ = fs::remove_file(path);
+            return Ok(());
+        }
+        let addr = self.addr(port);
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("failed to bind {}", addr))?;
+        info!("Listening on {}", addr);
+        let incoming = TcpListenerStream::new(listener);
+        router
+            .serve_with_incoming_shutdown(incoming, signal)
+            .await?;
+        Ok(())
+    }
+}
+
+grpc_server!(crate::pb::grpc::health::v1::health_server::HealthServer);
+grpc_server!(crate::pb::grpc::reflection::v1::server_reflection_server::ServerReflectionServer);
+grpc_server!(
+    crate::pb::grpc::reflection::v1alpha::server_reflection_server::ServerReflectionServer
+);
+
+#[cfg(test)]
+mod tests {
+    use tokio::{net::UnixStream, sync::oneshot};
+    use tonic::{
+        codec::CompressionEncoding,
+        transport::{Endpoint, Uri},
+        Code,
+    };
+
+    use super::*;
+    use crate::{
+        pb::grpc::health::v1::{health_client::HealthClient, HealthCheckRequest},
+        HealthService,
+    };
+
+    fn socket(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("crm-core-{}-{}.sock", name, std::process::id()))
+    }
+
+    /// Health server on a unix socket, stopped when the sender is dropped
+    async fn serve(listen: ListenConfig) -> oneshot::Sender<()> {
+        let (stop, stopped) = oneshot::channel::<()>();
+        let (builder, callers) = listen.server(None).unwrap();
+        let health = listen.configure(HealthService::builder().build().into_server());
+        let router = builder.layer(callers.into_layer()).add_service(health);
+        let path = listen.uds.clone().unwrap();
+        tokio::spawn(async move {
+            listen
+                .serve(router, 0, async {
+                    let _ = stopped.await;
+                })
+                .await
+                .unwrap();
+        });
+        while !path.exists() {
+            tokio::task::yield_now().await;
+        }
+        stop
+    }
+
+    async fn client(path: PathBuf) -> HealthClient<tonic::transport::Channel> {
+        let channel = Endpoint::from_static("http://[::]:50051")
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let path = path.clone();
+                async move {
+                    let stream = UnixStream::connect(path).await?;
+                    Ok::<_, std::io::Error>(hyper_util::rt::TokioIo::new(stream))
+                }
+            }))
+            .await
+            .unwrap();
+        HealthClient::new(channel)
+    }
+
+    #[tokio::test]
+    async fn server_should_listen_on_unix_socket_with_options() {
+        let path = socket("uds");
+        let listen = ListenConfig {
+            uds: Some(path.clone()),
+            max_message_size: 16,
+            zstd: true,
+            ..Default::default()
+        };
+        let stop = serve(listen).await;
+
+        let mut client = client(path.clone())
+            .await
+            .send_compressed(CompressionEncoding::Zstd)
+            .accept_compressed(CompressionEncoding::Zstd);
+        let res = client.check(HealthCheckRequest::default()).await.unwrap();
+        assert_eq!(
+            res.metadata()
+                .get("grpc-encoding")
+                .unwrap()
+                .to_str()
+                .unwrap(),
+            "zstd"
+        );
+
+        let req = HealthCheckRequest {
+            service: "x".repeat(64),
+        };
+        let err = client.check(req).await.unwrap_err();
+        assert_eq!(err.code(), Code::OutOfRange);
+
+        drop(stop);
+        let start = std::time::Instant::now();
+        while path.exists() {
+            assert!(
+                start.elapsed() < Duration::from_secs(2),
+                "socket not removed"
+            );
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[test]
+    fn listen_config_should_default_to_localhost() {
+        let listen: ListenConfig = serde_yaml::from_str("host: 0.0.0.0\nzstd: true").unwrap();
+        assert_eq!(listen.addr(50000).to_string(), "0.0.0.0:50000");
+        assert!(listen.zstd);
+        assert_eq!(listen.max_message_size, 4 * 1024 * 1024);
+        assert_eq!(ListenConfig::default().addr(1).to_string(), "[::1]:1");
+
+        let mut checks = Checks::default();
+        ListenConfig {
+            uds: Some(PathBuf::from("/nonexistent/crm.sock")),
+            max_concurrent_streams: Some(0),
+            ..Default::default()
+        }
+        .validate("server", &mut checks);
+        let err = checks.into_result().unwrap_err().to_string();
+        assert!(err.contains("server.uds: parent directory"), "{}", err);
+        assert!(err.contains("server.max_concurrent_streams"), "{}", err);
+    }
+}
@@ -0,0 +1,483 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, LazyLock, Mutex, OnceLock},
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+
+use axum::{routing::get, Router};
+use http::{HeaderMap, Request, Response};
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tokio::net::TcpListener;
+use tonic::Code;
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{info, warn};
+
+/// Latency buckets in seconds, from 1ms to 10s
+const DEFAULT_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+static STARTED: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    counter(
+        "grpc_server_started_total",
+        "RPCs started on the server",
+        &["grpc_service", "grpc_method"],
+    )
+});
+static HANDLED: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    counter(
+        "grpc_server_handled_total",
+        "RPCs completed on the server, by status code",
+        &["grpc_service", "grpc_method", "grpc_code"],
+    )
+});
+static HANDLING: LazyLock<Arc<Histogram>> = LazyLock::new(|| {
+    histogram(
+        "grpc_server_handling_seconds",
+        "Time from receiving the request to the end of the response stream",
+        &["grpc_service", "grpc_method"],
+    )
+});
+
+/// Metric families of the process, rendered in the prometheus text format
+#[derive(Default)]
+struct Registry {
+    metrics: Mutex<Vec<Arc<dyn Metric>>>,
+}
+
+trait Metric: Send + Sync {
+    fn render(&self, out: &mut String);
+}
+
+/// Family of counters sharing a name, one value per set of label values
+#[derive(Debug)]
+pub struct Counter {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    values: Mutex<BTreeMap<Vec<String>, u64>>,
+}
+
+/// Family of histograms sharing a name and buckets
+#[derive(Debug)]
+pub struct Histogram {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    buckets: &'static [f64],
+    values: Mutex<BTreeMap<Vec<String>, Observations>>,
+}
+
+#[derive(Debug, Default, Clone)]
+struct Observations {
+    /// non cumulative count per bucket, the last one is +Inf
+    counts: Vec<u64>,
+    sum: f64,
+}
+
+/// Register a counter, call it once per name (e.g. from a `LazyLock`)
+pub fn counter(
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+) -> Arc<Counter> {
+    let counter = Arc::new(Counter {
+        name,
+        help,
+        labels,
+        values: Default::default(),
+    });
+    registry().register(counter.clone());
+    counter
+}
+
+/// Register a histogram with latency buckets, call it once per name
+pub fn histogram(
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+) -> Arc<Histogram> {
+    let histogram = Arc::new(Histogram {
+        name,
+        help,
+        labels,
+        buckets: DEFAULT_BUCKETS,
+        values: Default::default(),
+    });
+    registry().register(histogram.clone());
+    histogram
+}
+
+/// All registered metrics in the prometheus text exposition format
+pub fn render() -> String {
+    let mut out = String::new();
+    for metric in registry().metrics.lock().unwrap().iter() {
+        metric.render(&mut out);
+    }
+    out
+}
+
+/// Bind `addr` and serve `GET /metrics` in the background until `signal`
+pub async fn serve(
+    addr: SocketAddr,
+    signal: impl Future<Output = ()> + Send + 'static,
+) -> anyhow::Result<()> {
+    let app = Router::new().route("/metrics", get(|| async { render() }));
+    let listener = TcpListener::bind(addr).await?;
+    info!("Serving metrics on http://{}/metrics", addr);
+    tokio::spawn(async move {
+        let server = axum::serve(listener, app).with_graceful_shutdown(signal);
+        if let Err(e) = server.await {
+            warn!("metrics endpoint failed: {}", e);
+        }
+    });
+    Ok(())
+}
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+impl Registry {
+    fn register(&self, metric: Arc<dyn Metric>) {
+        self.metrics.lock().unwrap().push(metric);
+    }
+}
+
+impl Counter {
+    pub fn inc(&self, labels: &[&str]) {
+        self.inc_by(labels, 1);
+    }
+
+    pub fn inc_by(&self, labels: &[&str], v: u64) {
+        debug_assert_eq!(labels.len(), self.labels.len(), "labels of {}", self.name);
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        *self.values.lock().unwrap().entry(key).or_default() += v;
+    }
+
+    pub fn get(&self, labels: &[&str]) -> u64 {
+        let key = labels.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        self.values
+            .lock()
+            .unwrap()
+            .get(&key)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+impl Metric for Counter {
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "counter");
+        for (values, v) in self.values.lock().unwrap().iter() {
+            let labels = label_set(self.labels, values, None);
+            let _ = writeln!(out, "{}{} {}", self.name, labels, v);
+        }
+    }
+}
+
+impl Histogram {
+    pub fn observe(&self, labels: &[&str], v: f64) {
+        debug_assert_eq!(labels.len(), self.labels.len(), "labels of {}", self.name);
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        let mut values = self.values.lock().unwrap();
+        let observations = values.entry(key).or_insert_with(|| Observations {
+            counts: vec![0; self.buckets.len() + 1],
+            sum: 0.0,
+        });
+        let bucket = self.buckets.partition_point(|le| *le < v);
+        observations.counts[bucket] += 1;
+        observations.sum += v;
+    }
+
+    /// Observe the seconds `fut` takes to complete
+    pub async fn time<F: Future>(&self, labels: &[&str], fut: F) -> F::Output {
+        let start = Instant::now();
+        let out = fut.await;
+        self.observe(labels, start.elapsed().as_secs_f64());
+        out
+    }
+
+    pub fn count(&self, labels: &[&str]) -> u64 {
+        let key = labels.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+        self.values
+            .lock()
+            .unwrap()
+            .get(&key)
+            .map(|o| o.counts.iter().sum())
+            .unwrap_or_default()
+    }
+}
+
+impl Metric for Histogram {
+    fn render(&self, out: &mut String) {
+        header(out, self.name, self.help, "histogram");
+        for (values, observations) in self.values.lock().unwrap().iter() {
+            let mut cumulative = 0;
+            let les = self.buckets.iter().map(|le| le.to_string());
+            for (le, count) in les
+                .chain(Some("+Inf".to_string()))
+                .zip(&observations.counts)
+            {
+                cumulative += count;
+                let labels = label_set(self.labels, values, Some(&le));
+                let _ = writeln!(out, "{}_bucket{} {}", self.name, labels, cumulative);
+            }
+            let labels = label_set(self.labels, values, None);
+            let _ = writeln!(out, "{}_sum{} {}", self.name, labels, observations.sum);
+            let _ = writeln!(out, "{}_count{} {}", self.name, labels, cumulative);
+        }
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn label_set(names: &[&str], values: &[String], le: Option<&str>) -> String {
+    let mut pairs = names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect::<Vec<_>>();
+    if let Some(le) = le {
+        pairs.push(format!("le=\"{}\"", le));
+    }
+    if pairs.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", pairs.join(","))
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Tower layer recording rate, status codes and latency of every RPC served,
+/// add it with `Server::builder().layer(metrics::layer())`
+pub fn layer() -> MetricsLayer {
+    // list the families on /metrics before the first RPC
+    LazyLock::force(&STARTED);
+    LazyLock::force(&HANDLED);
+    LazyLock::force(&HANDLING);
+    MetricsLayer
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MetricsLayer;
+
+#[derive(Debug, Clone)]
+pub struct MetricsService<S> {
+    inner: S,
+}
+
+impl<S> Layer<S> for MetricsLayer {
+    type Service = MetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MetricsService { inner }
+    }
+}
+
+impl<S, B, ResBody> Service<Request<B>> for MetricsService<S>
+where
+    S: Service<Request<B>, Response = Response<ResBody>>,
+{
+    type Response = Response<MetricsBody<ResBody>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let recorder = Recorder::new(req.uri().path());
+        ResponseFuture {
+            inner: self.inner.call(req),
+            recorder: Some(recorder),
+        }
+    }
+}
+
+pin_project! {
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        recorder: Option<Recorder>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = Result<Response<MetricsBody<ResBody>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx))?;
+        let mut recorder = this.recorder.take();
+        if let Some(recorder) = recorder.as_mut() {
+            // errors before the first message are sent as trailers only
+            recorder.status(res.headers());
+        }
+        Poll::Ready(Ok(res.map(|inner| MetricsBody { inner, recorder })))
+    }
+}
+
+pin_project! {
+    /// Response body recording the RPC once the stream ends or is dropped
+    pub struct MetricsBody<B> {
+        #[pin]
+        inner: B,
+        recorder: Option<Recorder>,
+    }
+}
+
+impl<B: Body> Body for MetricsBody<B> {
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        match &frame {
+            Some(Ok(frame)) => {
+                if let (Some(trailers), Some(recorder)) =
+                    (frame.trailers_ref(), this.recorder.as_mut())
+                {
+                    recorder.status(trailers);
+                }
+            }
+            Some(Err(_)) => {
+                if let Some(recorder) = this.recorder.as_mut() {
+                    recorder.code.get_or_insert(Code::Internal);
+                }
+                this.recorder.take();
+            }
+            None => {
+                this.recorder.take();
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+/// Records the RPC when dropped, a stream dropped before its trailers were
+/// sent was cancelled by the client
+struct Recorder {
+    service: String,
+    method: String,
+    code: Option<Code>,
+    start: Instant,
+}
+
+impl Recorder {
+    fn new(path: &str) -> Self {
+        let (service, method) = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .unwrap_or(("unknown", "unknown"));
+        STARTED.inc(&[service, method]);
+        Self {
+            service: service.to_string(),
+            method: method.to_string(),
+            code: None,
+            start: Instant::now(),
+        }
+    }
+
+    fn status(&mut self, headers: &HeaderMap) {
+        let code = headers
+            .get("grpc-status")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<i32>().ok());
+        if let Some(code) = code {
+            self.code = Some(Code::from(code));
+        }
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let code = format!("{:?}", self.code.unwrap_or(Code::Cancelled));
+        HANDLED.inc(&[&self.service, &self.method, &code]);
+        HANDLING.observe(
+            &[&self.service, &self.method],
+            self.start.elapsed().as_secs_f64(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counter_should_render() {
+        let counter = counter("test_events_total", "Events seen", &["kind"]);
+        counter.inc(&["a\"b"]);
+        counter.inc_by(&["c"], 2);
+        let out = render();
+        assert!(out.contains("# TYPE test_events_total counter\n"));
+        assert!(out.contains("test_events_total{kind=\"a\\\"b\"} 1\n"));
+        assert!(out.contains("test_events_total{kind=\"c\"} 2\n"));
+        assert_eq!(counter.get(&["c"]), 2);
+    }
+
+    #[test]
+    fn histogram_should_render_cumulative_buckets() {
+        let histogram = histogram("test_duration_seconds", "Durations", &[]);
+        histogram.observe(&[], 0.003);
+        histogram.observe(&[], 0.003);
+        histogram.observe(&[], 20.0);
+        let out = render();
+        assert!(out.contains("test_duration_seconds_bucket{le=\"0.001\"} 0\n"));
+        assert!(out.contains("test_duration_seconds_bucket{le=\"0.005\"} 2\n"));
+        assert!(out.contains("test_duration_seconds_bucket{le=\"10\"} 2\n"));
+        assert!(out.contains("test_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(out.contains("test_duration_seconds_count 3\n"));
+        assert_eq!(histogram.count(&[]), 3);
+    }
+
+    #[test]
+    fn recorder_should_default_to_cancelled() {
+        let mut headers = HeaderMap::new();
+        headers.insert("grpc-status", "5".parse().unwrap());
+        let mut recorder = Recorder::new("/test.Recorder/Found");
+        recorder.status(&headers);
+        drop(recorder);
+        drop(Recorder::new("/test.Recorder/Dropped"));
+
+        assert_eq!(HANDLED.get(&["test.Recorder", "Found", "NotFound"]), 1);
+        assert_eq!(HANDLED.get(&["test.Recorder", "Dropped", "Cancelled"]), 1);
+        assert_eq!(STARTED.get(&["test.Recorder", "Found"]), 1);
+        assert_eq!(HANDLING.count(&["test.Recorder", "Found"]), 1);
+    }
+}
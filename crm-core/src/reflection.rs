@@ -0,0 +1,329 @@
+use std::{
+    collections::{HashMap, HashSet},
+    pin::Pin,
+    sync::Arc,
+};
+
+use prost::Message;
+use prost_types::{DescriptorProto, FileDescriptorProto, FileDescriptorSet};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Code, Request, Response, Status, Streaming};
+use tracing::warn;
+
+use crate::pb::{
+    grpc::reflection::{
+        v1::{
+            self,
+            server_reflection_request::MessageRequest,
+            server_reflection_response::MessageResponse,
+            server_reflection_server::{ServerReflection, ServerReflectionServer},
+            ErrorResponse, ExtensionNumberResponse, FileDescriptorResponse, ListServiceResponse,
+            ServerReflectionRequest, ServerReflectionResponse, ServiceResponse,
+        },
+        v1alpha,
+    },
+    FILE_DESCRIPTOR_SET,
+};
+
+type ReflectionStream =
+    Pin<Box<dyn Stream<Item = Result<ServerReflectionResponse, Status>> + Send>>;
+
+/// `grpc.reflection.v1.ServerReflection`, also served as v1alpha, answering
+/// from the file descriptor sets the service crates generate at build time
+#[derive(Debug, Clone)]
+pub struct ReflectionService {
+    inner: Arc<Registry>,
+}
+
+#[derive(Debug, Default)]
+pub struct ReflectionBuilder {
+    sets: Vec<&'static [u8]>,
+}
+
+#[derive(Debug, Default)]
+struct Registry {
+    /// encoded FileDescriptorProto and its dependencies by file name
+    files: HashMap<String, (Vec<u8>, Vec<String>)>,
+    /// file declaring each fully qualified symbol
+    symbols: HashMap<String, String>,
+    services: Vec<String>,
+}
+
+impl ReflectionService {
+    pub fn builder() -> ReflectionBuilder {
+        // health and reflection are served by every binary
+        ReflectionBuilder {
+            sets: vec![FILE_DESCRIPTOR_SET],
+        }
+    }
+
+    pub fn into_server(self) -> ServerReflectionServer<Self> {
+        ServerReflectionServer::new(self)
+    }
+
+    pub fn into_v1alpha_server(
+        self,
+    ) -> v1alpha::server_reflection_server::ServerReflectionServer<Self> {
+        v1alpha::server_reflection_server::ServerReflectionServer::new(self)
+    }
+
+    fn answer(&self, request: ServerReflectionRequest) -> ServerReflectionResponse {
+        let response = match &request.message_request {
+            Some(MessageRequest::FileByFilename(name)) => self.inner.file(name),
+            Some(MessageRequest::FileContainingSymbol(symbol)) => self
+                .inner
+                .symbols
+                .get(symbol.trim_start_matches('.'))
+                .ok_or_else(|| format!("symbol {} not found", symbol))
+                .and_then(|name| self.inner.file(name)),
+            Some(MessageRequest::FileContainingExtension(req)) => Err(format!(
+                "extension {} of {} not found",
+                req.extension_number, req.containing_type
+            )),
+            Some(MessageRequest::AllExtensionNumbersOfType(name)) => {
+                match self
+                    .inner
+                    .symbols
+                    .contains_key(name.trim_start_matches('.'))
+                {
+                    // the crm protos don't declare any extension
+                    true => Ok(MessageResponse::AllExtensionNumbersResponse(
+                        ExtensionNumberResponse {
+                            base_type_name: name.clone(),
+                            extension_number: vec![],
+                        },
+                    )),
+                    false => Err(format!("type {} not found", name)),
+                }
+            }
+            Some(MessageRequest::ListServices(_)) => {
+                let service = self
+                    .inner
+                    .services
+                    .iter()
+                    .map(|name| ServiceResponse { name: name.clone() })
+                    .collect();
+                Ok(MessageResponse::ListServicesResponse(ListServiceResponse {
+                    service,
+                }))
+            }
+            None => Err("message_request is required".to_string()),
+        };
+        let message_response = response.unwrap_or_else(|error_message| {
+            MessageResponse::ErrorResponse(ErrorResponse {
+                error_code: Code::NotFound as i32,
+                error_message,
+            })
+        });
+        ServerReflectionResponse {
+            valid_host: request.host.clone(),
+            original_request: Some(request),
+            message_response: Some(message_response),
+        }
+    }
+
+    fn info(&self, requests: Streaming<ServerReflectionRequest>) -> ReflectionStream {
+        let svc = self.clone();
+        let responses = requests
+            .map_while(|request| {
+                request
+                    .inspect_err(|e| warn!("reflection request stream failed: {}", e))
+                    .ok()
+            })
+            .map(move |request| svc.answer(request))
+            .map(Ok);
+        Box::pin(responses)
+    }
+}
+
+impl ReflectionBuilder {
+    /// Encoded `FileDescriptorSet`, as written by `file_descriptor_set_path`
+    pub fn register(mut self, set: &'static [u8]) -> Self {
+        self.sets.push(set);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<ReflectionService> {
+        let mut registry = Registry::default();
+        for set in self.sets {
+            for file in FileDescriptorSet::decode(set)?.file {
+                registry.add(file);
+            }
+        }
+        registry.services.sort();
+        registry.services.dedup();
+        Ok(ReflectionService {
+            inner: Arc::new(registry),
+        })
+    }
+}
+
+impl Registry {
+    fn add(&mut self, file: FileDescriptorProto) {
+        let name = file.name().to_string();
+        let package = file.package();
+        for message in &file.message_type {
+            self.add_message(&name, package, message);
+        }
+        for e in &file.enum_type {
+            self.add_symbol(&name, package, e.name());
+        }
+        for service in &file.service {
+            let full_name = self.add_symbol(&name, package, service.name());
+            for method in &service.method {
+                self.add_symbol(&name, &full_name, method.name());
+            }
+            self.services.push(full_name);
+        }
+        if !package.is_empty() {
+            self.symbols.insert(package.to_string(), name.clone());
+        }
+        let deps = file.dependency.clone();
+        self.files.insert(name, (file.encode_to_vec(), deps));
+    }
+
+    fn add_message(&mut self, file: &str, scope: &str, message: &DescriptorProto) {
+        let full_name = self.add_symbol(file, scope, message.name());
+        for nested in &message.nested_type {
+            self.add_message(file, &full_name, nested);
+        }
+        for e in &message.enum_type {
+            self.add_symbol(file, &full_name, e.name());
+        }
+    }
+
+    fn add_symbol(&mut self, file: &str, scope: &str, name: &str) -> String {
+        let full_name = if scope.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", scope, name)
+        };
+        self.symbols.insert(full_name.clone(), file.to_string());
+        full_name
+    }
+
+    /// The file and all its transitive dependencies, the file itself first
+    fn file(&self, name: &str) -> Result<MessageResponse, String> {
+        if !self.files.contains_key(name) {
+            return Err(format!("file {} not found", name));
+        }
+        let mut seen = HashSet::new();
+        let mut pending = vec![name];
+        let mut file_descriptor_proto = Vec::new();
+        while let Some(name) = pending.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+            // a dependency outside the registered sets is left to the client
+            if let Some((encoded, deps)) = self.files.get(name) {
+                file_descriptor_proto.push(encoded.clone());
+                pending.extend(deps.iter().map(String::as_str));
+            }
+        }
+        Ok(MessageResponse::FileDescriptorResponse(
+            FileDescriptorResponse {
+                file_descriptor_proto,
+            },
+        ))
+    }
+}
+
+#[tonic::async_trait]
+impl ServerReflection for ReflectionService {
+    type ServerReflectionInfoStream = ReflectionStream;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        Ok(Response::new(self.info(request.into_inner())))
+    }
+}
+
+#[tonic::async_trait]
+impl v1alpha::server_reflection_server::ServerReflection for ReflectionService {
+    type ServerReflectionInfoStream = ReflectionStream;
+
+    async fn server_reflection_info(
+        &self,
+        request: Request<Streaming<v1::ServerReflectionRequest>>,
+    ) -> Result<Response<Self::ServerReflectionInfoStream>, Status> {
+        Ok(Response::new(self.info(request.into_inner())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(request: MessageRequest) -> MessageResponse {
+        let svc = ReflectionService::builder().build().unwrap();
+        let request = ServerReflectionRequest {
+            host: String::new(),
+            message_request: Some(request),
+        };
+        svc.answer(request).message_response.unwrap()
+    }
+
+    fn file_names(response: MessageResponse) -> Vec<String> {
+        let MessageResponse::FileDescriptorResponse(res) = response else {
+            panic!("unexpected response {:?}", response);
+        };
+        res.file_descriptor_proto
+            .iter()
+            .map(|b| {
+                FileDescriptorProto::decode(b.as_slice())
+                    .unwrap()
+                    .name()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn list_services_should_include_health_and_reflection() {
+        let MessageResponse::ListServicesResponse(res) =
+            answer(MessageRequest::ListServices(String::new()))
+        else {
+            panic!("unexpected response");
+        };
+        let names = res.service.into_iter().map(|s| s.name).collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "grpc.health.v1.Health",
+                "grpc.reflection.v1.ServerReflection"
+            ]
+        );
+    }
+
+    #[test]
+    fn file_containing_symbol_should_work() {
+        for symbol in [
+            "grpc.health.v1.Health",
+            "grpc.health.v1.Health.Watch",
+            ".grpc.health.v1.HealthCheckResponse.ServingStatus",
+        ] {
+            let names = file_names(answer(MessageRequest::FileContainingSymbol(
+                symbol.to_string(),
+            )));
+            assert_eq!(names, vec!["grpc/health/v1/health.proto"]);
+        }
+        assert!(matches!(
+            answer(MessageRequest::FileContainingSymbol("foo.Bar".to_string())),
+            MessageResponse::ErrorResponse(ErrorResponse { error_code, .. }) if error_code == Code::NotFound as i32
+        ));
+    }
+
+    #[test]
+    fn file_by_filename_should_work() {
+        let names = file_names(answer(MessageRequest::FileByFilename(
+            "grpc/reflection/v1/reflection.proto".to_string(),
+        )));
+        assert_eq!(names, vec!["grpc/reflection/v1/reflection.proto"]);
+        assert!(matches!(
+            answer(MessageRequest::FileByFilename("foo.proto".to_string())),
+            MessageResponse::ErrorResponse(_)
+        ));
+    }
+}
@@ -0,0 +1,226 @@
+use std::{convert::Infallible, future::Future, pin::Pin, time::Duration};
+
+use anyhow::Result;
+use http::{Request, Response};
+use serde::{Deserialize, Serialize};
+use tonic::{body::BoxBody, server::NamedService};
+use tower_service::Service;
+use tracing::info;
+
+use crate::{
+    config::{Checks, Config},
+    listen::{GrpcServer, ListenConfig},
+    metrics,
+    reflection::ReflectionBuilder,
+    shutdown::Shutdown,
+    telemetry::{self, Telemetry, TelemetryConfig},
+    tls::TlsConfig,
+    HealthService, ReflectionService,
+};
+
+/// The `server` section of every service config, `E` being the settings
+/// specific to the service, e.g. its database
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ServerConfig<E = ()> {
+    /// host, unix socket and connection options of the grpc server
+    #[serde(flatten)]
+    pub listen: ListenConfig,
+    pub port: u16,
+    /// port of the prometheus `/metrics` http endpoint
+    pub metrics_port: u16,
+    /// seconds in-flight requests get to finish after SIGTERM, the process
+    /// exits with an error when they don't
+    pub shutdown_timeout_secs: u64,
+    /// TLS of the listener and of the calls to other services, plaintext when unset
+    #[serde(default)]
+    pub tls: Option<TlsConfig>,
+    #[serde(flatten)]
+    pub extra: E,
+}
+
+impl<E> ServerConfig<E> {
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout_secs)
+    }
+
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        checks.port(&format!("{}.port", path), self.port);
+        checks.port(&format!("{}.metrics_port", path), self.metrics_port);
+        self.listen.validate(path, checks);
+        if let Some(tls) = &self.tls {
+            tls.validate(&format!("{}.tls", path), checks);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct AuthConfig {
+    pub pk: String,
+}
+
+impl AuthConfig {
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        checks.pem(&format!("{}.pk", path), &self.pk);
+    }
+}
+
+/// Config of a service binary, the sections `Bootstrap` reads. Implemented
+/// with `service_config!` for the configs with `server` and `telemetry` fields.
+pub trait ServiceConfig: Config {
+    type Extra;
+
+    fn server(&self) -> &ServerConfig<Self::Extra>;
+
+    fn telemetry(&self) -> &TelemetryConfig;
+}
+
+/// `ServiceConfig` of a config, given the type of its server extra settings if any
+#[macro_export]
+macro_rules! service_config {
+    ($config:ty) => {
+        $crate::service_config!($config, ());
+    };
+    ($config:ty, $extra:ty) => {
+        impl $crate::server::ServiceConfig for $config {
+            type Extra = $extra;
+
+            fn server(&self) -> &$crate::server::ServerConfig<$extra> {
+                &self.server
+            }
+
+            fn telemetry(&self) -> &$crate::telemetry::TelemetryConfig {
+                &self.telemetry
+            }
+        }
+    };
+}
+
+type Drain = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+/// What every service binary runs around its grpc service: tracing, the
+/// `/metrics` endpoint, health, reflection, the allowed callers check and
+/// the graceful shutdown
+pub struct Bootstrap {
+    listen: ListenConfig,
+    port: u16,
+    tls: Option<TlsConfig>,
+    telemetry: Telemetry,
+    shutdown: Shutdown,
+    reflection: ReflectionBuilder,
+    /// work to finish once the server stopped
+    drains: Vec<Drain>,
+}
+
+impl Bootstrap {
+    /// Resolve the config from the command line, then install tracing for
+    /// `service`, the signal handlers and serve `/metrics`
+    pub async fn init<T: ServiceConfig>(service: &str) -> Result<(T, Self)> {
+        let config = T::from_args()?;
+        let telemetry = telemetry::init(service, config.telemetry())?;
+        let server = config.server();
+        let shutdown = Shutdown::on_signals(server.shutdown_timeout())?;
+        metrics::serve(server.listen.addr(server.metrics_port), shutdown.signal()).await?;
+        let bootstrap = Self {
+            listen: server.listen.clone(),
+            port: server.port,
+            tls: server.tls.clone(),
+            telemetry,
+            shutdown,
+            reflection: ReflectionService::builder(),
+            drains: Vec::new(),
+        };
+        Ok((config, bootstrap))
+    }
+
+    /// Expose the protos of an encoded `FileDescriptorSet` to grpcurl
+    pub fn reflect(mut self, set: &'static [u8]) -> Self {
+        self.reflection = self.reflection.register(set);
+        self
+    }
+
+    /// Also wait for `fut` once the server stopped, e.g. for the work queued
+    /// by the last requests, within the same shutdown deadline
+    pub fn drain(mut self, fut: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.drains.push(Box::pin(fut));
+        self
+    }
+
+    /// Serve `service` along with `health` and reflection until the shutdown
+    /// signal, then let the in-flight requests finish
+    pub async fn serve<S>(self, service: S, health: HealthService) -> Result<()>
+    where
+        S: GrpcServer
+            + Service<Request<BoxBody>, Response = Response<BoxBody>, Error = Infallible>
+            + NamedService
+            + Clone
+            + Send
+            + 'static,
+        S::Future: Send + 'static,
+    {
+        let listen = &self.listen;
+        let (builder, callers) = listen.server(self.tls.as_ref())?;
+        let reflection = self.reflection.build()?;
+        let router = builder
+            .layer(metrics::layer())
+            .layer(telemetry::server_layer())
+            .layer(callers.into_layer())
+            .add_service(listen.configure(health.into_server()))
+            .add_service(listen.configure(reflection.clone().into_server()))
+            .add_service(listen.configure(reflection.into_v1alpha_server()))
+            .add_service(listen.configure(service));
+        self.shutdown
+            .drain(listen.serve(router, self.port, self.shutdown.signal()))
+            .await??;
+        for drain in self.drains {
+            self.shutdown.drain(drain).await?;
+        }
+        info!("Server stopped");
+        self.telemetry.shutdown().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn server_config_should_flatten_listen() {
+        let server: ServerConfig = serde_yaml::from_str(
+            "host: 0.0.0.0\nport: 50000\nmetrics_port: 0\nshutdown_timeout_secs: 5\nzstd: true",
+        )
+        .unwrap();
+        assert_eq!(server.listen.addr(server.port).to_string(), "0.0.0.0:50000");
+        assert!(server.listen.zstd);
+        assert_eq!(server.shutdown_timeout(), Duration::from_secs(5));
+
+        let mut checks = Checks::default();
+        server.validate("server", &mut checks);
+        AuthConfig {
+            pk: "not a pem".to_string(),
+        }
+        .validate("auth", &mut checks);
+        let err = checks.into_result().unwrap_err().to_string();
+        assert!(
+            err.contains("server.metrics_port: port must not be 0"),
+            "{}",
+            err
+        );
+        assert!(err.contains("auth.pk: invalid PEM"), "{}", err);
+    }
+
+    #[test]
+    fn server_config_should_flatten_extra() {
+        #[derive(Debug, Deserialize)]
+        struct Db {
+            db_url: String,
+        }
+
+        let server: ServerConfig<Db> = serde_yaml::from_str(
+            "port: 50000\nmetrics_port: 9100\nshutdown_timeout_secs: 5\ndb_url: postgres://db",
+        )
+        .unwrap();
+        assert_eq!(server.port, 50000);
+        assert_eq!(server.extra.db_url, "postgres://db");
+    }
+}
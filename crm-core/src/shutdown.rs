@@ -0,0 +1,132 @@
+use std::{future::Future, io, pin::pin, time::Duration};
+
+use anyhow::anyhow;
+use tokio::{
+    signal::{
+        ctrl_c,
+        unix::{signal, SignalKind},
+    },
+    sync::watch,
+    time::{self, Instant},
+};
+use tracing::info;
+
+/// The shutdown of a server: its `signal` is awaited by the listeners,
+/// `drain` bounds the time spent finishing the work in flight. Every `drain`
+/// shares the same deadline, `timeout` after the signal.
+#[derive(Debug, Clone)]
+pub struct Shutdown {
+    timeout: Duration,
+    /// when the shutdown was triggered, None until then
+    triggered: watch::Sender<Option<Instant>>,
+}
+
+impl Shutdown {
+    /// A shutdown only triggered by `trigger`
+    pub fn new(timeout: Duration) -> Self {
+        let (triggered, _) = watch::channel(None);
+        Self { timeout, triggered }
+    }
+
+    /// A shutdown triggered by SIGTERM or SIGINT. Listens to the signals,
+    /// so a SIGTERM no longer kills the process.
+    pub fn on_signals(timeout: Duration) -> io::Result<Self> {
+        let shutdown = Self::new(timeout);
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let forward = shutdown.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = sigterm.recv() => info!("SIGTERM received"),
+                _ = ctrl_c() => info!("SIGINT received"),
+                // triggered some other way, nothing left to forward
+                _ = forward.signal() => return,
+            }
+            forward.trigger();
+        });
+        Ok(shutdown)
+    }
+
+    /// Start the shutdown, later calls keep the first deadline
+    pub fn trigger(&self) {
+        self.triggered.send_if_modified(|at| {
+            let first = at.is_none();
+            at.get_or_insert_with(Instant::now);
+            first
+        });
+    }
+
+    /// Resolves once the shutdown was triggered. Can be awaited from any
+    /// number of places, e.g. `serve_with_shutdown` and `drain`.
+    pub fn signal(&self) -> impl Future<Output = ()> + Send + 'static {
+        let mut triggered = self.triggered.subscribe();
+        async move {
+            // the sender lives as long as the receiver, it is kept in `self`
+            let _ = triggered.wait_for(Option::is_some).await;
+        }
+    }
+
+    /// Run `fut` to completion, but fail if it is still running `timeout`
+    /// after the shutdown signal
+    pub async fn drain<F: Future>(&self, fut: F) -> anyhow::Result<F::Output> {
+        let mut fut = pin!(fut);
+        tokio::select! {
+            out = &mut fut => return Ok(out),
+            _ = self.signal() => {}
+        }
+        let triggered_at = self.triggered.borrow().unwrap_or_else(Instant::now);
+        time::timeout_at(triggered_at + self.timeout, fut)
+            .await
+            .map_err(|_| {
+                anyhow!(
+                    "not drained within {:?} of the shutdown signal",
+                    self.timeout
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn drain_should_respect_the_deadline() {
+        let shutdown = Shutdown::new(Duration::from_millis(200));
+        assert_eq!(shutdown.drain(async { 1 }).await.unwrap(), 1);
+
+        let signal = tokio::spawn(shutdown.signal());
+        shutdown.trigger();
+        time::timeout(Duration::from_secs(1), signal)
+            .await
+            .expect("signal not observed")
+            .unwrap();
+
+        let drained = shutdown.drain(time::sleep(Duration::from_millis(10))).await;
+        assert!(drained.is_ok());
+        let drained = shutdown.drain(time::sleep(Duration::from_secs(10))).await;
+        assert!(drained.is_err());
+    }
+
+    #[tokio::test]
+    async fn shutdowns_should_be_independent() {
+        let triggered = Shutdown::new(Duration::ZERO);
+        let other = Shutdown::new(Duration::ZERO);
+        triggered.trigger();
+        assert!(triggered.drain(std::future::pending::<()>()).await.is_err());
+        let signaled = time::timeout(Duration::from_millis(50), other.signal()).await;
+        assert!(signaled.is_err());
+    }
+
+    #[tokio::test]
+    async fn on_signals_should_trigger_on_sigterm() {
+        let shutdown = Shutdown::on_signals(Duration::ZERO).unwrap();
+        let status = std::process::Command::new("kill")
+            .args(["-TERM", &std::process::id().to_string()])
+            .status()
+            .unwrap();
+        assert!(status.success());
+        time::timeout(Duration::from_secs(5), shutdown.signal())
+            .await
+            .expect("SIGTERM not observed");
+    }
+}
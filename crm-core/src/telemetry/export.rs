@@ -0,0 +1,181 @@
+use std::{fs::OpenOptions, io::Write, path::PathBuf, time::Duration};
+
+use serde_json::{json, Value};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time,
+};
+use tonic::transport::Channel;
+use tracing::warn;
+
+use super::propagation::hex;
+use crate::pb::opentelemetry::proto::{
+    collector::trace::v1::{trace_service_client::TraceServiceClient, ExportTraceServiceRequest},
+    common::v1::{any_value, AnyValue, InstrumentationScope, KeyValue},
+    resource::v1::Resource,
+    trace::v1::{span, ResourceSpans, ScopeSpans, Span, Status},
+};
+
+const MAX_BATCH: usize = 512;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(super) enum Exporter {
+    Otlp(TraceServiceClient<Channel>),
+    /// OTLP/JSON, one export request per line
+    File(PathBuf),
+}
+
+/// Batch the spans of `rx` until `stop` fires, then flush what is left
+pub(super) async fn run(
+    service: String,
+    mut exporter: Exporter,
+    mut rx: mpsc::Receiver<Span>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let resource = Resource {
+        attributes: vec![KeyValue {
+            key: "service.name".to_string(),
+            value: Some(AnyValue {
+                value: Some(any_value::Value::StringValue(service)),
+            }),
+        }],
+        dropped_attributes_count: 0,
+    };
+    let mut batch = Vec::new();
+    let mut ticker = time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            Some(span) = rx.recv() => {
+                batch.push(span);
+                if batch.len() < MAX_BATCH {
+                    continue;
+                }
+            }
+            _ = ticker.tick() => {}
+            _ = &mut stop => {
+                while let Ok(span) = rx.try_recv() {
+                    batch.push(span);
+                }
+                exporter.export(&resource, &mut batch).await;
+                return;
+            }
+        }
+        exporter.export(&resource, &mut batch).await;
+    }
+}
+
+impl Exporter {
+    async fn export(&mut self, resource: &Resource, batch: &mut Vec<Span>) {
+        if batch.is_empty() {
+            return;
+        }
+        let request = ExportTraceServiceRequest {
+            resource_spans: vec![ResourceSpans {
+                resource: Some(resource.clone()),
+                scope_spans: vec![ScopeSpans {
+                    scope: Some(InstrumentationScope {
+                        name: env!("CARGO_PKG_NAME").to_string(),
+                        version: env!("CARGO_PKG_VERSION").to_string(),
+                        ..Default::default()
+                    }),
+                    spans: std::mem::take(batch),
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        };
+        // warnings are logged outside of any span, so they aren't exported again
+        match self {
+            Exporter::Otlp(client) => {
+                if let Err(e) = client.export(request).await {
+                    warn!("failed to export spans: {}", e);
+                }
+            }
+            Exporter::File(path) => {
+                let line = format!("{}\n", request_to_json(&request));
+                let written = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&*path)
+                    .and_then(|mut f| f.write_all(line.as_bytes()));
+                if let Err(e) = written {
+                    warn!("failed to write spans to {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+/// The OTLP/JSON encoding: camelCase names, hex ids, 64 bit integers as strings
+fn request_to_json(request: &ExportTraceServiceRequest) -> Value {
+    let resource_spans = request
+        .resource_spans
+        .iter()
+        .map(|rs| {
+            let scope_spans = rs
+                .scope_spans
+                .iter()
+                .map(|ss| {
+                    json!({
+                        "scope": ss.scope.as_ref().map(|s| json!({
+                            "name": s.name,
+                            "version": s.version,
+                        })),
+                        "spans": ss.spans.iter().map(span_to_json).collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "resource": rs.resource.as_ref().map(|r| json!({
+                    "attributes": attributes_to_json(&r.attributes),
+                })),
+                "scopeSpans": scope_spans,
+            })
+        })
+        .collect::<Vec<_>>();
+    json!({ "resourceSpans": resource_spans })
+}
+
+fn span_to_json(span: &Span) -> Value {
+    json!({
+        "traceId": hex(&span.trace_id),
+        "spanId": hex(&span.span_id),
+        "parentSpanId": hex(&span.parent_span_id),
+        "flags": span.flags,
+        "name": span.name,
+        "kind": span.kind,
+        "startTimeUnixNano": span.start_time_unix_nano.to_string(),
+        "endTimeUnixNano": span.end_time_unix_nano.to_string(),
+        "attributes": attributes_to_json(&span.attributes),
+        "events": span.events.iter().map(event_to_json).collect::<Vec<_>>(),
+        "status": span.status.as_ref().map(status_to_json),
+    })
+}
+
+fn event_to_json(event: &span::Event) -> Value {
+    json!({
+        "timeUnixNano": event.time_unix_nano.to_string(),
+        "name": event.name,
+        "attributes": attributes_to_json(&event.attributes),
+    })
+}
+
+fn status_to_json(status: &Status) -> Value {
+    json!({ "message": status.message, "code": status.code })
+}
+
+fn attributes_to_json(attributes: &[KeyValue]) -> Value {
+    attributes
+        .iter()
+        .map(|kv| {
+            let value = match kv.value.as_ref().and_then(|v| v.value.as_ref()) {
+                Some(any_value::Value::StringValue(s)) => json!({ "stringValue": s }),
+                Some(any_value::Value::BoolValue(b)) => json!({ "boolValue": b }),
+                Some(any_value::Value::IntValue(i)) => json!({ "intValue": i.to_string() }),
+                Some(any_value::Value::DoubleValue(d)) => json!({ "doubleValue": d }),
+                _ => json!({}),
+            };
+            json!({ "key": kv.key, "value": value })
+        })
+        .collect()
+}
@@ -0,0 +1,248 @@
+use std::{
+    fmt::Debug,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use tokio::sync::mpsc;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+
+use super::TraceContext;
+use crate::pb::opentelemetry::proto::{
+    common::v1::{any_value, AnyValue, KeyValue},
+    trace::v1::{span, status::StatusCode, Span, Status},
+};
+
+/// Turns closed `tracing` spans into OTLP spans, sent to the exporter.
+///
+/// A few fields are interpreted instead of being kept as attributes:
+/// `otel.name` renames the span, `otel.kind` sets its kind (server, client,
+/// producer, consumer) and `traceparent` continues a remote trace.
+pub struct OtelLayer {
+    tx: mpsc::Sender<Span>,
+}
+
+/// The span being recorded, kept in the span's extensions until it closes
+struct Building {
+    ctx: TraceContext,
+    span: Span,
+}
+
+impl OtelLayer {
+    pub fn new(tx: mpsc::Sender<Span>) -> Self {
+        Self { tx }
+    }
+}
+
+impl<S> Layer<S> for OtelLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut visitor = SpanVisitor {
+            span: Span {
+                name: attrs.metadata().name().to_string(),
+                kind: span::SpanKind::Internal as i32,
+                start_time_unix_nano: now(),
+                ..Default::default()
+            },
+            remote: None,
+        };
+        attrs.record(&mut visitor);
+
+        let parent = visitor.remote.or_else(|| {
+            span.parent()
+                .and_then(|parent| parent.extensions().get::<Building>().map(|b| b.ctx))
+        });
+        let ctx = match parent {
+            Some(parent) => {
+                visitor.span.parent_span_id = parent.span_id.to_vec();
+                parent.child()
+            }
+            None => TraceContext::new_root(),
+        };
+        visitor.span.trace_id = ctx.trace_id.to_vec();
+        visitor.span.span_id = ctx.span_id.to_vec();
+        visitor.span.flags = ctx.flags as u32;
+        span.extensions_mut().insert(Building {
+            ctx,
+            span: visitor.span,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(building) = extensions.get_mut::<Building>() else {
+            return;
+        };
+        let mut visitor = SpanVisitor {
+            span: std::mem::take(&mut building.span),
+            remote: None,
+        };
+        values.record(&mut visitor);
+        building.span = visitor.span;
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.event_span(event) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        let Some(building) = extensions.get_mut::<Building>() else {
+            return;
+        };
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+        let level = event.metadata().level();
+        if *level == Level::ERROR {
+            building.span.status = Some(Status {
+                message: visitor.message.clone(),
+                code: StatusCode::Error as i32,
+            });
+        }
+        visitor
+            .attributes
+            .push(key_value("level", string(level.as_str())));
+        building.span.events.push(span::Event {
+            time_unix_nano: now(),
+            name: visitor.message,
+            attributes: visitor.attributes,
+            dropped_attributes_count: 0,
+        });
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(mut building) = span.extensions_mut().remove::<Building>() else {
+            return;
+        };
+        building.span.end_time_unix_nano = now();
+        // never block the traced code, spans are dropped if the exporter lags
+        let _ = self.tx.try_send(building.span);
+    }
+}
+
+/// Trace context of a span recorded by an `OtelLayer`
+pub(super) fn context_of<S>(
+    span: &tracing_subscriber::registry::SpanRef<'_, S>,
+) -> Option<TraceContext>
+where
+    S: for<'a> LookupSpan<'a>,
+{
+    span.extensions().get::<Building>().map(|b| b.ctx)
+}
+
+struct SpanVisitor {
+    span: Span,
+    remote: Option<TraceContext>,
+}
+
+impl SpanVisitor {
+    fn record(&mut self, field: &Field, value: any_value::Value) {
+        let any_value::Value::StringValue(s) = &value else {
+            self.span.attributes.push(key_value(field.name(), value));
+            return;
+        };
+        match field.name() {
+            "otel.name" => self.span.name = s.clone(),
+            "otel.kind" => self.span.kind = kind(s) as i32,
+            "otel.status_code" if s.eq_ignore_ascii_case("error") => {
+                self.span.status.get_or_insert_with(Default::default).code =
+                    StatusCode::Error as i32;
+            }
+            "otel.status_message" => {
+                self.span
+                    .status
+                    .get_or_insert_with(Default::default)
+                    .message = s.clone();
+            }
+            "traceparent" => self.remote = s.parse().ok(),
+            name => self.span.attributes.push(key_value(name, value)),
+        }
+    }
+}
+
+impl Visit for SpanVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, string(value));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, any_value::Value::IntValue(value));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, any_value::Value::IntValue(value as i64));
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.record(field, any_value::Value::DoubleValue(value));
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.record(field, any_value::Value::BoolValue(value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record(field, string(&format!("{:?}", value)));
+    }
+}
+
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    attributes: Vec<KeyValue>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => self.attributes.push(key_value(name, string(value))),
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.record_str(field, &format!("{:?}", value));
+    }
+}
+
+fn kind(s: &str) -> span::SpanKind {
+    match s {
+        "server" => span::SpanKind::Server,
+        "client" => span::SpanKind::Client,
+        "producer" => span::SpanKind::Producer,
+        "consumer" => span::SpanKind::Consumer,
+        _ => span::SpanKind::Internal,
+    }
+}
+
+fn key_value(key: &str, value: any_value::Value) -> KeyValue {
+    KeyValue {
+        key: key.to_string(),
+        value: Some(AnyValue { value: Some(value) }),
+    }
+}
+
+fn string(s: &str) -> any_value::Value {
+    any_value::Value::StringValue(s.to_string())
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos() as u64
+}
@@ -0,0 +1,218 @@
+mod export;
+mod layer;
+mod propagation;
+
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tonic::transport::Endpoint;
+use tracing::{field, info_span, level_filters::LevelFilter};
+use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+
+use crate::config::Checks;
+
+pub use layer::OtelLayer;
+pub use propagation::{
+    server_layer, TraceContext, TraceInterceptor, TraceLayer, TraceService, TracedChannel,
+};
+
+use crate::pb::opentelemetry::proto::{
+    collector::trace::v1::trace_service_client::TraceServiceClient, trace::v1::Span,
+};
+use export::Exporter;
+
+/// spans waiting for export, newer ones are dropped beyond that
+const QUEUE_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryConfig {
+    /// OTLP/gRPC collector the spans are exported to, e.g. http://localhost:4317
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// append the spans as OTLP/JSON lines to this file instead, for tests
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+}
+
+impl TelemetryConfig {
+    pub fn validate(&self, checks: &mut Checks) {
+        if let Some(url) = &self.otlp_endpoint {
+            checks.url("telemetry.otlp_endpoint", url, &["http", "https"]);
+        }
+    }
+}
+
+/// Handle on the span exporter, `shutdown` flushes what is still queued
+#[derive(Debug)]
+pub struct Telemetry {
+    exporter: Option<(oneshot::Sender<()>, JoinHandle<()>)>,
+}
+
+/// Install the global subscriber: INFO logs on stdout, and the spans exported
+/// to the collector or file in `config`, if any
+pub fn init(service: &str, config: &TelemetryConfig) -> anyhow::Result<Telemetry> {
+    let exporter = match (&config.file, &config.otlp_endpoint) {
+        (Some(path), _) => Some(Exporter::File(path.clone())),
+        (None, Some(url)) => {
+            let endpoint = Endpoint::from_shared(url.clone())
+                .with_context(|| format!("invalid otlp endpoint {}", url))?;
+            Some(Exporter::Otlp(TraceServiceClient::new(
+                endpoint.connect_lazy(),
+            )))
+        }
+        (None, None) => None,
+    };
+    let registry =
+        tracing_subscriber::registry().with(fmt::Layer::default().with_filter(LevelFilter::INFO));
+    let Some(exporter) = exporter else {
+        registry.try_init()?;
+        return Ok(Telemetry { exporter: None });
+    };
+
+    let (tx, rx) = mpsc::channel(QUEUE_SIZE);
+    registry
+        .with(OtelLayer::new(tx).with_filter(LevelFilter::INFO))
+        .try_init()?;
+    Ok(Telemetry::spawn(service, exporter, rx))
+}
+
+/// Client span around a postgres query, named after the query rather than
+/// the sql, record `db.statement` on it for ad hoc sql
+pub fn sql_span(query: &str) -> tracing::Span {
+    info_span!(
+        "sql",
+        otel.name = query,
+        otel.kind = "client",
+        db.system = "postgresql",
+        db.statement = field::Empty,
+    )
+}
+
+impl Telemetry {
+    fn spawn(service: &str, exporter: Exporter, rx: mpsc::Receiver<Span>) -> Self {
+        let (stop, stopped) = oneshot::channel();
+        let handle = tokio::spawn(export::run(service.to_string(), exporter, rx, stopped));
+        Self {
+            exporter: Some((stop, handle)),
+        }
+    }
+
+    /// Export the spans closed so far, call it last thing before exiting
+    pub async fn shutdown(self) {
+        if let Some((stop, handle)) = self.exporter {
+            let _ = stop.send(());
+            let _ = handle.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use serde_json::Value;
+    use tracing::{info, info_span, subscriber, warn, Instrument};
+    use tracing_subscriber::Registry;
+
+    use super::*;
+    use crate::pb::opentelemetry::proto::trace::v1::span::SpanKind;
+
+    fn recording() -> (impl tracing::Subscriber + Send + Sync, mpsc::Receiver<Span>) {
+        let (tx, rx) = mpsc::channel(16);
+        (Registry::default().with(OtelLayer::new(tx)), rx)
+    }
+
+    #[test]
+    fn traceparent_should_round_trip() {
+        let s = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let ctx = s.parse::<TraceContext>().unwrap();
+        assert_eq!(
+            ctx.span_id,
+            [0x00, 0xf0, 0x67, 0xaa, 0x0b, 0xa9, 0x02, 0xb7]
+        );
+        assert_eq!(ctx.flags, 1);
+        assert_eq!(ctx.to_string(), s);
+
+        for invalid in [
+            "",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7",
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e473-00f067aa0ba902b7-01",
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902bx-01",
+        ] {
+            assert!(invalid.parse::<TraceContext>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn spans_should_link_to_their_parent() {
+        let (subscriber, mut rx) = recording();
+        let remote = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let (header, child_ctx) = subscriber::with_default(subscriber, || {
+            let server = info_span!("grpc.server", otel.kind = "server", traceparent = remote);
+            let _enter = server.enter();
+            let client = info_span!("sql", otel.name = "SELECT users", db.system = "postgresql");
+            let _enter = client.enter();
+            warn!(rows = 0, "no rows");
+            let mut request = tonic::Request::new(());
+            request = tonic::service::Interceptor::call(&mut TraceInterceptor, request).unwrap();
+            let header = request.metadata().get("traceparent").cloned();
+            (header, TraceContext::current().unwrap())
+        });
+
+        let child = rx.try_recv().unwrap();
+        let server = rx.try_recv().unwrap();
+        let remote = remote.parse::<TraceContext>().unwrap();
+        assert_eq!(server.trace_id, remote.trace_id);
+        assert_eq!(server.parent_span_id, remote.span_id);
+        assert_eq!(server.kind, SpanKind::Server as i32);
+        assert_eq!(child.name, "SELECT users");
+        assert_eq!(child.trace_id, remote.trace_id);
+        assert_eq!(child.parent_span_id, server.span_id);
+        assert_eq!(child.span_id, child_ctx.span_id);
+        assert_eq!(child.attributes[0].key, "db.system");
+        assert_eq!(child.events[0].name, "no rows");
+        assert_eq!(header.unwrap().to_str().unwrap(), child_ctx.to_string());
+
+        // no span, no header
+        let (subscriber, _rx) = recording();
+        subscriber::with_default(subscriber, || assert!(TraceContext::current().is_none()));
+    }
+
+    #[tokio::test]
+    async fn file_exporter_should_write_otlp_json() {
+        let path =
+            std::env::temp_dir().join(format!("crm-core-spans-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let (subscriber, rx) = recording();
+        let telemetry = Telemetry::spawn("test", Exporter::File(path.clone()), rx);
+        let _guard = subscriber::set_default(subscriber);
+        async {
+            info!("working");
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+        .instrument(info_span!("work", job = 7))
+        .await;
+        telemetry.shutdown().await;
+
+        let content = fs::read_to_string(&path).unwrap();
+        let line: Value = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        let resource_spans = &line["resourceSpans"][0];
+        assert_eq!(
+            resource_spans["resource"]["attributes"][0]["value"]["stringValue"],
+            "test"
+        );
+        let span = &resource_spans["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["name"], "work");
+        assert_eq!(span["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(span["attributes"][0]["value"]["intValue"], "7");
+        assert_eq!(span["events"][0]["name"], "working");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,194 @@
+use std::{
+    fmt::{self, Display, Write as _},
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use http::Request;
+use tonic::{
+    metadata::MetadataValue, service::interceptor::InterceptedService, service::Interceptor,
+    transport::Channel, Status,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::{info_span, instrument::Instrumented, Instrument, Span};
+use tracing_subscriber::{registry::LookupSpan, Registry};
+
+use super::layer::context_of;
+
+const TRACEPARENT: &str = "traceparent";
+
+/// A channel sending the trace context of the calling span along every request
+pub type TracedChannel = InterceptedService<Channel, TraceInterceptor>;
+
+/// W3C trace context, as carried by the `traceparent` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+    pub flags: u8,
+}
+
+impl TraceContext {
+    /// Start a new trace, sampled
+    pub(super) fn new_root() -> Self {
+        Self {
+            trace_id: random_id(),
+            span_id: random_id(),
+            flags: 1,
+        }
+    }
+
+    /// A new span in the same trace
+    pub(super) fn child(&self) -> Self {
+        Self {
+            span_id: random_id(),
+            ..*self
+        }
+    }
+
+    /// Context of the current span, none outside of a span or without `OtelLayer`
+    pub fn current() -> Option<Self> {
+        Span::current()
+            .with_subscriber(|(id, dispatch)| {
+                let registry = dispatch.downcast_ref::<Registry>()?;
+                context_of(&registry.span(id)?)
+            })
+            .flatten()
+    }
+}
+
+impl Display for TraceContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "00-{}-{}-{:02x}",
+            hex(&self.trace_id),
+            hex(&self.span_id),
+            self.flags
+        )
+    }
+}
+
+impl FromStr for TraceContext {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s.trim().split('-').collect::<Vec<_>>();
+        let [version, trace_id, span_id, flags] = parts[..] else {
+            return Err("traceparent must have 4 parts");
+        };
+        // future versions may append fields, but ff is forbidden
+        if version.len() != 2 || version == "ff" {
+            return Err("unsupported traceparent version");
+        }
+        let ctx = Self {
+            trace_id: unhex(trace_id).ok_or("invalid trace id")?,
+            span_id: unhex(span_id).ok_or("invalid span id")?,
+            flags: unhex::<1>(flags).ok_or("invalid trace flags")?[0],
+        };
+        if ctx.trace_id == [0; 16] || ctx.span_id == [0; 8] {
+            return Err("all zero trace or span id");
+        }
+        Ok(ctx)
+    }
+}
+
+/// Client interceptor adding the `traceparent` of the current span to requests,
+/// e.g. `UserStatsClient::with_interceptor(channel, TraceInterceptor)`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceInterceptor;
+
+impl Interceptor for TraceInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
+        if let Some(ctx) = TraceContext::current() {
+            if let Ok(value) = MetadataValue::try_from(ctx.to_string()) {
+                request.metadata_mut().insert(TRACEPARENT, value);
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Server side counterpart of `TraceInterceptor`: every RPC runs in a server
+/// span continuing the caller's trace, add it with `Server::builder().layer(..)`
+pub fn server_layer() -> TraceLayer {
+    TraceLayer
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TraceLayer;
+
+#[derive(Debug, Clone)]
+pub struct TraceService<S> {
+    inner: S,
+}
+
+impl<S> Layer<S> for TraceLayer {
+    type Service = TraceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceService { inner }
+    }
+}
+
+impl<S, B> Service<Request<B>> for TraceService<S>
+where
+    S: Service<Request<B>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        let path = req.uri().path().trim_start_matches('/');
+        let (service, method) = path.split_once('/').unwrap_or((path, ""));
+        let traceparent = req
+            .headers()
+            .get(TRACEPARENT)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+        let span = info_span!(
+            "grpc.server",
+            otel.name = path,
+            otel.kind = "server",
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method,
+            traceparent = traceparent,
+        );
+        self.inner.call(req).instrument(span)
+    }
+}
+
+fn random_id<const N: usize>() -> [u8; N] {
+    loop {
+        let mut id = [0; N];
+        id.iter_mut().for_each(|b| *b = rand::random());
+        if id != [0; N] {
+            return id;
+        }
+    }
+}
+
+pub(super) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+fn unhex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    if s.len() != N * 2 {
+        return None;
+    }
+    let mut bytes = [0; N];
+    for (i, b) in bytes.iter_mut().enumerate() {
+        *b = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(bytes)
+}
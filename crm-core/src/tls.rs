@@ -0,0 +1,286 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{Context as _, Result};
+use serde::{Deserialize, Serialize};
+use tonic::{
+    service::{interceptor::InterceptorLayer, Interceptor},
+    transport::{
+        server::{TcpConnectInfo, TlsConnectInfo, UdsConnectInfo},
+        Certificate, CertificateDer, ClientTlsConfig, Identity, ServerTlsConfig,
+    },
+    Request, Status,
+};
+use webpki::EndEntityCert;
+
+use crate::config::Checks;
+
+/// TLS of a service, used both for its listener and its calls to other
+/// services: `cert` and `key` identify the service, `ca` signs its peers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain, leaf first
+    pub cert: PathBuf,
+    /// PEM private key of the leaf certificate
+    pub key: PathBuf,
+    /// PEM certificates of the CA the peers' certificates are checked against
+    pub ca: PathBuf,
+    /// require callers to present a certificate signed by `ca`
+    #[serde(default)]
+    pub mtls: bool,
+    /// DNS names a caller certificate must have one of, any caller signed by
+    /// `ca` is accepted when empty. Only checked with `mtls`.
+    #[serde(default)]
+    pub allowed_callers: Vec<String>,
+}
+
+impl TlsConfig {
+    pub fn validate(&self, path: &str, checks: &mut Checks) {
+        for (field, file) in [("cert", &self.cert), ("ca", &self.ca)] {
+            let path = format!("{}.{}", path, field);
+            match read_certs(file) {
+                Ok(certs) => checks.check(&path, !certs.is_empty(), "no certificate found"),
+                Err(e) => checks.check(&path, false, &format!("{:#}", e)),
+            }
+        }
+        let key = format!("{}.key", path);
+        match read_key(&self.key) {
+            Ok(found) => checks.check(&key, found, "no private key found"),
+            Err(e) => checks.check(&key, false, &format!("{:#}", e)),
+        }
+        checks.check(
+            &format!("{}.allowed_callers", path),
+            self.mtls || self.allowed_callers.is_empty(),
+            "requires mtls",
+        );
+    }
+
+    /// TLS of the listener, client certificates are required with `mtls`
+    pub fn server_config(&self) -> Result<ServerTlsConfig> {
+        let config = ServerTlsConfig::new().identity(self.identity()?);
+        if !self.mtls {
+            return Ok(config);
+        }
+        Ok(config.client_ca_root(Certificate::from_pem(read(&self.ca)?)))
+    }
+
+    /// TLS of the calls to other services, presenting this service's certificate
+    pub fn client_config(&self) -> Result<ClientTlsConfig> {
+        Ok(ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(read(&self.ca)?))
+            .identity(self.identity()?))
+    }
+
+    /// Rejects the callers not in `allowed_callers`, a no-op without mtls or
+    /// with an empty list
+    pub fn allowed_callers(&self) -> AllowedCallers {
+        if !self.mtls {
+            return AllowedCallers::default();
+        }
+        AllowedCallers::new(self.allowed_callers.iter().cloned())
+    }
+
+    fn identity(&self) -> Result<Identity> {
+        Ok(Identity::from_pem(read(&self.cert)?, read(&self.key)?))
+    }
+}
+
+/// Interceptor mapping the client certificate to a caller identity, its DNS
+/// names, and checking it against an allow list.
+///
+/// The handshake already verified the certificate against the CA, only the
+/// identity is checked here.
+#[derive(Debug, Clone, Default)]
+pub struct AllowedCallers {
+    names: Arc<HashSet<String>>,
+}
+
+impl AllowedCallers {
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            names: Arc::new(names.into_iter().collect()),
+        }
+    }
+
+    pub fn into_layer(self) -> InterceptorLayer<Self> {
+        tonic::service::interceptor(self)
+    }
+
+    fn allows(&self, cert: &CertificateDer<'_>) -> bool {
+        match EndEntityCert::try_from(cert) {
+            Ok(cert) => cert.valid_dns_names().any(|name| self.names.contains(name)),
+            Err(_) => false,
+        }
+    }
+}
+
+impl Interceptor for AllowedCallers {
+    fn call(&mut self, request: Request<()>) -> Result<Request<()>, Status> {
+        if self.names.is_empty() {
+            return Ok(request);
+        }
+        let Some(certs) = peer_certs(&request) else {
+            return Err(Status::unauthenticated("client certificate required"));
+        };
+        match certs.first() {
+            Some(cert) if self.allows(cert) => Ok(request),
+            _ => Err(Status::permission_denied("caller not allowed")),
+        }
+    }
+}
+
+/// Certificates the client presented, over tcp or a unix socket
+fn peer_certs(request: &Request<()>) -> Option<Arc<Vec<CertificateDer<'static>>>> {
+    let extensions = request.extensions();
+    if let Some(info) = extensions.get::<TlsConnectInfo<TcpConnectInfo>>() {
+        return info.peer_certs();
+    }
+    extensions
+        .get::<TlsConnectInfo<UdsConnectInfo>>()
+        .and_then(|info| info.peer_certs())
+}
+
+fn read(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).with_context(|| format!("failed to read {}", path.display()))
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = read(path)?;
+    rustls_pemfile::certs(&mut BufReader::new(pem.as_slice()))
+        .collect::<Result<_, _>>()
+        .context("invalid PEM")
+}
+
+fn read_key(path: &Path) -> Result<bool> {
+    let pem = read(path)?;
+    let key =
+        rustls_pemfile::private_key(&mut BufReader::new(pem.as_slice())).context("invalid PEM")?;
+    Ok(key.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use tonic::{
+        transport::{Channel, Endpoint},
+        Code,
+    };
+
+    use super::*;
+    use crate::{
+        pb::grpc::health::v1::{
+            health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+        },
+        HealthService, ListenConfig,
+    };
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/tls")
+            .join(name)
+    }
+
+    fn config(name: &str) -> TlsConfig {
+        TlsConfig {
+            cert: fixture(&format!("{}.pem", name)),
+            key: fixture(&format!("{}.key", name)),
+            ca: fixture("ca.pem"),
+            mtls: true,
+            allowed_callers: vec!["crm".to_string()],
+        }
+    }
+
+    async fn serve(tls: &TlsConfig) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (builder, callers) = ListenConfig::default().server(Some(tls)).unwrap();
+        let server = builder
+            .layer(callers.into_layer())
+            .add_service(HealthService::builder().build().into_server());
+        tokio::spawn(server.serve_with_incoming(TcpListenerStream::new(listener)));
+        addr
+    }
+
+    async fn check(addr: SocketAddr, client: Option<&str>) -> Result<ServingStatus, Status> {
+        let mut tls = ClientTlsConfig::new()
+            .ca_certificate(Certificate::from_pem(fs::read(fixture("ca.pem")).unwrap()))
+            .domain_name("localhost");
+        if let Some(client) = client {
+            tls = config(client)
+                .client_config()
+                .unwrap()
+                .domain_name("localhost");
+        }
+        let channel: Channel = Endpoint::from_shared(format!("https://{}", addr))
+            .unwrap()
+            .tls_config(tls)
+            .unwrap()
+            .connect()
+            .await
+            .map_err(|e| Status::unavailable(e.to_string()))?;
+        let res = HealthClient::new(channel)
+            .check(HealthCheckRequest::default())
+            .await?;
+        Ok(res.into_inner().status())
+    }
+
+    #[test]
+    fn validate_should_check_the_pem_files() {
+        let mut checks = Checks::default();
+        config("server").validate("server.tls", &mut checks);
+        assert!(checks.into_result().is_ok());
+
+        let mut checks = Checks::default();
+        let mut tls = config("server");
+        tls.cert = fixture("server.key");
+        tls.key = fixture("missing.key");
+        tls.mtls = false;
+        tls.validate("server.tls", &mut checks);
+        let err = checks.into_result().unwrap_err().to_string();
+        assert!(
+            err.contains("server.tls.cert: no certificate found"),
+            "{}",
+            err
+        );
+        assert!(err.contains("server.tls.key: failed to read"), "{}", err);
+        assert!(
+            err.contains("server.tls.allowed_callers: requires mtls"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn mtls_should_only_let_allowed_callers_in() {
+        let addr = serve(&config("server")).await;
+        assert_eq!(
+            check(addr, Some("crm")).await.unwrap(),
+            ServingStatus::Serving
+        );
+        let err = check(addr, Some("intruder")).await.unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+        // no client certificate, the handshake fails
+        assert!(check(addr, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tls_without_mtls_should_accept_any_client() {
+        let mut tls = config("server");
+        tls.mtls = false;
+        tls.allowed_callers.clear();
+        let addr = serve(&tls).await;
+        assert_eq!(check(addr, None).await.unwrap(), ServingStatus::Serving);
+        assert_eq!(
+            check(addr, Some("intruder")).await.unwrap(),
+            ServingStatus::Serving
+        );
+    }
+}
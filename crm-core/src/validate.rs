@@ -0,0 +1,319 @@
+// the errors are the statuses handlers return as is
+#![allow(clippy::result_large_err)]
+
+use prost_types::Timestamp;
+use tonic::{Request, Status};
+use url::Url;
+
+use crate::error::{bad_request, FieldViolation};
+
+/// 0001-01-01T00:00:00Z, the first timestamp protobuf allows
+const MIN_SECONDS: i64 = -62_135_596_800;
+/// 9999-12-31T23:59:59Z, the last one
+const MAX_SECONDS: i64 = 253_402_300_799;
+
+/// Checks of a request message, run before its handler so that it only deals
+/// with well formed input
+pub trait Validate {
+    /// Record the invalid fields of the message
+    fn validate(&self, v: &mut Violations);
+
+    fn is_valid(&self) -> bool {
+        let mut v = Violations::default();
+        self.validate(&mut v);
+        v.is_empty()
+    }
+
+    /// The message, or `invalid_argument` of `domain` listing all its invalid
+    /// fields
+    fn validated(self, domain: &str) -> Result<Self, Status>
+    where
+        Self: Sized,
+    {
+        let mut v = Violations::default();
+        self.validate(&mut v);
+        v.into_result(domain)?;
+        Ok(self)
+    }
+}
+
+/// Message of `request` once validated
+pub fn valid<T: Validate>(domain: &str, request: Request<T>) -> Result<T, Status> {
+    request.into_inner().validated(domain)
+}
+
+/// Invalid fields found while validating a message, by their path from the
+/// root of the request, e.g. `users[0].email`
+#[derive(Debug, Default)]
+pub struct Violations {
+    prefix: String,
+    violations: Vec<FieldViolation>,
+}
+
+impl Violations {
+    pub fn is_empty(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    pub fn into_vec(self) -> Vec<FieldViolation> {
+        self.violations
+    }
+
+    pub fn into_result(self, domain: &str) -> Result<(), Status> {
+        if self.violations.is_empty() {
+            return Ok(());
+        }
+        Err(bad_request(domain, self.violations))
+    }
+
+    pub fn check(&mut self, field: &str, ok: bool, description: &str) {
+        if !ok {
+            let field = self.path(field);
+            self.violations
+                .push(FieldViolation::new(field, description));
+        }
+    }
+
+    pub fn required(&mut self, field: &str, value: &str) {
+        self.check(field, !value.trim().is_empty(), "is required");
+    }
+
+    pub fn not_empty<T>(&mut self, field: &str, values: &[T]) {
+        self.check(field, !values.is_empty(), "must not be empty");
+    }
+
+    pub fn positive(&mut self, field: &str, value: impl Into<u64>) {
+        self.check(field, value.into() > 0, "must be greater than 0");
+    }
+
+    /// At most `max` items, e.g. the ids of a batch
+    pub fn max_items<T>(&mut self, field: &str, values: &[T], max: usize) {
+        let description = format!("must have at most {} items", max);
+        self.check(field, values.len() <= max, &description);
+    }
+
+    /// Fits the `int` of postgres, ids are stored as such
+    pub fn db_id(&mut self, field: &str, value: u32) {
+        self.check(field, i32::try_from(value).is_ok(), "is out of range");
+    }
+
+    pub fn email(&mut self, field: &str, value: &str) {
+        if value.is_empty() {
+            return self.check(field, false, "is required");
+        }
+        self.check(field, is_email(value), "must be a valid email");
+    }
+
+    /// E.164, e.g. +8613800138000
+    pub fn phone(&mut self, field: &str, value: &str) {
+        if value.is_empty() {
+            return self.check(field, false, "is required");
+        }
+        self.check(field, is_phone(value), "must be a valid E.164 phone number");
+    }
+
+    /// Absolute http(s) url, the only ones the clients can render
+    pub fn url(&mut self, field: &str, value: &str) {
+        let ok = match Url::parse(value) {
+            Ok(url) => matches!(url.scheme(), "http" | "https") && url.has_host(),
+            Err(_) => false,
+        };
+        self.check(field, ok, "must be a valid http(s) url");
+    }
+
+    /// A value of the enum `E` other than its unspecified 0
+    pub fn specified<E: TryFrom<i32>>(&mut self, field: &str, value: i32) {
+        if value == 0 {
+            return self.check(field, false, "is required");
+        }
+        self.known::<E>(field, value);
+    }
+
+    /// A value of the enum `E`, unspecified included
+    pub fn known<E: TryFrom<i32>>(&mut self, field: &str, value: i32) {
+        let description = format!("unknown value {}", value);
+        self.check(field, E::try_from(value).is_ok(), &description);
+    }
+
+    /// Within the range of protobuf timestamps, if set
+    pub fn timestamp(&mut self, field: &str, value: Option<&Timestamp>) {
+        let ok = value.is_none_or(|ts| {
+            (MIN_SECONDS..=MAX_SECONDS).contains(&ts.seconds)
+                && (0..1_000_000_000).contains(&ts.nanos)
+        });
+        self.check(field, ok, "is out of range");
+    }
+
+    /// Validate a nested message, its fields are reported under `field`
+    pub fn nested<T: Validate>(&mut self, field: &str, message: &T) {
+        let path = self.path(field);
+        let prefix = std::mem::replace(&mut self.prefix, path);
+        message.validate(self);
+        self.prefix = prefix;
+    }
+
+    /// Validate every message of a repeated field, reported under `field[i]`
+    pub fn each<T: Validate>(&mut self, field: &str, messages: &[T]) {
+        for (i, message) in messages.iter().enumerate() {
+            self.nested(&format!("{}[{}]", field, i), message);
+        }
+    }
+
+    /// An empty `field` is the message being validated itself
+    fn path(&self, field: &str) -> String {
+        match (self.prefix.is_empty(), field.is_empty()) {
+            (true, _) => field.to_string(),
+            (false, true) => self.prefix.clone(),
+            (false, false) => format!("{}.{}", self.prefix, field),
+        }
+    }
+}
+
+fn is_email(value: &str) -> bool {
+    let Some((local, domain)) = value.split_once('@') else {
+        return false;
+    };
+    let labels = domain.split('.').collect::<Vec<_>>();
+    !local.is_empty()
+        && !local
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_control() || c == '@')
+        && labels.len() >= 2
+        && labels.iter().all(|label| {
+            !label.is_empty() && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        })
+}
+
+fn is_phone(value: &str) -> bool {
+    let Some(digits) = value.strip_prefix('+') else {
+        return false;
+    };
+    (8..=15).contains(&digits.len())
+        && !digits.starts_with('0')
+        && digits.chars().all(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Code;
+
+    use super::*;
+    use crate::ErrorDetails;
+
+    struct Recipient {
+        email: String,
+        phone: String,
+    }
+
+    struct Message {
+        subject: String,
+        to: Vec<Recipient>,
+        sent_at: Option<Timestamp>,
+    }
+
+    impl Validate for Recipient {
+        fn validate(&self, v: &mut Violations) {
+            v.email("email", &self.email);
+            v.phone("phone", &self.phone);
+        }
+    }
+
+    impl Validate for Message {
+        fn validate(&self, v: &mut Violations) {
+            v.required("subject", &self.subject);
+            v.not_empty("to", &self.to);
+            v.each("to", &self.to);
+            v.timestamp("sent_at", self.sent_at.as_ref());
+        }
+    }
+
+    #[test]
+    fn violations_should_have_the_path_of_the_field() {
+        let message = Message {
+            subject: " ".to_string(),
+            to: vec![
+                Recipient {
+                    email: "alice@example.com".to_string(),
+                    phone: "+8613800138000".to_string(),
+                },
+                Recipient {
+                    email: "bob@".to_string(),
+                    phone: "13800138000".to_string(),
+                },
+            ],
+            sent_at: Some(Timestamp {
+                seconds: 0,
+                nanos: -1,
+            }),
+        };
+        let status = message.validated("test").err().unwrap();
+        assert_eq!(status.code(), Code::InvalidArgument);
+        let details = ErrorDetails::from_status(&status);
+        assert_eq!(
+            details.fields(),
+            vec!["subject", "to[1].email", "to[1].phone", "sent_at"]
+        );
+        assert_eq!(details.info.unwrap().domain, "test");
+        assert!(status
+            .message()
+            .contains("to[1].email: must be a valid email"));
+
+        let message = Message {
+            subject: "hi".to_string(),
+            to: vec![],
+            sent_at: None,
+        };
+        let details = ErrorDetails::from_status(&message.validated("test").err().unwrap());
+        assert_eq!(details.fields(), vec!["to"]);
+    }
+
+    #[test]
+    fn emails_and_phones_should_be_checked() {
+        for email in [
+            "a@example.com",
+            "first.last+tag@mail.example.co",
+            "张三@例子.中国",
+        ] {
+            assert!(is_email(email), "{}", email);
+        }
+        for email in [
+            "",
+            "a",
+            "a@b",
+            "@example.com",
+            "a@example..com",
+            "a b@example.com",
+        ] {
+            assert!(!is_email(email), "{}", email);
+        }
+        assert!(is_phone("+14155552671"));
+        for phone in ["14155552671", "+0123456789", "+1415", "+1415555abcd"] {
+            assert!(!is_phone(phone), "{}", phone);
+        }
+    }
+
+    #[test]
+    fn enums_should_be_specified_and_known() {
+        #[derive(Debug)]
+        struct Kind;
+        impl TryFrom<i32> for Kind {
+            type Error = ();
+            fn try_from(value: i32) -> Result<Self, ()> {
+                (0..3).contains(&value).then_some(Kind).ok_or(())
+            }
+        }
+        let mut v = Violations::default();
+        v.specified::<Kind>("a", 0);
+        v.specified::<Kind>("b", 1);
+        v.known::<Kind>("c", 0);
+        v.known::<Kind>("d", 7);
+        let fields = v.into_vec();
+        assert_eq!(
+            fields,
+            vec![
+                FieldViolation::new("a", "is required"),
+                FieldViolation::new("d", "unknown value 7"),
+            ]
+        );
+    }
+}
@@ -1,11 +1,13 @@
 use proto_builder_trait::tonic::BuilderAttributes;
-use std::fs;
+use std::{env, fs, path::PathBuf};
 fn main() -> anyhow::Result<()> {
     fs::create_dir_all("src/pb")?;
+    let descriptor = PathBuf::from(env::var("OUT_DIR")?).join("crm_metadata_descriptor.bin");
     let builder = tonic_build::configure();
 
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(&descriptor)
         .with_type_attributes(&["MaterializeRequest"], &[r#"#[derive(Eq, Hash)]"#])
         .compile_protos(
             &[
@@ -0,0 +1,175 @@
+INSERT INTO publishers(name, avatar)
+  VALUES ('Alice Chen', 'https://placehold.co/400x400?text=1');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Bob Li', 'https://placehold.co/400x400?text=2');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Carol Wang', 'https://placehold.co/400x400?text=3');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('David Zhang', 'https://placehold.co/400x400?text=4');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Eve Liu', 'https://placehold.co/400x400?text=5');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Frank Zhao', 'https://placehold.co/400x400?text=6');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Grace Sun', 'https://placehold.co/400x400?text=7');
+
+INSERT INTO publishers(name, avatar)
+  VALUES ('Henry Zhou', 'https://placehold.co/400x400?text=8');
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Science and travel episode 1', 'A vlog about science with some travel on the side', 'https://example.com/contents/1', 'https://placehold.co/1600x900?text=1', 'vlog', '2024-05-03T12:00:00+00:00', 85319, 791, 18);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Rust and movie episode 2', 'A movie about rust with some movie on the side', 'https://example.com/contents/2', 'https://placehold.co/1600x900?text=2', 'movie', '2024-04-30T18:00:00+00:00', 4914, 44, 6);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Cooking and movie episode 3', 'A ai generated about cooking with some movie on the side', 'https://example.com/contents/3', 'https://placehold.co/1600x900?text=3', 'ai_generated', '2024-04-27T11:00:00+00:00', 7747, 579, 15);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Coding and history episode 4', 'A short about coding with some history on the side', 'https://example.com/contents/4', 'https://placehold.co/1600x900?text=4', 'short', '2024-04-24T23:00:00+00:00', 28977, 190, 35);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and travel episode 5', 'A vlog about history with some travel on the side', 'https://example.com/contents/5', 'https://placehold.co/1600x900?text=5', 'vlog', '2024-04-21T07:00:00+00:00', 15439, 1169, 78);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Coding and music episode 6', 'A movie about coding with some music on the side', 'https://example.com/contents/6', 'https://placehold.co/1600x900?text=6', 'movie', '2024-04-18T13:00:00+00:00', 12770, 1121, 182);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Coding and music episode 7', 'A ai generated about coding with some music on the side', 'https://example.com/contents/7', 'https://placehold.co/1600x900?text=7', 'ai_generated', '2024-04-15T09:00:00+00:00', 89181, 8711, 875);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Gaming and science episode 8', 'A short about gaming with some science on the side', 'https://example.com/contents/8', 'https://placehold.co/1600x900?text=8', 'short', '2024-04-12T15:00:00+00:00', 32561, 3253, 184);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Football and movie episode 9', 'A vlog about football with some movie on the side', 'https://example.com/contents/9', 'https://placehold.co/1600x900?text=9', 'vlog', '2024-04-09T09:00:00+00:00', 45020, 3676, 294);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and travel episode 10', 'A movie about history with some travel on the side', 'https://example.com/contents/10', 'https://placehold.co/1600x900?text=10', 'movie', '2024-04-06T14:00:00+00:00', 19920, 1911, 250);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Cooking and movie episode 11', 'A ai generated about cooking with some movie on the side', 'https://example.com/contents/11', 'https://placehold.co/1600x900?text=11', 'ai_generated', '2024-04-03T06:00:00+00:00', 41123, 2786, 358);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Cooking and coding episode 12', 'A short about cooking with some coding on the side', 'https://example.com/contents/12', 'https://placehold.co/1600x900?text=12', 'short', '2024-03-31T16:00:00+00:00', 62141, 5710, 133);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Coding and gaming episode 13', 'A vlog about coding with some gaming on the side', 'https://example.com/contents/13', 'https://placehold.co/1600x900?text=13', 'vlog', '2024-03-28T15:00:00+00:00', 93929, 6320, 710);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Science and travel episode 14', 'A movie about science with some travel on the side', 'https://example.com/contents/14', 'https://placehold.co/1600x900?text=14', 'movie', '2024-03-25T05:00:00+00:00', 15347, 1011, 15);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Travel and music episode 15', 'A ai generated about travel with some music on the side', 'https://example.com/contents/15', 'https://placehold.co/1600x900?text=15', 'ai_generated', '2024-03-22T12:00:00+00:00', 51242, 4067, 82);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and movie episode 16', 'A short about history with some movie on the side', 'https://example.com/contents/16', 'https://placehold.co/1600x900?text=16', 'short', '2024-03-19T16:00:00+00:00', 17947, 1677, 220);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Science and history episode 17', 'A vlog about science with some history on the side', 'https://example.com/contents/17', 'https://placehold.co/1600x900?text=17', 'vlog', '2024-03-16T17:00:00+00:00', 19781, 169, 11);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Music and rust episode 18', 'A movie about music with some rust on the side', 'https://example.com/contents/18', 'https://placehold.co/1600x900?text=18', 'movie', '2024-03-13T09:00:00+00:00', 77217, 1493, 134);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and movie episode 19', 'A ai generated about history with some movie on the side', 'https://example.com/contents/19', 'https://placehold.co/1600x900?text=19', 'ai_generated', '2024-03-10T13:00:00+00:00', 79929, 4639, 326);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Gaming and movie episode 20', 'A short about gaming with some movie on the side', 'https://example.com/contents/20', 'https://placehold.co/1600x900?text=20', 'short', '2024-03-07T12:00:00+00:00', 52175, 3268, 403);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and rust episode 21', 'A vlog about history with some rust on the side', 'https://example.com/contents/21', 'https://placehold.co/1600x900?text=21', 'vlog', '2024-03-04T18:00:00+00:00', 8827, 213, 28);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Science and rust episode 22', 'A movie about science with some rust on the side', 'https://example.com/contents/22', 'https://placehold.co/1600x900?text=22', 'movie', '2024-03-01T21:00:00+00:00', 30, 4, 0);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Rust and cooking episode 23', 'A ai generated about rust with some cooking on the side', 'https://example.com/contents/23', 'https://placehold.co/1600x900?text=23', 'ai_generated', '2024-02-27T18:00:00+00:00', 80487, 3082, 152);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Science and gaming episode 24', 'A short about science with some gaming on the side', 'https://example.com/contents/24', 'https://placehold.co/1600x900?text=24', 'short', '2024-02-24T21:00:00+00:00', 15119, 999, 119);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Cooking and travel episode 25', 'A vlog about cooking with some travel on the side', 'https://example.com/contents/25', 'https://placehold.co/1600x900?text=25', 'vlog', '2024-02-21T21:00:00+00:00', 98261, 5613, 542);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Rust and music episode 26', 'A movie about rust with some music on the side', 'https://example.com/contents/26', 'https://placehold.co/1600x900?text=26', 'movie', '2024-02-18T08:00:00+00:00', 47415, 1200, 176);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Football and cooking episode 27', 'A ai generated about football with some cooking on the side', 'https://example.com/contents/27', 'https://placehold.co/1600x900?text=27', 'ai_generated', '2024-02-15T02:00:00+00:00', 34224, 2123, 187);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Music and movie episode 28', 'A short about music with some movie on the side', 'https://example.com/contents/28', 'https://placehold.co/1600x900?text=28', 'short', '2024-02-12T07:00:00+00:00', 65889, 2700, 228);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('History and music episode 29', 'A vlog about history with some music on the side', 'https://example.com/contents/29', 'https://placehold.co/1600x900?text=29', 'vlog', '2024-02-09T18:00:00+00:00', 67847, 4036, 364);
+
+INSERT INTO contents(name, description, url, image, content_type, created_at, views, likes, dislikes)
+  VALUES ('Gaming and football episode 30', 'A movie about gaming with some football on the side', 'https://example.com/contents/30', 'https://placehold.co/1600x900?text=30', 'movie', '2024-02-06T18:00:00+00:00', 90770, 5640, 915);
+
+INSERT INTO content_publishers(content_id, publisher_id)
+  VALUES (1, 2),
+    (1, 3),
+    (1, 5),
+    (2, 2),
+    (2, 8),
+    (3, 1),
+    (4, 5),
+    (5, 1),
+    (5, 3),
+    (5, 5),
+    (6, 1),
+    (7, 5),
+    (7, 8),
+    (8, 1),
+    (8, 4),
+    (8, 5),
+    (9, 1),
+    (9, 2),
+    (9, 5),
+    (10, 1),
+    (10, 6),
+    (11, 4),
+    (11, 5),
+    (11, 8),
+    (12, 5),
+    (13, 8),
+    (14, 5),
+    (15, 8),
+    (16, 4),
+    (16, 5),
+    (16, 6),
+    (17, 4),
+    (18, 1),
+    (18, 2),
+    (19, 1),
+    (20, 8),
+    (21, 2),
+    (22, 2),
+    (22, 3),
+    (22, 5),
+    (23, 3),
+    (23, 5),
+    (23, 8),
+    (24, 3),
+    (24, 8),
+    (25, 3),
+    (25, 5),
+    (26, 1),
+    (26, 5),
+    (26, 7),
+    (27, 6),
+    (28, 2),
+    (28, 4),
+    (28, 7),
+    (29, 1),
+    (29, 3),
+    (29, 8),
+    (30, 1),
+    (30, 3),
+    (30, 6);
@@ -0,0 +1,34 @@
+-- Add migration script here
+CREATE TYPE content_type AS ENUM(
+  'unspecified',
+  'short',
+  'vlog',
+  'movie',
+  'ai_generated'
+);
+
+CREATE TABLE publishers(
+  id serial PRIMARY KEY,
+  name varchar(64) NOT NULL,
+  avatar varchar(512) NOT NULL DEFAULT ''
+);
+
+CREATE TABLE contents(
+  id serial PRIMARY KEY,
+  name varchar(256) NOT NULL,
+  description text NOT NULL DEFAULT '',
+  url varchar(512) NOT NULL,
+  image varchar(512) NOT NULL,
+  content_type content_type NOT NULL,
+  created_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  views bigint NOT NULL DEFAULT 0,
+  likes bigint NOT NULL DEFAULT 0,
+  dislikes bigint NOT NULL DEFAULT 0,
+  search tsvector GENERATED ALWAYS AS (to_tsvector('simple', name || ' ' || description)) STORED
+);
+
+CREATE TABLE content_publishers(
+  content_id int NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+  publisher_id int NOT NULL REFERENCES publishers(id) ON DELETE CASCADE,
+  PRIMARY KEY (content_id, publisher_id)
+);
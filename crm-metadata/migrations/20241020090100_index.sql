@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE INDEX contents_content_type_idx ON contents(content_type);
+
+CREATE INDEX contents_created_at_idx ON contents(created_at);
+
+CREATE INDEX contents_views_idx ON contents(views);
+
+CREATE INDEX contents_likes_idx ON contents(likes);
+
+CREATE INDEX contents_search_idx ON contents USING GIN(search);
+
+CREATE INDEX content_publishers_publisher_id_idx ON content_publishers(publisher_id);
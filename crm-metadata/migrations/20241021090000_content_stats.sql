@@ -0,0 +1,12 @@
+-- Add migration script here
+-- counters per content and UTC day, the totals stay in contents
+CREATE TABLE content_stats_daily(
+  content_id int NOT NULL REFERENCES contents(id) ON DELETE CASCADE,
+  day date NOT NULL,
+  views bigint NOT NULL DEFAULT 0,
+  likes bigint NOT NULL DEFAULT 0,
+  dislikes bigint NOT NULL DEFAULT 0,
+  PRIMARY KEY (content_id, day)
+);
+
+CREATE INDEX content_stats_daily_day_idx ON content_stats_daily(day);
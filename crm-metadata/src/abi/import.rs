@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use crm_core::{telemetry::sql_span, ServiceError, Validate, Violations};
+use serde::Deserialize;
+use sqlx::PgConnection;
+use tonic::Response;
+use tracing::Instrument;
+
+use super::write::{insert_content, write_error, NewContent};
+use crate::{
+    pb::metadata::{ContentType, ImportContentsRequest, ImportContentsResponse, ImportFormat},
+    Error, MetadataService, ServiceResult,
+};
+
+const PUBLISHER_IDS_SQL: &str = r#"
+    SELECT DISTINCT ON (name) name, id
+    FROM publishers
+    WHERE name = ANY($1)
+    ORDER BY name, id
+    "#;
+
+const INSERT_PUBLISHERS_SQL: &str = r#"
+    INSERT INTO publishers(name)
+    SELECT unnest($1::text[])
+    RETURNING name, id
+    "#;
+
+/// A content of the CMS export, publishers are referred to by name
+#[derive(Debug, Deserialize)]
+struct ImportRecord {
+    name: String,
+    #[serde(default)]
+    description: String,
+    url: String,
+    image: String,
+    content_type: String,
+    publishers: Vec<String>,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+}
+
+/// csv can't hold a list in a field, publishers are separated by `;`
+#[derive(Debug, Deserialize)]
+struct CsvRecord {
+    name: String,
+    #[serde(default)]
+    description: String,
+    url: String,
+    image: String,
+    content_type: String,
+    publishers: String,
+    #[serde(default)]
+    created_at: Option<DateTime<Utc>>,
+}
+
+impl MetadataService {
+    /// Import every record in a single transaction, nothing is imported if one is invalid
+    pub async fn import_contents(
+        &self,
+        req: ImportContentsRequest,
+    ) -> ServiceResult<ImportContentsResponse> {
+        let records = parse_records(req.format(), &req.data)?;
+        let mut v = Violations::default();
+        v.each("records", &records);
+        v.into_result(Error::DOMAIN)?;
+
+        let mut tx = self.begin().await?;
+        let names = records
+            .iter()
+            .flat_map(|r| r.publishers.iter().cloned())
+            .collect::<Vec<_>>();
+        let (publishers, created) = resolve_publishers(&mut tx, names)
+            .await
+            .map_err(write_error("import contents"))?;
+        let mut imported = 0;
+        for record in records {
+            let content = record.into_content(&publishers);
+            insert_content(&mut tx, &content)
+                .await
+                .map_err(write_error("import contents"))?;
+            imported += 1;
+        }
+        tx.commit().await.map_err(write_error("import contents"))?;
+
+        Ok(Response::new(ImportContentsResponse {
+            contents: imported,
+            publishers: created,
+        }))
+    }
+}
+
+fn parse_records(format: ImportFormat, data: &[u8]) -> Result<Vec<ImportRecord>, Error> {
+    match format {
+        ImportFormat::Json => {
+            serde_json::from_slice(data).map_err(|e| Error::invalid("data", e.to_string()))
+        }
+        ImportFormat::Csv => csv::Reader::from_reader(data)
+            .deserialize::<CsvRecord>()
+            .enumerate()
+            .map(|(i, record)| {
+                record
+                    .map(Into::into)
+                    .map_err(|e| Error::invalid("data", format!("record {}: {}", i, e)))
+            })
+            .collect(),
+        ImportFormat::Unspecified => Err(Error::invalid("format", "format is required")),
+    }
+}
+
+/// Map publisher names to ids, creating the ones not in the catalog yet.
+/// Returns the map and how many publishers were created
+async fn resolve_publishers(
+    conn: &mut PgConnection,
+    mut names: Vec<String>,
+) -> sqlx::Result<(HashMap<String, i32>, u32)> {
+    names.sort();
+    names.dedup();
+    let mut publishers: HashMap<String, i32> = sqlx::query_as(PUBLISHER_IDS_SQL)
+        .bind(&names)
+        .fetch_all(&mut *conn)
+        .instrument(sql_span("get_publisher_ids"))
+        .await?
+        .into_iter()
+        .collect();
+    let missing = names
+        .into_iter()
+        .filter(|name| !publishers.contains_key(name))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return Ok((publishers, 0));
+    }
+    let created: Vec<(String, i32)> = sqlx::query_as(INSERT_PUBLISHERS_SQL)
+        .bind(&missing)
+        .fetch_all(&mut *conn)
+        .instrument(sql_span("insert_publishers"))
+        .await?;
+    let count = created.len() as u32;
+    publishers.extend(created);
+    Ok((publishers, count))
+}
+
+impl Validate for ImportRecord {
+    fn validate(&self, v: &mut Violations) {
+        v.required("name", &self.name);
+        v.url("url", &self.url);
+        v.url("image", &self.image);
+        v.check(
+            "content_type",
+            ContentType::from_db_name(&self.content_type) != ContentType::Unspecified,
+            "is not a content type",
+        );
+        v.not_empty("publishers", &self.publishers);
+    }
+}
+
+impl ImportRecord {
+    /// The content of a validated record, every publisher is in `publishers`
+    fn into_content(self, publishers: &HashMap<String, i32>) -> NewContent {
+        let publisher_ids = self
+            .publishers
+            .iter()
+            .filter_map(|name| publishers.get(name).copied())
+            .collect();
+        NewContent {
+            name: self.name,
+            description: self.description,
+            url: self.url,
+            image: self.image,
+            content_type: ContentType::from_db_name(&self.content_type),
+            publisher_ids,
+            created_at: self.created_at,
+        }
+    }
+}
+
+impl From<CsvRecord> for ImportRecord {
+    fn from(record: CsvRecord) -> Self {
+        let publishers = record
+            .publishers
+            .split(';')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(ToString::to_string)
+            .collect();
+        Self {
+            name: record.name,
+            description: record.description,
+            url: record.url,
+            image: record.image,
+            content_type: record.content_type,
+            publishers,
+            created_at: record.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::metadata::ListContentsRequest;
+
+    const CSV: &str = "name,description,url,image,content_type,publishers,created_at
+Rust in Action,hands on,https://example.com/1,https://example.com/1.png,movie,Alice Chen;New Studio,2024-01-02T03:04:05Z
+Tokio tips,,https://example.com/2,https://example.com/2.png,short,New Studio,
+";
+
+    #[test]
+    fn parse_csv_should_work() {
+        let records = parse_records(ImportFormat::Csv, CSV.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].publishers, vec!["Alice Chen", "New Studio"]);
+        assert!(records[0].created_at.is_some());
+        assert!(records[1].description.is_empty());
+        assert!(records[1].created_at.is_none());
+    }
+
+    #[test]
+    fn parse_json_should_work() {
+        let data = r#"[{"name": "Rust in Action", "url": "https://example.com/1",
+            "image": "https://example.com/1.png", "content_type": "vlog",
+            "publishers": ["Alice Chen"]}]"#;
+        let records = parse_records(ImportFormat::Json, data.as_bytes()).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].content_type, "vlog");
+
+        assert!(parse_records(ImportFormat::Json, b"{}").is_err());
+        assert!(parse_records(ImportFormat::Unspecified, data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn records_should_be_validated() {
+        let data = r#"[{"name": " ", "url": "https://example.com/1", "image": "1.png",
+            "content_type": "podcast", "publishers": []}]"#;
+        let mut records = parse_records(ImportFormat::Json, data.as_bytes()).unwrap();
+        let status = records.remove(0).validated(Error::DOMAIN).unwrap_err();
+        let details = crm_core::ErrorDetails::from_status(&status);
+        assert_eq!(
+            details.fields(),
+            vec!["name", "image", "content_type", "publishers"]
+        );
+    }
+
+    #[tokio::test]
+    async fn import_contents_should_work() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = ImportContentsRequest {
+            format: ImportFormat::Csv as i32,
+            data: CSV.as_bytes().to_vec(),
+        };
+        let res = svc.import_contents(req).await.unwrap().into_inner();
+        assert_eq!(res.contents, 2);
+        assert_eq!(res.publishers, 1);
+
+        let req = ListContentsRequest {
+            page_size: 100,
+            ..Default::default()
+        };
+        let contents = svc.list_contents(req).await.unwrap().into_inner().contents;
+        assert_eq!(contents.len(), 32);
+        let imported = contents
+            .iter()
+            .find(|c| c.name == "Rust in Action")
+            .unwrap();
+        assert_eq!(imported.publishers.len(), 2);
+        assert_eq!(imported.created_at.unwrap().seconds, 1704164645);
+    }
+
+    #[tokio::test]
+    async fn import_contents_should_be_all_or_nothing() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let data = format!(
+            "{}Bad one,,not a url,https://example.com/3.png,vlog,Alice Chen,\n",
+            CSV
+        );
+        let req = ImportContentsRequest {
+            format: ImportFormat::Csv as i32,
+            data: data.into_bytes(),
+        };
+        let err = svc.import_contents(req).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        assert!(err.message().starts_with("records[2].url"));
+        let details = crm_core::ErrorDetails::from_status(&err);
+        assert_eq!(details.fields(), vec!["records[2].url"]);
+
+        let req = ListContentsRequest {
+            page_size: 100,
+            ..Default::default()
+        };
+        let contents = svc.list_contents(req).await.unwrap().into_inner().contents;
+        assert_eq!(contents.len(), 30);
+    }
+}
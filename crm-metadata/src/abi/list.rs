@@ -0,0 +1,246 @@
+use crm_core::{telemetry::sql_span, ToUtc};
+use sqlx::{Postgres, QueryBuilder};
+use tonic::Response;
+use tracing::Instrument;
+
+use super::{ContentRow, CONTENT_COLUMNS};
+use crate::{
+    error::db,
+    pb::metadata::{
+        ContentOrder, ListContentsRequest, ListContentsResponse, SearchContentsRequest,
+    },
+    MetadataService, ServiceResult,
+};
+
+const DEFAULT_PAGE_SIZE: u32 = 20;
+const MAX_PAGE_SIZE: u32 = 100;
+
+/// A page of results, `page_token` is the offset of the page
+struct Page {
+    offset: i64,
+    size: i64,
+}
+
+impl MetadataService {
+    pub async fn list_contents(
+        &self,
+        req: ListContentsRequest,
+    ) -> ServiceResult<ListContentsResponse> {
+        let page = Page::new(req.page_size, &req.page_token);
+        let publisher_ids = req
+            .publisher_ids
+            .iter()
+            .map(|id| i32::try_from(*id).expect("publisher_ids are checked by the validation"))
+            .collect::<Vec<_>>();
+
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM contents WHERE TRUE",
+            CONTENT_COLUMNS
+        ));
+        if !req.content_types.is_empty() {
+            let types = req.content_types().map(|t| t.db_name()).collect::<Vec<_>>();
+            query
+                .push(" AND content_type = ANY(")
+                .push_bind(types)
+                .push("::text[]::content_type[])");
+        }
+        if !publisher_ids.is_empty() {
+            query
+                .push(" AND EXISTS (SELECT 1 FROM content_publishers cp WHERE cp.content_id = contents.id AND cp.publisher_id = ANY(")
+                .push_bind(publisher_ids)
+                .push("))");
+        }
+        if let Some(after) = req.created_after.as_ref() {
+            let after = after
+                .to_utc()
+                .expect("created_after is checked by the validation");
+            query.push(" AND created_at >= ").push_bind(after);
+        }
+        if let Some(before) = req.created_before.as_ref() {
+            let before = before
+                .to_utc()
+                .expect("created_before is checked by the validation");
+            query.push(" AND created_at < ").push_bind(before);
+        }
+        query.push(match req.order_by() {
+            ContentOrder::Views => " ORDER BY views DESC, id DESC",
+            ContentOrder::Likes => " ORDER BY likes DESC, id DESC",
+            ContentOrder::Unspecified => " ORDER BY created_at DESC, id DESC",
+        });
+        page.push_limit(&mut query);
+
+        self.fetch_page(query, page).await
+    }
+
+    pub async fn search_contents(
+        &self,
+        req: SearchContentsRequest,
+    ) -> ServiceResult<ListContentsResponse> {
+        let page = Page::new(req.page_size, &req.page_token);
+
+        let mut query = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM contents, plainto_tsquery('simple', ",
+            CONTENT_COLUMNS
+        ));
+        query
+            .push_bind(req.query)
+            .push(") AS q WHERE search @@ q ORDER BY ts_rank(search, q) DESC, id DESC");
+        page.push_limit(&mut query);
+
+        self.fetch_page(query, page).await
+    }
+
+    async fn fetch_page(
+        &self,
+        mut query: QueryBuilder<'_, Postgres>,
+        page: Page,
+    ) -> ServiceResult<ListContentsResponse> {
+        let mut rows: Vec<ContentRow> = query
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .instrument(sql_span("list_contents"))
+            .await
+            .map_err(db("list contents"))?;
+        // one extra row was fetched to know if there is a next page
+        let next_page_token = if rows.len() as i64 > page.size {
+            rows.truncate(page.size as usize);
+            (page.offset + page.size).to_string()
+        } else {
+            String::new()
+        };
+        let contents = self.to_contents(rows).await?;
+        Ok(Response::new(ListContentsResponse {
+            contents,
+            next_page_token,
+        }))
+    }
+}
+
+impl Page {
+    fn new(page_size: u32, page_token: &str) -> Self {
+        let size = match page_size {
+            0 => DEFAULT_PAGE_SIZE,
+            n => n.min(MAX_PAGE_SIZE),
+        };
+        let offset = match page_token {
+            "" => 0,
+            token => token
+                .parse::<u32>()
+                .expect("page_token is checked by the validation"),
+        };
+        Self {
+            offset: offset as i64,
+            size: size as i64,
+        }
+    }
+
+    fn push_limit(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        query
+            .push(" LIMIT ")
+            .push_bind(self.size + 1)
+            .push(" OFFSET ")
+            .push_bind(self.offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Request;
+
+    use super::*;
+    use crate::pb::metadata::{metadata_server::Metadata, ContentType};
+
+    #[tokio::test]
+    async fn list_contents_should_paginate() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = ListContentsRequest::default();
+        let first = svc.list_contents(req.clone()).await.unwrap().into_inner();
+        assert_eq!(first.contents.len(), DEFAULT_PAGE_SIZE as usize);
+        assert_eq!(first.next_page_token, "20");
+
+        let req = ListContentsRequest {
+            page_token: first.next_page_token,
+            ..req
+        };
+        let second = svc.list_contents(req).await.unwrap().into_inner();
+        assert_eq!(second.contents.len(), 10);
+        assert!(second.next_page_token.is_empty());
+        assert!(second
+            .contents
+            .iter()
+            .all(|c| first.contents.iter().all(|f| f.id != c.id)));
+    }
+
+    #[tokio::test]
+    async fn list_contents_should_filter() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = ListContentsRequest {
+            content_types: vec![ContentType::Movie as i32],
+            ..Default::default()
+        };
+        let res = svc.list_contents(req).await.unwrap().into_inner();
+        assert!(!res.contents.is_empty());
+        assert!(res
+            .contents
+            .iter()
+            .all(|c| c.content_type == ContentType::Movie as i32));
+
+        let req = ListContentsRequest {
+            publisher_ids: vec![1],
+            ..Default::default()
+        };
+        let res = svc.list_contents(req).await.unwrap().into_inner();
+        assert!(!res.contents.is_empty());
+        assert!(res
+            .contents
+            .iter()
+            .all(|c| c.publishers.iter().any(|p| p.id == 1)));
+    }
+
+    #[tokio::test]
+    async fn list_contents_should_order_by_views() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = ListContentsRequest {
+            order_by: ContentOrder::Views as i32,
+            page_size: 100,
+            ..Default::default()
+        };
+        let res = svc.list_contents(req).await.unwrap().into_inner();
+        assert_eq!(res.contents.len(), 30);
+        assert!(res.contents.windows(2).all(|w| w[0].views >= w[1].views));
+    }
+
+    #[tokio::test]
+    async fn list_contents_should_reject_bad_token() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = ListContentsRequest {
+            page_token: "abc".to_string(),
+            ..Default::default()
+        };
+        let err = Metadata::list_contents(&svc, Request::new(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn search_contents_should_work() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = SearchContentsRequest {
+            query: "rust".to_string(),
+            ..Default::default()
+        };
+        let res = svc.search_contents(req).await.unwrap().into_inner();
+        assert!(!res.contents.is_empty());
+        assert!(res.contents.iter().all(|c| {
+            let text = format!("{} {}", c.name, c.description).to_lowercase();
+            text.contains("rust")
+        }));
+
+        let req = SearchContentsRequest::default();
+        let err = Metadata::search_contents(&svc, Request::new(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{future, pin::pin};
+
+use futures_util::{stream, Stream, StreamExt};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Code, Response, Status};
+use tracing::{warn, Instrument};
+
+use crate::{
+    pb::metadata::{
+        materialize_response::Result as MaterializeResult, BatchGetRequest, BatchGetResponse,
+        Content, MaterializeError, MaterializeRequest, MaterializeResponse,
+    },
+    Error, MetadataService, ResponseStream, ServiceResult,
+};
+
+const CHANNEL_SIZE: usize = 1024;
+/// max number of ids resolved at the same time for one request
+const MAX_CONCURRENCY: usize = 32;
+pub(crate) const MAX_BATCH_SIZE: usize = 1000;
+
+impl MetadataService {
+    /// Resolve ids while the client is still streaming them. Contents are sent
+    /// as soon as they resolve, a missing id gets an error response instead of
+    /// ending the stream.
+    pub async fn materialize(
+        &self,
+        requests: impl Stream<Item = Result<MaterializeRequest, Status>> + Send + 'static,
+    ) -> ServiceResult<ResponseStream> {
+        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
+        let svc = self.clone();
+        tokio::spawn(
+            async move {
+                let responses = requests
+                    .take_while(|request| {
+                        if let Err(e) = request {
+                            warn!("materialize request stream failed: {}", e);
+                        }
+                        future::ready(request.is_ok())
+                    })
+                    .filter_map(|request| future::ready(request.ok()))
+                    .map(|request| {
+                        let svc = svc.clone();
+                        async move { svc.materialize_one(request.id).await }
+                    })
+                    .buffer_unordered(MAX_CONCURRENCY);
+                let mut responses = pin!(responses);
+                while let Some(response) = responses.next().await {
+                    if tx.send(Ok(response)).await.is_err() {
+                        // the client went away, stop resolving
+                        break;
+                    }
+                }
+            }
+            .in_current_span(),
+        );
+        let stream = Box::pin(ReceiverStream::new(rx));
+        Ok(Response::new(stream))
+    }
+
+    /// Resolve all ids at once, keeping their order. Duplicated ids are
+    /// answered as many times as they are requested.
+    pub async fn batch_get(&self, req: BatchGetRequest) -> ServiceResult<BatchGetResponse> {
+        if req.ids.len() > MAX_BATCH_SIZE {
+            let description = format!("at most {} ids per batch", MAX_BATCH_SIZE);
+            return Err(Error::invalid("ids", description).into());
+        }
+        let results = stream::iter(req.ids)
+            .map(|id| async move { (id, self.get_content(id).await) })
+            .buffered(MAX_CONCURRENCY)
+            .collect::<Vec<_>>()
+            .await;
+
+        let mut contents = Vec::with_capacity(results.len());
+        let mut missing_ids = Vec::new();
+        for (id, result) in results {
+            match result {
+                Ok(content) => contents.push(content),
+                Err(status) if status.code() == Code::NotFound => missing_ids.push(id),
+                Err(status) => return Err(status),
+            }
+        }
+        Ok(Response::new(BatchGetResponse {
+            contents,
+            missing_ids,
+        }))
+    }
+
+    async fn materialize_one(&self, id: u32) -> MaterializeResponse {
+        let result = match self.get_content(id).await {
+            Ok(content) => MaterializeResult::Content(content),
+            Err(status) => MaterializeResult::Error(MaterializeError {
+                code: status.code() as i32,
+                message: status.message().to_string(),
+            }),
+        };
+        MaterializeResponse {
+            id,
+            result: Some(result),
+        }
+    }
+}
+
+impl MaterializeResponse {
+    pub fn into_content(self) -> Option<Content> {
+        match self.result {
+            Some(MaterializeResult::Content(content)) => Some(content),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_service_materialize() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let stream = tokio_stream::iter(vec![
+            Ok(MaterializeRequest { id: 1 }),
+            Ok(MaterializeRequest { id: 10000 }),
+            Ok(MaterializeRequest { id: 2 }),
+        ]);
+        let response = svc.materialize(stream).await.unwrap();
+        let mut responses = response
+            .into_inner()
+            .map(Result::unwrap)
+            .collect::<Vec<_>>()
+            .await;
+        responses.sort_by_key(|r| r.id);
+        assert_eq!(responses.len(), 3);
+        assert!(
+            matches!(responses[0].result, Some(MaterializeResult::Content(ref c)) if c.id == 1)
+        );
+        assert!(
+            matches!(responses[1].result, Some(MaterializeResult::Content(ref c)) if c.id == 2)
+        );
+        assert!(matches!(
+            responses[2].result,
+            Some(MaterializeResult::Error(ref e)) if e.code == tonic::Code::NotFound as i32
+        ));
+    }
+
+    #[tokio::test]
+    async fn batch_get_should_keep_order() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = BatchGetRequest {
+            ids: vec![3, 10000, 1, 2, 1, 20000],
+        };
+        let res = svc.batch_get(req).await.unwrap().into_inner();
+        let ids = res.contents.iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 1, 2, 1]);
+        assert_eq!(res.missing_ids, vec![10000, 20000]);
+
+        let req = BatchGetRequest {
+            ids: vec![1; MAX_BATCH_SIZE + 1],
+        };
+        let err = svc.batch_get(req).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn materialize_should_respond_before_half_close() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let (req_tx, req_rx) = mpsc::channel(1);
+        let response = svc.materialize(ReceiverStream::new(req_rx)).await.unwrap();
+        let mut responses = response.into_inner();
+
+        req_tx.send(Ok(MaterializeRequest { id: 1 })).await.unwrap();
+        let first = timeout(Duration::from_secs(5), responses.next())
+            .await
+            .expect("no response while the request stream is open")
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.id, 1);
+
+        drop(req_tx);
+        assert!(responses.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn materialize_should_not_deadlock_on_large_requests() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let ids = (0..CHANNEL_SIZE as u32 * 2)
+            .map(|i| MaterializeRequest { id: i % 40 })
+            .collect::<Vec<_>>();
+        let stream = tokio_stream::iter(ids).map(Ok);
+        let response = svc.materialize(stream).await.unwrap();
+        let count = timeout(Duration::from_secs(30), response.into_inner().count())
+            .await
+            .unwrap();
+        assert_eq!(count, CHANNEL_SIZE * 2);
+    }
+}
@@ -1,104 +1,178 @@
-use std::collections::HashSet;
-
-use chrono::{DateTime, Days, Utc};
-use fake::faker::chrono::zh_cn::DateTimeBetween;
-use fake::faker::lorem::zh_cn::Sentence;
-use fake::{faker::name::zh_cn::Name, Fake};
-use rand::Rng;
-use tokio::sync::mpsc;
-use tokio_stream::wrappers::ReceiverStream;
-use tokio_stream::{Stream, StreamExt};
-use tonic::Response;
+mod import;
+mod list;
+mod materialize;
+mod popularity;
+mod write;
+
+pub(crate) use materialize::MAX_BATCH_SIZE;
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use crm_core::{telemetry::sql_span, ToTimestamp};
+use tokio_stream::Stream;
+use tonic::Status;
+use tracing::Instrument;
 
 use crate::{
-    pb::metadata::{Content, MaterializeRequest, Publisher},
-    MetadataService, ResponseStream, ServiceResult,
+    error::db,
+    pb::metadata::{Content, ContentType, MaterializeRequest, Publisher},
+    Error, MetadataService,
 };
-const CHANNEL_SIZE: usize = 1024;
+
+/// Columns of `contents` selected into a [`ContentRow`]
+const CONTENT_COLUMNS: &str = "id, name, description, url, image, content_type::text AS content_type, created_at, views, likes, dislikes";
+
+const PUBLISHERS_SQL: &str = r#"
+    SELECT cp.content_id, p.id, p.name, p.avatar
+    FROM content_publishers cp
+    JOIN publishers p ON p.id = cp.publisher_id
+    WHERE cp.content_id = ANY($1)
+    ORDER BY cp.content_id, p.id
+    "#;
+
+#[derive(Debug, sqlx::FromRow)]
+struct ContentRow {
+    id: i32,
+    name: String,
+    description: String,
+    url: String,
+    image: String,
+    content_type: String,
+    created_at: DateTime<Utc>,
+    views: i64,
+    likes: i64,
+    dislikes: i64,
+}
+
 impl MetadataService {
-    pub async fn materialize(
-        &self,
-        mut requests: impl Stream<Item = Result<MaterializeRequest, tonic::Status>> + Unpin + Send,
-    ) -> ServiceResult<ResponseStream> {
-        let (tx, rx) = mpsc::channel(CHANNEL_SIZE);
-        while let Some(Ok(request)) = requests.next().await {
-            let id = request.id;
-            tx.send(Ok(Content::materialize(id))).await.unwrap();
-        }
-        let stream = Box::pin(ReceiverStream::new(rx));
-        Ok(Response::new(stream))
+    async fn get_content(&self, id: u32) -> Result<Content, Status> {
+        let load = || async move { self.load_content(id).await.map_err(Status::from) };
+        self.cache.get_or_load(id, load).await
     }
-}
 
-impl Content {
-    fn materialize(id: u32) -> Self {
-        let mut rng = rand::thread_rng();
-        let created_at = DateTimeBetween(before(30), Utc::now()).fake::<DateTime<Utc>>();
-        let created_at = prost_types::Timestamp {
-            seconds: created_at.timestamp(),
-            nanos: created_at.timestamp_subsec_nanos() as i32,
-        };
-        Self {
-            id,
-            name: Name().fake(),
-            description: Sentence(3..20).fake(),
-            publishers: (0..rng.gen_range(2..10))
-                .map(|_| Publisher::new())
-                .collect(),
-            url: "https://placehold.co/1600x900".to_string(),
-            image: "https://placehold.co/1600x900".to_string(),
-            content_type: (0..=4).fake(),
-            created_at: Some(created_at),
-            views: (0..10000).fake(),
-            likes: (0..10000).fake(),
-            dislikes: (0..10000).fake(),
+    async fn load_content(&self, id: u32) -> Result<Content, Error> {
+        let not_found = || Error::ContentNotFound(id);
+        let db_id = i32::try_from(id).map_err(|_| not_found())?;
+        let sql = format!("SELECT {} FROM contents WHERE id = $1", CONTENT_COLUMNS);
+        let row: Option<ContentRow> = sqlx::query_as(&sql)
+            .bind(db_id)
+            .fetch_optional(&self.pool)
+            .instrument(sql_span("get_content"))
+            .await
+            .map_err(db("fetch content"))?;
+        let row = row.ok_or_else(not_found)?;
+        let mut contents = self.to_contents(vec![row]).await?;
+        contents.pop().ok_or_else(not_found)
+    }
+
+    /// Attach the publishers to the rows, keeping their order
+    async fn to_contents(&self, rows: Vec<ContentRow>) -> Result<Vec<Content>, Error> {
+        let ids = rows.iter().map(|row| row.id).collect::<Vec<_>>();
+        let publishers: Vec<(i32, i32, String, String)> = sqlx::query_as(PUBLISHERS_SQL)
+            .bind(&ids)
+            .fetch_all(&self.pool)
+            .instrument(sql_span("get_publishers"))
+            .await
+            .map_err(db("fetch publishers"))?;
+        let mut publishers_by_content: HashMap<i32, Vec<Publisher>> = HashMap::new();
+        for (content_id, id, name, avatar) in publishers {
+            publishers_by_content
+                .entry(content_id)
+                .or_default()
+                .push(Publisher {
+                    id: id as u32,
+                    name,
+                    avatar,
+                });
         }
+
+        let contents = rows
+            .into_iter()
+            .map(|row| {
+                let publishers = publishers_by_content.remove(&row.id).unwrap_or_default();
+                Content {
+                    id: row.id as u32,
+                    name: row.name,
+                    description: row.description,
+                    publishers,
+                    url: row.url,
+                    image: row.image,
+                    content_type: ContentType::from_db_name(&row.content_type) as i32,
+                    created_at: Some(row.created_at.to_timestamp()),
+                    views: row.views as u64,
+                    likes: row.likes as u64,
+                    dislikes: row.dislikes as u64,
+                }
+            })
+            .collect();
+        Ok(contents)
     }
 }
 
-impl Publisher {
-    fn new() -> Self {
-        Self {
-            id: (10000..200000).fake(),
-            name: Name().fake(),
-            avatar: "https://placehold.co/400x400".to_string(),
+impl ContentType {
+    /// Name of the type in the `content_type` db enum
+    fn db_name(&self) -> &'static str {
+        match self {
+            ContentType::Unspecified => "unspecified",
+            ContentType::Short => "short",
+            ContentType::Vlog => "vlog",
+            ContentType::Movie => "movie",
+            ContentType::AiGenerated => "ai_generated",
         }
     }
-}
 
-fn before(n: u64) -> DateTime<Utc> {
-    let now = Utc::now();
-    now.checked_sub_days(Days::new(n)).unwrap()
+    fn from_db_name(name: &str) -> Self {
+        match name {
+            "short" => ContentType::Short,
+            "vlog" => ContentType::Vlog,
+            "movie" => ContentType::Movie,
+            "ai_generated" => ContentType::AiGenerated,
+            _ => ContentType::Unspecified,
+        }
+    }
 }
 
 impl MaterializeRequest {
+    /// One request per distinct id, in the order the ids first appear
     pub fn new_with_ids(ids: &[u32]) -> impl Stream<Item = MaterializeRequest> {
-        let reqs: HashSet<_> = ids.iter().map(move |id| Self { id: *id }).collect();
+        let mut seen = HashSet::new();
+        let reqs: Vec<_> = ids
+            .iter()
+            .filter(|id| seen.insert(**id))
+            .map(|id| Self { id: *id })
+            .collect();
         tokio_stream::iter(reqs)
     }
 }
 #[cfg(test)]
 mod tests {
+    use tokio_stream::StreamExt;
 
     use super::*;
-    use crate::config::AppConfig;
 
     #[tokio::test]
-    async fn test_content_materialize() {
-        let content = Content::materialize(1);
+    async fn test_get_content() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let content = svc.get_content(1).await.unwrap();
         println!("{:?}", content);
         assert_eq!(content.id, 1);
+        assert!(!content.publishers.is_empty());
+
+        let err = svc.get_content(10000).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+
+        svc.get_content(1).await.unwrap();
+        let stats = svc.cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.size), (1, 2, 1));
     }
 
     #[tokio::test]
-    async fn test_service_materialize() {
-        let config = AppConfig::load().unwrap();
-        let svc = MetadataService::new(config);
-        let stream = tokio_stream::iter(vec![Ok(MaterializeRequest { id: 1 })]);
-        let response = svc.materialize(stream).await.unwrap();
-        let mut stream = response.into_inner();
-        while let Some(content) = stream.next().await {
-            println!("{:?}", content.unwrap());
-        }
+    async fn new_with_ids_should_keep_order() {
+        let ids = MaterializeRequest::new_with_ids(&[3, 1, 3, 2])
+            .map(|req| req.id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(ids, vec![3, 1, 2]);
     }
 }
@@ -0,0 +1,399 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, NaiveDate, Utc};
+use crm_core::{batch::batches, telemetry::sql_span, ToUtc, Validate};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Response, Status};
+use tracing::Instrument;
+
+use super::{ContentRow, CONTENT_COLUMNS};
+use crate::{
+    error::db,
+    pb::metadata::{
+        ContentEvent, ContentEventType, ContentType, IngestResponse, TrendingContent,
+        TrendingGroup, TrendingRequest, TrendingResponse,
+    },
+    Error, MetadataService, ServiceResult,
+};
+
+const DEFAULT_TRENDING_DAYS: u32 = 7;
+const MAX_TRENDING_DAYS: u32 = 90;
+const DEFAULT_TRENDING_LIMIT: u32 = 10;
+const MAX_TRENDING_LIMIT: u32 = 100;
+
+// each row of the unnest holds the counters of one (content, day) of the batch
+const UPSERT_DAILY_SQL: &str = r#"
+    INSERT INTO content_stats_daily(content_id, day, views, likes, dislikes)
+    SELECT * FROM unnest($1::int[], $2::date[], $3::bigint[], $4::bigint[], $5::bigint[])
+    ON CONFLICT (content_id, day) DO UPDATE SET
+      views = content_stats_daily.views + EXCLUDED.views,
+      likes = content_stats_daily.likes + EXCLUDED.likes,
+      dislikes = content_stats_daily.dislikes + EXCLUDED.dislikes
+    "#;
+
+const UPDATE_TOTALS_SQL: &str = r#"
+    UPDATE contents c SET
+      views = c.views + t.views,
+      likes = c.likes + t.likes,
+      dislikes = c.dislikes + t.dislikes
+    FROM (
+      SELECT content_id, SUM(views) AS views, SUM(likes) AS likes, SUM(dislikes) AS dislikes
+      FROM unnest($1::int[], $2::bigint[], $3::bigint[], $4::bigint[])
+        AS t(content_id, views, likes, dislikes)
+      GROUP BY content_id
+    ) t
+    WHERE c.id = t.content_id
+    "#;
+
+// $1 first day of the window, $2 content types, $3 contents per type
+const TRENDING_SQL: &str = r#"
+    WITH totals AS (
+      SELECT content_id, SUM(views)::bigint AS views, SUM(likes)::bigint AS likes,
+        SUM(dislikes)::bigint AS dislikes
+      FROM content_stats_daily
+      WHERE day >= $1
+      GROUP BY content_id
+    ), ranked AS (
+      SELECT c.id, c.content_type::text AS content_type, t.views, t.likes, t.dislikes,
+        t.views + 5 * t.likes - 5 * t.dislikes AS score,
+        row_number() OVER (
+          PARTITION BY c.content_type
+          ORDER BY t.views + 5 * t.likes - 5 * t.dislikes DESC, c.id DESC
+        ) AS rank
+      FROM totals t
+      JOIN contents c ON c.id = t.content_id
+      WHERE c.content_type = ANY($2::text[]::content_type[])
+    )
+    SELECT id, content_type, views, likes, dislikes, score
+    FROM ranked
+    WHERE rank <= $3
+    ORDER BY content_type, rank
+    "#;
+
+/// Counters of one content on one day
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Counts {
+    views: i64,
+    likes: i64,
+    dislikes: i64,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct TrendingRow {
+    id: i32,
+    content_type: String,
+    views: i64,
+    likes: i64,
+    dislikes: i64,
+    score: i64,
+}
+
+impl MetadataService {
+    /// Count a stream of views, likes and dislikes into the daily and all-time
+    /// counters of their contents, `ingest.batch_size` events per transaction.
+    /// Malformed events and the ones of unknown contents are rejected.
+    pub async fn ingest(
+        &self,
+        stream: impl Stream<Item = Result<ContentEvent, Status>> + Unpin + Send,
+    ) -> ServiceResult<IngestResponse> {
+        let mut ret = IngestResponse::default();
+        let mut malformed = 0;
+        let events = stream.filter_map(|event| {
+            let event = event.map(parse_event).transpose();
+            if event.is_none() {
+                malformed += 1;
+            }
+            event
+        });
+
+        let mut batches = batches(events, self.config.ingest.batch_size);
+        while let Some(batch) = batches.next().await {
+            let batch = batch?;
+            let accepted = self.apply_events(&batch).await?;
+            ret.accepted += accepted;
+            ret.rejected += batch.len() as u64 - accepted;
+        }
+        ret.rejected += malformed;
+
+        Ok(Response::new(ret))
+    }
+
+    pub async fn trending(&self, req: TrendingRequest) -> ServiceResult<TrendingResponse> {
+        let days = match req.days {
+            0 => DEFAULT_TRENDING_DAYS,
+            n => n.min(MAX_TRENDING_DAYS),
+        };
+        let limit = match req.limit {
+            0 => DEFAULT_TRENDING_LIMIT,
+            n => n.min(MAX_TRENDING_LIMIT),
+        };
+        let mut types = req
+            .content_types()
+            .filter(|t| *t != ContentType::Unspecified)
+            .collect::<Vec<_>>();
+        if types.is_empty() {
+            types = vec![
+                ContentType::Short,
+                ContentType::Vlog,
+                ContentType::Movie,
+                ContentType::AiGenerated,
+            ];
+        }
+        let mut seen = HashSet::new();
+        types.retain(|t| seen.insert(*t));
+
+        let since = Utc::now().date_naive() - Duration::days(days as i64 - 1);
+        let internal = db("compute trending contents");
+        let rows: Vec<TrendingRow> = sqlx::query_as(TRENDING_SQL)
+            .bind(since)
+            .bind(types.iter().map(|t| t.db_name()).collect::<Vec<_>>())
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .instrument(sql_span("trending"))
+            .await
+            .map_err(internal)?;
+
+        let ids = rows.iter().map(|row| row.id).collect::<Vec<_>>();
+        let sql = format!(
+            "SELECT {} FROM contents WHERE id = ANY($1)",
+            CONTENT_COLUMNS
+        );
+        let content_rows: Vec<ContentRow> = sqlx::query_as(&sql)
+            .bind(&ids)
+            .fetch_all(&self.pool)
+            .instrument(sql_span("get_contents"))
+            .await
+            .map_err(internal)?;
+        let mut contents = self
+            .to_contents(content_rows)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, c))
+            .collect::<HashMap<_, _>>();
+
+        let mut groups = types
+            .iter()
+            .map(|t| TrendingGroup {
+                content_type: *t as i32,
+                contents: vec![],
+            })
+            .collect::<Vec<_>>();
+        for row in rows {
+            let content_type = ContentType::from_db_name(&row.content_type) as i32;
+            let Some(group) = groups.iter_mut().find(|g| g.content_type == content_type) else {
+                continue;
+            };
+            group.contents.push(TrendingContent {
+                content: contents.remove(&(row.id as u32)),
+                views: row.views as u64,
+                likes: row.likes as u64,
+                dislikes: row.dislikes as u64,
+                score: row.score,
+            });
+        }
+
+        Ok(Response::new(TrendingResponse { groups }))
+    }
+
+    /// Count the events of known contents, returns how many were counted
+    async fn apply_events(
+        &self,
+        events: &[(i32, NaiveDate, ContentEventType)],
+    ) -> Result<u64, Error> {
+        let internal = db("ingest events");
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+
+        // the lock keeps the contents from being deleted before the batch is committed
+        let ids = events.iter().map(|(id, _, _)| *id).collect::<Vec<_>>();
+        let known: HashSet<i32> =
+            sqlx::query_scalar("SELECT id FROM contents WHERE id = ANY($1) FOR KEY SHARE")
+                .bind(&ids)
+                .fetch_all(&mut *tx)
+                .instrument(sql_span("lock_contents"))
+                .await
+                .map_err(internal)?
+                .into_iter()
+                .collect();
+
+        let mut accepted = 0;
+        let mut daily: HashMap<(i32, NaiveDate), Counts> = HashMap::new();
+        for (id, day, event_type) in events {
+            if !known.contains(id) {
+                continue;
+            }
+            accepted += 1;
+            let counts = daily.entry((*id, *day)).or_default();
+            match event_type {
+                ContentEventType::View => counts.views += 1,
+                ContentEventType::Like => counts.likes += 1,
+                ContentEventType::Dislike => counts.dislikes += 1,
+                ContentEventType::Unspecified => {}
+            }
+        }
+        if daily.is_empty() {
+            return Ok(0);
+        }
+
+        let (keys, counts): (Vec<_>, Vec<_>) = daily.into_iter().unzip();
+        let ids = keys.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let days = keys.iter().map(|(_, day)| *day).collect::<Vec<_>>();
+        let views = counts.iter().map(|c| c.views).collect::<Vec<_>>();
+        let likes = counts.iter().map(|c| c.likes).collect::<Vec<_>>();
+        let dislikes = counts.iter().map(|c| c.dislikes).collect::<Vec<_>>();
+        sqlx::query(UPSERT_DAILY_SQL)
+            .bind(&ids)
+            .bind(&days)
+            .bind(&views)
+            .bind(&likes)
+            .bind(&dislikes)
+            .execute(&mut *tx)
+            .instrument(sql_span("upsert_daily_counts"))
+            .await
+            .map_err(internal)?;
+        sqlx::query(UPDATE_TOTALS_SQL)
+            .bind(&ids)
+            .bind(&views)
+            .bind(&likes)
+            .bind(&dislikes)
+            .execute(&mut *tx)
+            .instrument(sql_span("update_totals"))
+            .await
+            .map_err(internal)?;
+        tx.commit().await.map_err(internal)?;
+
+        // cached contents hold the old totals
+        for id in known {
+            self.cache.invalidate(id as u32);
+        }
+        Ok(accepted)
+    }
+}
+
+/// Returns None if the event is malformed
+fn parse_event(event: ContentEvent) -> Option<(i32, NaiveDate, ContentEventType)> {
+    if !event.is_valid() {
+        return None;
+    }
+    let event_type = ContentEventType::try_from(event.event_type).ok()?;
+    let content_id = i32::try_from(event.content_id).ok()?;
+    let day = event
+        .timestamp
+        .as_ref()
+        .and_then(ToUtc::to_utc)
+        .unwrap_or_else(Utc::now)
+        .date_naive();
+    Some((content_id, day, event_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use prost_types::Timestamp;
+
+    use super::*;
+
+    fn event(content_id: u32, event_type: ContentEventType) -> ContentEvent {
+        ContentEvent {
+            content_id,
+            event_type: event_type as i32,
+            timestamp: None,
+        }
+    }
+
+    async fn ingest(svc: &MetadataService, events: Vec<ContentEvent>) -> IngestResponse {
+        svc.ingest(tokio_stream::iter(events.into_iter().map(Ok)))
+            .await
+            .unwrap()
+            .into_inner()
+    }
+
+    #[test]
+    fn parse_event_should_reject_malformed_events() {
+        assert!(parse_event(event(1, ContentEventType::View)).is_some());
+        assert!(parse_event(event(1, ContentEventType::Unspecified)).is_none());
+        assert!(parse_event(event(0, ContentEventType::Like)).is_none());
+        assert!(parse_event(ContentEvent {
+            event_type: 10,
+            ..event(1, ContentEventType::View)
+        })
+        .is_none());
+    }
+
+    #[tokio::test]
+    async fn ingest_should_update_totals() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let before = svc.get_content(1).await.unwrap();
+        let events = vec![
+            event(1, ContentEventType::View),
+            event(1, ContentEventType::View),
+            event(1, ContentEventType::Like),
+            event(1, ContentEventType::Dislike),
+            event(10000, ContentEventType::View),
+            event(1, ContentEventType::Unspecified),
+        ];
+        let res = ingest(&svc, events).await;
+        assert_eq!(res.accepted, 4);
+        assert_eq!(res.rejected, 2);
+
+        let after = svc.get_content(1).await.unwrap();
+        assert_eq!(after.views, before.views + 2);
+        assert_eq!(after.likes, before.likes + 1);
+        assert_eq!(after.dislikes, before.dislikes + 1);
+    }
+
+    #[tokio::test]
+    async fn trending_should_rank_recent_activity_per_type() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let t1 = svc.get_content(1).await.unwrap().content_type();
+        let same_type = svc
+            .list_contents(crate::pb::metadata::ListContentsRequest {
+                content_types: vec![t1 as i32],
+                page_size: 100,
+                ..Default::default()
+            })
+            .await
+            .unwrap()
+            .into_inner()
+            .contents;
+        let other = same_type.iter().find(|c| c.id != 1).unwrap().id;
+
+        let old = Timestamp {
+            seconds: (Utc::now() - Duration::days(30)).timestamp(),
+            nanos: 0,
+        };
+        let mut events = vec![
+            event(1, ContentEventType::Like),
+            event(other, ContentEventType::View),
+            event(other, ContentEventType::View),
+        ];
+        // outside of the window
+        events.extend((0..10).map(|_| ContentEvent {
+            timestamp: Some(old),
+            ..event(other, ContentEventType::Like)
+        }));
+        ingest(&svc, events).await;
+
+        let req = TrendingRequest {
+            content_types: vec![t1 as i32],
+            ..Default::default()
+        };
+        let res = svc.trending(req).await.unwrap().into_inner();
+        assert_eq!(res.groups.len(), 1);
+        let group = &res.groups[0];
+        assert_eq!(group.content_type, t1 as i32);
+        let ids = group
+            .contents
+            .iter()
+            .map(|c| c.content.as_ref().unwrap().id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, other]);
+        assert_eq!(group.contents[0].score, 5);
+        assert_eq!(group.contents[1].views, 2);
+
+        let res = svc
+            .trending(TrendingRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.groups.len(), 4);
+    }
+}
@@ -0,0 +1,393 @@
+use chrono::{DateTime, Utc};
+use crm_core::telemetry::sql_span;
+use sqlx::{PgConnection, Postgres, Transaction};
+use tonic::Response;
+use tracing::Instrument;
+
+use crate::{
+    error::db,
+    pb::metadata::{
+        Content, ContentType, CreateContentRequest, CreatePublisherRequest, DeleteContentRequest,
+        DeletePublisherRequest, DeleteResponse, Publisher, UpdateContentRequest,
+    },
+    Error, MetadataService, ServiceResult,
+};
+
+const INSERT_CONTENT_SQL: &str = r#"
+    INSERT INTO contents(name, description, url, image, content_type, created_at)
+    VALUES ($1, $2, $3, $4, $5::text::content_type, COALESCE($6, CURRENT_TIMESTAMP))
+    RETURNING id
+    "#;
+
+const UPDATE_CONTENT_SQL: &str = r#"
+    UPDATE contents
+    SET name = $2, description = $3, url = $4, image = $5, content_type = $6::text::content_type
+    WHERE id = $1
+    RETURNING id
+    "#;
+
+const LINK_PUBLISHERS_SQL: &str = r#"
+    INSERT INTO content_publishers(content_id, publisher_id)
+    SELECT $1, unnest($2::int[])
+    ON CONFLICT DO NOTHING
+    "#;
+
+/// Editable fields of a content, validated
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct NewContent {
+    pub(super) name: String,
+    pub(super) description: String,
+    pub(super) url: String,
+    pub(super) image: String,
+    pub(super) content_type: ContentType,
+    pub(super) publisher_ids: Vec<i32>,
+    /// defaults to now, only used on insert
+    pub(super) created_at: Option<DateTime<Utc>>,
+}
+
+impl MetadataService {
+    pub async fn create_content(&self, req: CreateContentRequest) -> ServiceResult<Content> {
+        let content = NewContent::from(req);
+        let mut tx = self.begin().await?;
+        let id = insert_content(&mut tx, &content)
+            .await
+            .map_err(write_error("create content"))?;
+        tx.commit().await.map_err(write_error("create content"))?;
+
+        Ok(Response::new(self.get_content(id as u32).await?))
+    }
+
+    pub async fn update_content(&self, req: UpdateContentRequest) -> ServiceResult<Content> {
+        let id = req.id;
+        let content = NewContent::from(req);
+        let not_found = || Error::ContentNotFound(id);
+        let id = i32::try_from(id).map_err(|_| not_found())?;
+
+        let mut tx = self.begin().await?;
+        let updated: Option<i32> = sqlx::query_scalar(UPDATE_CONTENT_SQL)
+            .bind(id)
+            .bind(&content.name)
+            .bind(&content.description)
+            .bind(&content.url)
+            .bind(&content.image)
+            .bind(content.content_type.db_name())
+            .fetch_optional(&mut *tx)
+            .instrument(sql_span("update_content"))
+            .await
+            .map_err(write_error("update content"))?;
+        if updated.is_none() {
+            return Err(not_found().into());
+        }
+        sqlx::query("DELETE FROM content_publishers WHERE content_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .instrument(sql_span("unlink_publishers"))
+            .await
+            .map_err(write_error("update content"))?;
+        link_publishers(&mut tx, id, &content.publisher_ids)
+            .await
+            .map_err(write_error("update content"))?;
+        tx.commit().await.map_err(write_error("update content"))?;
+        self.cache.invalidate(id as u32);
+
+        Ok(Response::new(self.get_content(id as u32).await?))
+    }
+
+    pub async fn delete_content(&self, req: DeleteContentRequest) -> ServiceResult<DeleteResponse> {
+        let Ok(id) = i32::try_from(req.id) else {
+            return Ok(Response::new(DeleteResponse { deleted: false }));
+        };
+        let deleted = sqlx::query("DELETE FROM contents WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .instrument(sql_span("delete_content"))
+            .await
+            .map_err(write_error("delete content"))?
+            .rows_affected();
+        self.cache.invalidate(req.id);
+        Ok(Response::new(DeleteResponse {
+            deleted: deleted > 0,
+        }))
+    }
+
+    pub async fn create_publisher(&self, req: CreatePublisherRequest) -> ServiceResult<Publisher> {
+        let (id, name, avatar): (i32, String, String) = sqlx::query_as(
+            "INSERT INTO publishers(name, avatar) VALUES ($1, $2) RETURNING id, name, avatar",
+        )
+        .bind(req.name)
+        .bind(req.avatar)
+        .fetch_one(&self.pool)
+        .instrument(sql_span("create_publisher"))
+        .await
+        .map_err(write_error("create publisher"))?;
+        Ok(Response::new(Publisher {
+            id: id as u32,
+            name,
+            avatar,
+        }))
+    }
+
+    pub async fn update_publisher(&self, req: Publisher) -> ServiceResult<Publisher> {
+        let not_found = || Error::PublisherNotFound(req.id);
+        let id = i32::try_from(req.id).map_err(|_| not_found())?;
+        let updated: Option<(String, String)> = sqlx::query_as(
+            "UPDATE publishers SET name = $2, avatar = $3 WHERE id = $1 RETURNING name, avatar",
+        )
+        .bind(id)
+        .bind(&req.name)
+        .bind(&req.avatar)
+        .fetch_optional(&self.pool)
+        .instrument(sql_span("update_publisher"))
+        .await
+        .map_err(write_error("update publisher"))?;
+        let (name, avatar) = updated.ok_or_else(not_found)?;
+        // the publisher is embedded in every content it published
+        self.cache.clear();
+        Ok(Response::new(Publisher {
+            id: req.id,
+            name,
+            avatar,
+        }))
+    }
+
+    pub async fn delete_publisher(
+        &self,
+        req: DeletePublisherRequest,
+    ) -> ServiceResult<DeleteResponse> {
+        let Ok(id) = i32::try_from(req.id) else {
+            return Ok(Response::new(DeleteResponse { deleted: false }));
+        };
+        let deleted = sqlx::query("DELETE FROM publishers WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .instrument(sql_span("delete_publisher"))
+            .await
+            .map_err(write_error("delete publisher"))?
+            .rows_affected();
+        self.cache.clear();
+        Ok(Response::new(DeleteResponse {
+            deleted: deleted > 0,
+        }))
+    }
+
+    pub(super) async fn begin(&self) -> Result<Transaction<'static, Postgres>, Error> {
+        self.pool.begin().await.map_err(db("begin transaction"))
+    }
+}
+
+pub(super) async fn insert_content(
+    conn: &mut PgConnection,
+    content: &NewContent,
+) -> sqlx::Result<i32> {
+    let id: i32 = sqlx::query_scalar(INSERT_CONTENT_SQL)
+        .bind(&content.name)
+        .bind(&content.description)
+        .bind(&content.url)
+        .bind(&content.image)
+        .bind(content.content_type.db_name())
+        .bind(content.created_at)
+        .fetch_one(&mut *conn)
+        .instrument(sql_span("insert_content"))
+        .await?;
+    link_publishers(conn, id, &content.publisher_ids).await?;
+    Ok(id)
+}
+
+async fn link_publishers(
+    conn: &mut PgConnection,
+    content_id: i32,
+    publisher_ids: &[i32],
+) -> sqlx::Result<()> {
+    sqlx::query(LINK_PUBLISHERS_SQL)
+        .bind(content_id)
+        .bind(publisher_ids)
+        .execute(conn)
+        .instrument(sql_span("link_publishers"))
+        .await?;
+    Ok(())
+}
+
+/// A foreign key violation means one of the publishers doesn't exist,
+/// which is the caller's fault rather than ours
+pub(super) fn write_error(action: &'static str) -> impl Fn(sqlx::Error) -> Error {
+    move |e| match &e {
+        sqlx::Error::Database(e) if e.is_foreign_key_violation() => {
+            Error::invalid("publisher_ids", "unknown publisher")
+        }
+        _ => db(action)(e),
+    }
+}
+
+impl From<CreateContentRequest> for NewContent {
+    fn from(req: CreateContentRequest) -> Self {
+        Self {
+            content_type: req.content_type(),
+            name: req.name,
+            description: req.description,
+            url: req.url,
+            image: req.image,
+            publisher_ids: to_db_ids(&req.publisher_ids),
+            created_at: None,
+        }
+    }
+}
+
+impl From<UpdateContentRequest> for NewContent {
+    fn from(req: UpdateContentRequest) -> Self {
+        Self {
+            content_type: req.content_type(),
+            name: req.name,
+            description: req.description,
+            url: req.url,
+            image: req.image,
+            publisher_ids: to_db_ids(&req.publisher_ids),
+            created_at: None,
+        }
+    }
+}
+
+fn to_db_ids(ids: &[u32]) -> Vec<i32> {
+    ids.iter()
+        .map(|id| i32::try_from(*id).expect("publisher_ids are checked by the validation"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use tonic::Request;
+
+    use super::*;
+    use crate::pb::metadata::metadata_server::Metadata;
+
+    fn create_request() -> CreateContentRequest {
+        CreateContentRequest {
+            name: "Learning Rust".to_string(),
+            description: "ownership explained".to_string(),
+            url: "https://example.com/contents/rust".to_string(),
+            image: "https://example.com/images/rust.png".to_string(),
+            content_type: ContentType::Vlog as i32,
+            publisher_ids: vec![1, 2],
+        }
+    }
+
+    #[tokio::test]
+    async fn content_crud_should_work() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let content = svc
+            .create_content(create_request())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(content.name, "Learning Rust");
+        assert_eq!(content.content_type, ContentType::Vlog as i32);
+        let ids = content.publishers.iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2]);
+
+        let req = UpdateContentRequest {
+            id: content.id,
+            name: "Learning Rust, 2nd edition".to_string(),
+            description: content.description.clone(),
+            url: content.url.clone(),
+            image: content.image.clone(),
+            content_type: ContentType::Movie as i32,
+            publisher_ids: vec![3],
+        };
+        let updated = svc.update_content(req).await.unwrap().into_inner();
+        assert_eq!(updated.name, "Learning Rust, 2nd edition");
+        assert_eq!(updated.content_type, ContentType::Movie as i32);
+        assert_eq!(updated.publishers.len(), 1);
+        assert_eq!(updated.publishers[0].id, 3);
+        assert_eq!(updated.created_at, content.created_at);
+
+        let req = DeleteContentRequest { id: content.id };
+        assert!(svc.delete_content(req).await.unwrap().into_inner().deleted);
+        assert!(!svc.delete_content(req).await.unwrap().into_inner().deleted);
+        let err = svc.get_content(content.id).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn writes_should_invalidate_cache() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let content = svc.get_content(1).await.unwrap();
+        let req = Publisher {
+            id: content.publishers[0].id,
+            name: "Renamed".to_string(),
+            avatar: String::new(),
+        };
+        svc.update_publisher(req).await.unwrap();
+        let cached = svc.get_content(1).await.unwrap();
+        assert!(cached.publishers.iter().any(|p| p.name == "Renamed"));
+
+        let req = DeleteContentRequest { id: 1 };
+        svc.delete_content(req).await.unwrap();
+        assert!(svc.get_content(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn content_with_unknown_publisher_should_be_rejected() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = CreateContentRequest {
+            publisher_ids: vec![10000],
+            ..create_request()
+        };
+        let err = svc.create_content(req).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let details = crm_core::ErrorDetails::from_status(&err);
+        assert_eq!(details.fields(), vec!["publisher_ids"]);
+
+        let req = UpdateContentRequest {
+            id: 10000,
+            name: "missing".to_string(),
+            url: "https://example.com".to_string(),
+            image: "https://example.com".to_string(),
+            content_type: ContentType::Short as i32,
+            publisher_ids: vec![1],
+            ..Default::default()
+        };
+        let err = svc.update_content(req).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+        let details = crm_core::ErrorDetails::from_status(&err);
+        assert_eq!(details.reason(), Some("CONTENT_NOT_FOUND"));
+        assert_eq!(details.info.unwrap().metadata["id"], "10000");
+    }
+
+    #[tokio::test]
+    async fn publisher_crud_should_work() {
+        let (_tdb, svc) = MetadataService::new_for_test().await;
+        let req = CreatePublisherRequest {
+            name: "Tyr Chen".to_string(),
+            avatar: String::new(),
+        };
+        let publisher = svc.create_publisher(req).await.unwrap().into_inner();
+        assert_eq!(publisher.name, "Tyr Chen");
+
+        let req = Publisher {
+            avatar: "https://example.com/avatar.png".to_string(),
+            ..publisher.clone()
+        };
+        let updated = svc.update_publisher(req).await.unwrap().into_inner();
+        assert_eq!(updated.avatar, "https://example.com/avatar.png");
+
+        let req = Publisher {
+            avatar: "avatar.png".to_string(),
+            ..publisher.clone()
+        };
+        let err = Metadata::update_publisher(&svc, Request::new(req))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+
+        // contents published by the deleted publisher are kept
+        let req = DeletePublisherRequest { id: 1 };
+        assert!(
+            svc.delete_publisher(req)
+                .await
+                .unwrap()
+                .into_inner()
+                .deleted
+        );
+        let content = svc.get_content(1).await.unwrap();
+        assert!(content.publishers.iter().all(|p| p.id != 1));
+    }
+}
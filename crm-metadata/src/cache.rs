@@ -0,0 +1,289 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crm_core::metrics::{self, Counter};
+use hashlink::LruCache;
+use tokio::sync::OnceCell;
+use tonic::Status;
+
+use crate::{config::CacheConfig, pb::metadata::Content};
+
+type Load = Arc<OnceCell<Result<Content, Status>>>;
+
+static CACHE_REQUESTS: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "metadata_cache_requests_total",
+        "Contents looked up in the materialize cache, by hit or miss",
+        &["result"],
+    )
+});
+static CACHE_COALESCED: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "metadata_cache_coalesced_total",
+        "Cache misses served by a load started by another request",
+        &[],
+    )
+});
+
+/// LRU cache of materialized contents. Concurrent loads of the same id are
+/// coalesced into a single storage hit, only found contents are cached.
+#[derive(Debug)]
+pub struct ContentCache {
+    entries: Mutex<LruCache<u32, Entry>>,
+    inflight: Mutex<HashMap<u32, Load>>,
+    ttl: Duration,
+    /// bumped on every invalidation, a load started before it must not be cached
+    generation: AtomicU64,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    coalesced: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    content: Content,
+    expires_at: Instant,
+}
+
+/// Counters since the service started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    /// lookups that went to storage, or joined a load already in flight
+    pub misses: u64,
+    /// misses served by a load started by another request
+    pub coalesced: u64,
+    pub size: usize,
+}
+
+impl ContentCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        Self {
+            entries: Mutex::new(LruCache::new(config.capacity)),
+            inflight: Mutex::new(HashMap::new()),
+            ttl: config.ttl(),
+            generation: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            coalesced: AtomicU64::new(0),
+        }
+    }
+
+    /// Get the content from the cache, or from `load` if it's missing or expired
+    pub async fn get_or_load<F, Fut>(&self, id: u32, load: F) -> Result<Content, Status>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Content, Status>>,
+    {
+        if let Some(content) = self.get(id) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            CACHE_REQUESTS.inc(&["hit"]);
+            return Ok(content);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        CACHE_REQUESTS.inc(&["miss"]);
+
+        let generation = self.generation.load(Ordering::Acquire);
+        let cell = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&id) {
+                Some(cell) => {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    CACHE_COALESCED.inc(&[]);
+                    cell.clone()
+                }
+                None => {
+                    let cell = Load::default();
+                    inflight.insert(id, cell.clone());
+                    cell
+                }
+            }
+        };
+
+        // if the request driving the load is dropped, a waiting one takes over
+        let ret = cell
+            .get_or_init(|| async {
+                let ret = load().await;
+                if let Ok(content) = &ret {
+                    self.insert(id, content.clone(), generation);
+                }
+                ret
+            })
+            .await
+            .clone();
+
+        let mut inflight = self.inflight.lock().unwrap();
+        if inflight.get(&id).is_some_and(|c| Arc::ptr_eq(c, &cell)) {
+            inflight.remove(&id);
+        }
+        ret
+    }
+
+    /// Drop the content so the next lookup reloads it
+    pub fn invalidate(&self, id: u32) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.inflight.lock().unwrap().remove(&id);
+        self.entries.lock().unwrap().remove(&id);
+    }
+
+    /// Drop every content, e.g. when a publisher embedded in many of them changed
+    pub fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.inflight.lock().unwrap().clear();
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            coalesced: self.coalesced.load(Ordering::Relaxed),
+            size: self.entries.lock().unwrap().len(),
+        }
+    }
+
+    fn get(&self, id: u32) -> Option<Content> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&id) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.content.clone()),
+            Some(_) => {
+                entries.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, id: u32, content: Content, generation: u64) {
+        let mut entries = self.entries.lock().unwrap();
+        // checked under the lock, invalidations bump the generation before taking it
+        if self.generation.load(Ordering::Acquire) != generation {
+            return;
+        }
+        let expires_at = Instant::now() + self.ttl;
+        entries.insert(
+            id,
+            Entry {
+                content,
+                expires_at,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    fn new_cache(capacity: usize, ttl_secs: u64) -> ContentCache {
+        ContentCache::new(&CacheConfig { capacity, ttl_secs })
+    }
+
+    fn content(id: u32) -> Content {
+        Content {
+            id,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn cache_should_hit_after_first_load() {
+        let cache = new_cache(10, 60);
+        let loads = AtomicUsize::new(0);
+        for _ in 0..3 {
+            let ret = cache
+                .get_or_load(1, || async {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    Ok(content(1))
+                })
+                .await
+                .unwrap();
+            assert_eq!(ret.id, 1);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.size), (2, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn cache_should_not_keep_errors() {
+        let cache = new_cache(10, 60);
+        let err = cache
+            .get_or_load(1, || async {
+                Err(Status::not_found("content 1 not found"))
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+        let ret = cache.get_or_load(1, || async { Ok(content(1)) }).await;
+        assert!(ret.is_ok());
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn cache_should_expire_and_evict() {
+        let cache = new_cache(2, 0);
+        cache
+            .get_or_load(1, || async { Ok(content(1)) })
+            .await
+            .unwrap();
+        cache
+            .get_or_load(1, || async { Ok(content(1)) })
+            .await
+            .unwrap();
+        assert_eq!(cache.stats().hits, 0);
+
+        let cache = new_cache(2, 60);
+        for id in [1, 2, 3] {
+            cache
+                .get_or_load(id, || async move { Ok(content(id)) })
+                .await
+                .unwrap();
+        }
+        assert_eq!(cache.stats().size, 2);
+        assert!(cache.get(1).is_none());
+        assert!(cache.get(3).is_some());
+
+        cache.invalidate(3);
+        assert!(cache.get(3).is_none());
+        cache.clear();
+        assert_eq!(cache.stats().size, 0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_loads_should_be_coalesced() {
+        let cache = Arc::new(new_cache(10, 60));
+        let loads = Arc::new(AtomicUsize::new(0));
+        let tasks = (0..10)
+            .map(|_| {
+                let cache = cache.clone();
+                let loads = loads.clone();
+                tokio::spawn(async move {
+                    cache
+                        .get_or_load(1, || async move {
+                            loads.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            Ok(content(1))
+                        })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            assert_eq!(task.await.unwrap().unwrap().id, 1);
+        }
+        assert_eq!(loads.load(Ordering::SeqCst), 1);
+        let stats = cache.stats();
+        assert_eq!(stats.misses, 10);
+        assert_eq!(stats.coalesced, 9);
+    }
+}
@@ -1,40 +1,79 @@
-use anyhow::{bail, Result};
+use crm_core::{AuthConfig, Checks, Config, ServerConfig, TelemetryConfig};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
-    pub server: ServerConfig,
+    pub server: ServerConfig<Db>,
     pub auth: AuthConfig,
+    pub cache: CacheConfig,
+    pub ingest: IngestConfig,
+    /// span export, traces stay local when unset
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
 }
 
+/// The database of the service
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ServerConfig {
-    // pub host: String,
-    pub port: u16,
+pub struct Db {
+    pub db_url: String,
 }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AuthConfig {
-    pub pk: String,
+pub struct CacheConfig {
+    /// max number of contents kept by materialize, 0 disables the cache
+    pub capacity: usize,
+    /// seconds a content is served from the cache before being reloaded
+    pub ttl_secs: u64,
 }
 
-impl AppConfig {
-    pub fn load() -> Result<Self> {
-        let ret: Result<AppConfig, _> = match (
-            fs::File::open("metadata.yml"),
-            fs::File::open("/etc/config/metadata.yml"),
-            std::env::var("METADATA_CONFIG"),
-        ) {
-            (Ok(reader), _, _) => serde_yaml::from_reader(reader),
-            (Err(_), Ok(reader), _) => serde_yaml::from_reader(reader),
-            (Err(_), Err(_), Ok(reader)) => serde_yaml::from_reader(fs::File::open(reader)?),
-            _ => bail!("Config file not found"),
-        };
-
-        Ok(ret?)
+impl CacheConfig {
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_secs)
     }
 }
 
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct IngestConfig {
+    /// max number of content events whose counts are added up in one transaction
+    pub batch_size: usize,
+}
+
+impl Config for AppConfig {
+    const NAME: &'static str = "metadata";
+    const ENV_PREFIX: &'static str = "METADATA";
+    const DEFAULTS: &'static str = r#"
+server:
+    port: 50002
+    metrics_port: 9102
+    shutdown_timeout_secs: 30
+cache:
+    capacity: 10000
+    ttl_secs: 60
+ingest:
+    batch_size: 500
+"#;
+    const SECRETS: &'static [&'static str] = &["server.db_url", "server.tls.key"];
+
+    fn validate(&self, checks: &mut Checks) {
+        self.server.validate("server", checks);
+        checks.url(
+            "server.db_url",
+            &self.server.extra.db_url,
+            &["postgres", "postgresql"],
+        );
+        self.auth.validate("auth", checks);
+        checks.check(
+            "ingest.batch_size",
+            self.ingest.batch_size > 0,
+            "must not be 0",
+        );
+        self.telemetry.validate(checks);
+    }
+}
+
+crm_core::service_config!(AppConfig, Db);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use crm_core::{FieldViolation, ServiceError};
+use tonic::{Code, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// `field` is the path of the invalid field, e.g. `records[2].url`
+    #[error("{description}")]
+    InvalidArgument { field: String, description: String },
+    #[error("content {0} not found")]
+    ContentNotFound(u32),
+    #[error("publisher {0} not found")]
+    PublisherNotFound(u32),
+    /// the db error is logged, never sent to the caller
+    #[error("failed to {op}")]
+    Db {
+        op: &'static str,
+        #[source]
+        source: sqlx::Error,
+    },
+}
+
+impl Error {
+    pub fn invalid(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Error::InvalidArgument {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Maps the failure of a db call made to `op`
+pub(crate) fn db(op: &'static str) -> impl Fn(sqlx::Error) -> Error + Copy {
+    move |source| Error::Db { op, source }
+}
+
+impl ServiceError for Error {
+    const DOMAIN: &'static str = "crm-metadata";
+
+    fn code(&self) -> Code {
+        match self {
+            Error::InvalidArgument { .. } => Code::InvalidArgument,
+            Error::ContentNotFound(_) | Error::PublisherNotFound(_) => Code::NotFound,
+            Error::Db { .. } => Code::Internal,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Error::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            Error::ContentNotFound(_) => "CONTENT_NOT_FOUND",
+            Error::PublisherNotFound(_) => "PUBLISHER_NOT_FOUND",
+            Error::Db { .. } => "INTERNAL",
+        }
+    }
+
+    fn violations(&self) -> Vec<FieldViolation> {
+        match self {
+            Error::InvalidArgument { field, description } => {
+                vec![FieldViolation::new(field, description)]
+            }
+            _ => vec![],
+        }
+    }
+
+    fn metadata(&self) -> HashMap<String, String> {
+        match self {
+            Error::ContentNotFound(id) | Error::PublisherNotFound(id) => {
+                HashMap::from([("id".to_string(), id.to_string())])
+            }
+            _ => HashMap::new(),
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        e.into_status()
+    }
+}
@@ -33,11 +33,229 @@ pub struct Publisher {
     #[prost(string, tag = "3")]
     pub avatar: ::prost::alloc::string::String,
 }
-#[derive(Eq, Hash, Clone, Copy, PartialEq, ::prost::Message)]
+#[derive(Eq, Hash)]
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
 pub struct MaterializeRequest {
     #[prost(uint32, tag = "1")]
     pub id: u32,
 }
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchGetRequest {
+    /// at most 1000 ids
+    #[prost(uint32, repeated, tag = "1")]
+    pub ids: ::prost::alloc::vec::Vec<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BatchGetResponse {
+    /// found contents in the order of the request ids
+    #[prost(message, repeated, tag = "1")]
+    pub contents: ::prost::alloc::vec::Vec<Content>,
+    /// ids with no content, in the order of the request ids
+    #[prost(uint32, repeated, tag = "2")]
+    pub missing_ids: ::prost::alloc::vec::Vec<u32>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MaterializeError {
+    /// grpc status code, e.g. 5 (NOT_FOUND)
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: ::prost::alloc::string::String,
+}
+/// responses are sent as soon as they resolve, not in request order
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct MaterializeResponse {
+    /// id of the request this responds to
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(oneof = "materialize_response::Result", tags = "2, 3")]
+    pub result: ::core::option::Option<materialize_response::Result>,
+}
+/// Nested message and enum types in `MaterializeResponse`.
+pub mod materialize_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Result {
+        #[prost(message, tag = "2")]
+        Content(super::Content),
+        #[prost(message, tag = "3")]
+        Error(super::MaterializeError),
+    }
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListContentsRequest {
+    /// match any of the types, all types if empty
+    #[prost(enumeration = "ContentType", repeated, tag = "1")]
+    pub content_types: ::prost::alloc::vec::Vec<i32>,
+    /// match contents published by any of the publishers, all publishers if empty
+    #[prost(uint32, repeated, tag = "2")]
+    pub publisher_ids: ::prost::alloc::vec::Vec<u32>,
+    #[prost(message, optional, tag = "3")]
+    pub created_after: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(message, optional, tag = "4")]
+    pub created_before: ::core::option::Option<::prost_types::Timestamp>,
+    #[prost(enumeration = "ContentOrder", tag = "5")]
+    pub order_by: i32,
+    /// defaults to 20, at most 100
+    #[prost(uint32, tag = "6")]
+    pub page_size: u32,
+    /// next_page_token of the previous response
+    #[prost(string, tag = "7")]
+    pub page_token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SearchContentsRequest {
+    /// words to look for in name and description
+    #[prost(string, tag = "1")]
+    pub query: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub page_size: u32,
+    #[prost(string, tag = "3")]
+    pub page_token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ListContentsResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub contents: ::prost::alloc::vec::Vec<Content>,
+    /// empty if there are no more pages
+    #[prost(string, tag = "2")]
+    pub next_page_token: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateContentRequest {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub description: ::prost::alloc::string::String,
+    /// must be a valid http(s) url
+    #[prost(string, tag = "3")]
+    pub url: ::prost::alloc::string::String,
+    /// must be a valid http(s) url
+    #[prost(string, tag = "4")]
+    pub image: ::prost::alloc::string::String,
+    /// must not be unspecified
+    #[prost(enumeration = "ContentType", tag = "5")]
+    pub content_type: i32,
+    /// at least one existing publisher
+    #[prost(uint32, repeated, tag = "6")]
+    pub publisher_ids: ::prost::alloc::vec::Vec<u32>,
+}
+/// replaces every editable field of the content
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateContentRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+    #[prost(string, tag = "2")]
+    pub name: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub description: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub url: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub image: ::prost::alloc::string::String,
+    #[prost(enumeration = "ContentType", tag = "6")]
+    pub content_type: i32,
+    #[prost(uint32, repeated, tag = "7")]
+    pub publisher_ids: ::prost::alloc::vec::Vec<u32>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeleteContentRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreatePublisherRequest {
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// empty or a valid http(s) url
+    #[prost(string, tag = "2")]
+    pub avatar: ::prost::alloc::string::String,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeletePublisherRequest {
+    #[prost(uint32, tag = "1")]
+    pub id: u32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct DeleteResponse {
+    #[prost(bool, tag = "1")]
+    pub deleted: bool,
+}
+/// records have the fields name, description, url, image, content_type (short, vlog,
+/// movie or ai_generated), publishers (names, created if missing) and an optional
+/// RFC 3339 created_at. The import is all or nothing.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ImportContentsRequest {
+    #[prost(enumeration = "ImportFormat", tag = "1")]
+    pub format: i32,
+    #[prost(bytes = "vec", tag = "2")]
+    pub data: ::prost::alloc::vec::Vec<u8>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ImportContentsResponse {
+    #[prost(uint32, tag = "1")]
+    pub contents: u32,
+    /// publishers created for names not yet in the catalog
+    #[prost(uint32, tag = "2")]
+    pub publishers: u32,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ContentEvent {
+    #[prost(uint32, tag = "1")]
+    pub content_id: u32,
+    #[prost(enumeration = "ContentEventType", tag = "2")]
+    pub event_type: i32,
+    /// defaults to the time the event is received
+    #[prost(message, optional, tag = "3")]
+    pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct IngestResponse {
+    #[prost(uint64, tag = "1")]
+    pub accepted: u64,
+    /// malformed events and events of unknown contents
+    #[prost(uint64, tag = "2")]
+    pub rejected: u64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrendingRequest {
+    /// one group per type, every type if empty
+    #[prost(enumeration = "ContentType", repeated, tag = "1")]
+    pub content_types: ::prost::alloc::vec::Vec<i32>,
+    /// window ending today (UTC), defaults to 7, at most 90
+    #[prost(uint32, tag = "2")]
+    pub days: u32,
+    /// contents per type, defaults to 10, at most 100
+    #[prost(uint32, tag = "3")]
+    pub limit: u32,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrendingContent {
+    #[prost(message, optional, tag = "1")]
+    pub content: ::core::option::Option<Content>,
+    /// counters over the window
+    #[prost(uint64, tag = "2")]
+    pub views: u64,
+    #[prost(uint64, tag = "3")]
+    pub likes: u64,
+    #[prost(uint64, tag = "4")]
+    pub dislikes: u64,
+    /// views + 5 * likes - 5 * dislikes over the window
+    #[prost(int64, tag = "5")]
+    pub score: i64,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrendingGroup {
+    #[prost(enumeration = "ContentType", tag = "1")]
+    pub content_type: i32,
+    /// highest score first, contents without events in the window are left out
+    #[prost(message, repeated, tag = "2")]
+    pub contents: ::prost::alloc::vec::Vec<TrendingContent>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TrendingResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub groups: ::prost::alloc::vec::Vec<TrendingGroup>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum ContentType {
@@ -73,6 +291,100 @@ impl ContentType {
         }
     }
 }
+/// contents are always sorted in descending order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ContentOrder {
+    /// newest first
+    Unspecified = 0,
+    Views = 1,
+    Likes = 2,
+}
+impl ContentOrder {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "CONTENT_ORDER_UNSPECIFIED",
+            Self::Views => "CONTENT_ORDER_VIEWS",
+            Self::Likes => "CONTENT_ORDER_LIKES",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CONTENT_ORDER_UNSPECIFIED" => Some(Self::Unspecified),
+            "CONTENT_ORDER_VIEWS" => Some(Self::Views),
+            "CONTENT_ORDER_LIKES" => Some(Self::Likes),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ImportFormat {
+    Unspecified = 0,
+    /// an array of records
+    Json = 1,
+    /// a header line then one record per line, publishers separated by `;`
+    Csv = 2,
+}
+impl ImportFormat {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "IMPORT_FORMAT_UNSPECIFIED",
+            Self::Json => "IMPORT_FORMAT_JSON",
+            Self::Csv => "IMPORT_FORMAT_CSV",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "IMPORT_FORMAT_UNSPECIFIED" => Some(Self::Unspecified),
+            "IMPORT_FORMAT_JSON" => Some(Self::Json),
+            "IMPORT_FORMAT_CSV" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum ContentEventType {
+    Unspecified = 0,
+    View = 1,
+    Like = 2,
+    Dislike = 3,
+}
+impl ContentEventType {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "CONTENT_EVENT_TYPE_UNSPECIFIED",
+            Self::View => "CONTENT_EVENT_TYPE_VIEW",
+            Self::Like => "CONTENT_EVENT_TYPE_LIKE",
+            Self::Dislike => "CONTENT_EVENT_TYPE_DISLIKE",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CONTENT_EVENT_TYPE_UNSPECIFIED" => Some(Self::Unspecified),
+            "CONTENT_EVENT_TYPE_VIEW" => Some(Self::View),
+            "CONTENT_EVENT_TYPE_LIKE" => Some(Self::Like),
+            "CONTENT_EVENT_TYPE_DISLIKE" => Some(Self::Dislike),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod metadata_client {
     #![allow(
@@ -80,10 +392,10 @@ pub mod metadata_client {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
-    use tonic::codegen::http::Uri;
     use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
     #[derive(Debug, Clone)]
     pub struct MetadataClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -127,8 +439,9 @@ pub mod metadata_client {
                     <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
                 >,
             >,
-            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
-                Into<StdError> + std::marker::Send + std::marker::Sync,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
         {
             MetadataClient::new(InterceptedService::new(inner, interceptor))
         }
@@ -163,23 +476,302 @@ pub mod metadata_client {
             self.inner = self.inner.max_encoding_message_size(limit);
             self
         }
+        /// a failed id gets an error response, the stream goes on with the others
         pub async fn materialize(
             &mut self,
-            request: impl tonic::IntoStreamingRequest<Message = super::MaterializeRequest>,
+            request: impl tonic::IntoStreamingRequest<
+                Message = super::MaterializeRequest,
+            >,
         ) -> std::result::Result<
-            tonic::Response<tonic::codec::Streaming<super::Content>>,
+            tonic::Response<tonic::codec::Streaming<super::MaterializeResponse>>,
             tonic::Status,
         > {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
-            })?;
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
             let codec = tonic::codec::ProstCodec::default();
-            let path = http::uri::PathAndQuery::from_static("/metadata.Metadata/Materialize");
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/Materialize",
+            );
             let mut req = request.into_streaming_request();
             req.extensions_mut()
                 .insert(GrpcMethod::new("metadata.Metadata", "Materialize"));
             self.inner.streaming(req, path, codec).await
         }
+        pub async fn batch_get(
+            &mut self,
+            request: impl tonic::IntoRequest<super::BatchGetRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BatchGetResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/BatchGet",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "BatchGet"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn list_contents(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ListContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListContentsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/ListContents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "ListContents"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// full-text search over name and description, best matches first
+        pub async fn search_contents(
+            &mut self,
+            request: impl tonic::IntoRequest<super::SearchContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListContentsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/SearchContents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "SearchContents"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn create_content(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreateContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::Content>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/CreateContent",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "CreateContent"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn update_content(
+            &mut self,
+            request: impl tonic::IntoRequest<super::UpdateContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::Content>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/UpdateContent",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "UpdateContent"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn delete_content(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeleteContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeleteResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/DeleteContent",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "DeleteContent"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn create_publisher(
+            &mut self,
+            request: impl tonic::IntoRequest<super::CreatePublisherRequest>,
+        ) -> std::result::Result<tonic::Response<super::Publisher>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/CreatePublisher",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "CreatePublisher"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// replaces name and avatar of the publisher
+        pub async fn update_publisher(
+            &mut self,
+            request: impl tonic::IntoRequest<super::Publisher>,
+        ) -> std::result::Result<tonic::Response<super::Publisher>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/UpdatePublisher",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "UpdatePublisher"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// contents are kept, only their link to the publisher is dropped
+        pub async fn delete_publisher(
+            &mut self,
+            request: impl tonic::IntoRequest<super::DeletePublisherRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeleteResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/DeletePublisher",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "DeletePublisher"));
+            self.inner.unary(req, path, codec).await
+        }
+        pub async fn import_contents(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ImportContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ImportContentsResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/ImportContents",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "ImportContents"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// count views, likes and dislikes in the content totals and daily buckets
+        pub async fn ingest(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::ContentEvent>,
+        ) -> std::result::Result<tonic::Response<super::IngestResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/metadata.Metadata/Ingest");
+            let mut req = request.into_streaming_request();
+            req.extensions_mut().insert(GrpcMethod::new("metadata.Metadata", "Ingest"));
+            self.inner.client_streaming(req, path, codec).await
+        }
+        /// top contents per type by recent activity
+        pub async fn trending(
+            &mut self,
+            request: impl tonic::IntoRequest<super::TrendingRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TrendingResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/metadata.Metadata/Trending",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("metadata.Metadata", "Trending"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -189,7 +781,7 @@ pub mod metadata_server {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
     use tonic::codegen::*;
     /// Generated trait containing gRPC methods that should be implemented for use with MetadataServer.
@@ -197,13 +789,86 @@ pub mod metadata_server {
     pub trait Metadata: std::marker::Send + std::marker::Sync + 'static {
         /// Server streaming response type for the Materialize method.
         type MaterializeStream: tonic::codegen::tokio_stream::Stream<
-                Item = std::result::Result<super::Content, tonic::Status>,
-            > + std::marker::Send
+                Item = std::result::Result<super::MaterializeResponse, tonic::Status>,
+            >
+            + std::marker::Send
             + 'static;
+        /// a failed id gets an error response, the stream goes on with the others
         async fn materialize(
             &self,
             request: tonic::Request<tonic::Streaming<super::MaterializeRequest>>,
-        ) -> std::result::Result<tonic::Response<Self::MaterializeStream>, tonic::Status>;
+        ) -> std::result::Result<
+            tonic::Response<Self::MaterializeStream>,
+            tonic::Status,
+        >;
+        async fn batch_get(
+            &self,
+            request: tonic::Request<super::BatchGetRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::BatchGetResponse>,
+            tonic::Status,
+        >;
+        async fn list_contents(
+            &self,
+            request: tonic::Request<super::ListContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListContentsResponse>,
+            tonic::Status,
+        >;
+        /// full-text search over name and description, best matches first
+        async fn search_contents(
+            &self,
+            request: tonic::Request<super::SearchContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ListContentsResponse>,
+            tonic::Status,
+        >;
+        async fn create_content(
+            &self,
+            request: tonic::Request<super::CreateContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::Content>, tonic::Status>;
+        async fn update_content(
+            &self,
+            request: tonic::Request<super::UpdateContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::Content>, tonic::Status>;
+        async fn delete_content(
+            &self,
+            request: tonic::Request<super::DeleteContentRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeleteResponse>, tonic::Status>;
+        async fn create_publisher(
+            &self,
+            request: tonic::Request<super::CreatePublisherRequest>,
+        ) -> std::result::Result<tonic::Response<super::Publisher>, tonic::Status>;
+        /// replaces name and avatar of the publisher
+        async fn update_publisher(
+            &self,
+            request: tonic::Request<super::Publisher>,
+        ) -> std::result::Result<tonic::Response<super::Publisher>, tonic::Status>;
+        /// contents are kept, only their link to the publisher is dropped
+        async fn delete_publisher(
+            &self,
+            request: tonic::Request<super::DeletePublisherRequest>,
+        ) -> std::result::Result<tonic::Response<super::DeleteResponse>, tonic::Status>;
+        async fn import_contents(
+            &self,
+            request: tonic::Request<super::ImportContentsRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ImportContentsResponse>,
+            tonic::Status,
+        >;
+        /// count views, likes and dislikes in the content totals and daily buckets
+        async fn ingest(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::ContentEvent>>,
+        ) -> std::result::Result<tonic::Response<super::IngestResponse>, tonic::Status>;
+        /// top contents per type by recent activity
+        async fn trending(
+            &self,
+            request: tonic::Request<super::TrendingRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::TrendingResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct MetadataServer<T> {
@@ -226,7 +891,10 @@ pub mod metadata_server {
                 max_encoding_message_size: None,
             }
         }
-        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
         where
             F: tonic::service::Interceptor,
         {
@@ -281,18 +949,26 @@ pub mod metadata_server {
                 "/metadata.Metadata/Materialize" => {
                     #[allow(non_camel_case_types)]
                     struct MaterializeSvc<T: Metadata>(pub Arc<T>);
-                    impl<T: Metadata> tonic::server::StreamingService<super::MaterializeRequest> for MaterializeSvc<T> {
-                        type Response = super::Content;
+                    impl<
+                        T: Metadata,
+                    > tonic::server::StreamingService<super::MaterializeRequest>
+                    for MaterializeSvc<T> {
+                        type Response = super::MaterializeResponse;
                         type ResponseStream = T::MaterializeStream;
-                        type Future =
-                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
                         fn call(
                             &mut self,
-                            request: tonic::Request<tonic::Streaming<super::MaterializeRequest>>,
+                            request: tonic::Request<
+                                tonic::Streaming<super::MaterializeRequest>,
+                            >,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut =
-                                async move { <T as Metadata>::materialize(&inner, request).await };
+                            let fut = async move {
+                                <T as Metadata>::materialize(&inner, request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -318,19 +994,559 @@ pub mod metadata_server {
                     };
                     Box::pin(fut)
                 }
-                _ => Box::pin(async move {
-                    let mut response = http::Response::new(empty_body());
-                    let headers = response.headers_mut();
-                    headers.insert(
-                        tonic::Status::GRPC_STATUS,
-                        (tonic::Code::Unimplemented as i32).into(),
-                    );
-                    headers.insert(
-                        http::header::CONTENT_TYPE,
-                        tonic::metadata::GRPC_CONTENT_TYPE,
-                    );
-                    Ok(response)
-                }),
+                "/metadata.Metadata/BatchGet" => {
+                    #[allow(non_camel_case_types)]
+                    struct BatchGetSvc<T: Metadata>(pub Arc<T>);
+                    impl<T: Metadata> tonic::server::UnaryService<super::BatchGetRequest>
+                    for BatchGetSvc<T> {
+                        type Response = super::BatchGetResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::BatchGetRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::batch_get(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = BatchGetSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/ListContents" => {
+                    #[allow(non_camel_case_types)]
+                    struct ListContentsSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::ListContentsRequest>
+                    for ListContentsSvc<T> {
+                        type Response = super::ListContentsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ListContentsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::list_contents(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ListContentsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/SearchContents" => {
+                    #[allow(non_camel_case_types)]
+                    struct SearchContentsSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::SearchContentsRequest>
+                    for SearchContentsSvc<T> {
+                        type Response = super::ListContentsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::SearchContentsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::search_contents(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = SearchContentsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/CreateContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreateContentSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::CreateContentRequest>
+                    for CreateContentSvc<T> {
+                        type Response = super::Content;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreateContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::create_content(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreateContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/UpdateContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdateContentSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::UpdateContentRequest>
+                    for UpdateContentSvc<T> {
+                        type Response = super::Content;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::UpdateContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::update_content(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UpdateContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/DeleteContent" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeleteContentSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::DeleteContentRequest>
+                    for DeleteContentSvc<T> {
+                        type Response = super::DeleteResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeleteContentRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::delete_content(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeleteContentSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/CreatePublisher" => {
+                    #[allow(non_camel_case_types)]
+                    struct CreatePublisherSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::CreatePublisherRequest>
+                    for CreatePublisherSvc<T> {
+                        type Response = super::Publisher;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::CreatePublisherRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::create_publisher(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = CreatePublisherSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/UpdatePublisher" => {
+                    #[allow(non_camel_case_types)]
+                    struct UpdatePublisherSvc<T: Metadata>(pub Arc<T>);
+                    impl<T: Metadata> tonic::server::UnaryService<super::Publisher>
+                    for UpdatePublisherSvc<T> {
+                        type Response = super::Publisher;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::Publisher>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::update_publisher(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = UpdatePublisherSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/DeletePublisher" => {
+                    #[allow(non_camel_case_types)]
+                    struct DeletePublisherSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::DeletePublisherRequest>
+                    for DeletePublisherSvc<T> {
+                        type Response = super::DeleteResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::DeletePublisherRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::delete_publisher(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = DeletePublisherSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/ImportContents" => {
+                    #[allow(non_camel_case_types)]
+                    struct ImportContentsSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::UnaryService<super::ImportContentsRequest>
+                    for ImportContentsSvc<T> {
+                        type Response = super::ImportContentsResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ImportContentsRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::import_contents(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ImportContentsSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/Ingest" => {
+                    #[allow(non_camel_case_types)]
+                    struct IngestSvc<T: Metadata>(pub Arc<T>);
+                    impl<
+                        T: Metadata,
+                    > tonic::server::ClientStreamingService<super::ContentEvent>
+                    for IngestSvc<T> {
+                        type Response = super::IngestResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::ContentEvent>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::ingest(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = IngestSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/metadata.Metadata/Trending" => {
+                    #[allow(non_camel_case_types)]
+                    struct TrendingSvc<T: Metadata>(pub Arc<T>);
+                    impl<T: Metadata> tonic::server::UnaryService<super::TrendingRequest>
+                    for TrendingSvc<T> {
+                        type Response = super::TrendingResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::TrendingRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Metadata>::trending(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = TrendingSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
             }
         }
     }
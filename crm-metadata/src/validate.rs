@@ -0,0 +1,217 @@
+use crm_core::{Validate, Violations};
+
+use crate::{
+    abi::MAX_BATCH_SIZE,
+    pb::metadata::{
+        BatchGetRequest, ContentEvent, ContentEventType, ContentOrder, ContentType,
+        CreateContentRequest, CreatePublisherRequest, DeleteContentRequest, DeletePublisherRequest,
+        ImportContentsRequest, ImportFormat, ListContentsRequest, Publisher, SearchContentsRequest,
+        TrendingRequest, UpdateContentRequest,
+    },
+};
+
+impl Validate for BatchGetRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.max_items("ids", &self.ids, MAX_BATCH_SIZE);
+    }
+}
+
+impl Validate for ListContentsRequest {
+    fn validate(&self, v: &mut Violations) {
+        content_types(v, &self.content_types);
+        for (i, id) in self.publisher_ids.iter().enumerate() {
+            v.db_id(&format!("publisher_ids[{}]", i), *id);
+        }
+        v.timestamp("created_after", self.created_after.as_ref());
+        v.timestamp("created_before", self.created_before.as_ref());
+        v.known::<ContentOrder>("order_by", self.order_by);
+        page_token(v, &self.page_token);
+    }
+}
+
+impl Validate for SearchContentsRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("query", &self.query);
+        page_token(v, &self.page_token);
+    }
+}
+
+impl Validate for CreateContentRequest {
+    fn validate(&self, v: &mut Violations) {
+        content(
+            v,
+            &self.name,
+            &self.url,
+            &self.image,
+            self.content_type,
+            &self.publisher_ids,
+        );
+    }
+}
+
+impl Validate for UpdateContentRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.positive("id", self.id);
+        content(
+            v,
+            &self.name,
+            &self.url,
+            &self.image,
+            self.content_type,
+            &self.publisher_ids,
+        );
+    }
+}
+
+impl Validate for DeleteContentRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.positive("id", self.id);
+    }
+}
+
+impl Validate for CreatePublisherRequest {
+    fn validate(&self, v: &mut Violations) {
+        publisher(v, &self.name, &self.avatar);
+    }
+}
+
+impl Validate for Publisher {
+    fn validate(&self, v: &mut Violations) {
+        v.positive("id", self.id);
+        publisher(v, &self.name, &self.avatar);
+    }
+}
+
+impl Validate for DeletePublisherRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.positive("id", self.id);
+    }
+}
+
+impl Validate for ImportContentsRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.specified::<ImportFormat>("format", self.format);
+        v.not_empty("data", &self.data);
+    }
+}
+
+impl Validate for ContentEvent {
+    fn validate(&self, v: &mut Violations) {
+        v.positive("content_id", self.content_id);
+        v.db_id("content_id", self.content_id);
+        v.specified::<ContentEventType>("event_type", self.event_type);
+        v.timestamp("timestamp", self.timestamp.as_ref());
+    }
+}
+
+impl Validate for TrendingRequest {
+    fn validate(&self, v: &mut Violations) {
+        content_types(v, &self.content_types);
+    }
+}
+
+/// Editable fields of a content, shared by create and update
+fn content(
+    v: &mut Violations,
+    name: &str,
+    url: &str,
+    image: &str,
+    content_type: i32,
+    publisher_ids: &[u32],
+) {
+    v.required("name", name);
+    v.url("url", url);
+    v.url("image", image);
+    v.specified::<ContentType>("content_type", content_type);
+    v.not_empty("publisher_ids", publisher_ids);
+    for (i, id) in publisher_ids.iter().enumerate() {
+        v.db_id(&format!("publisher_ids[{}]", i), *id);
+    }
+}
+
+fn publisher(v: &mut Violations, name: &str, avatar: &str) {
+    v.required("name", name);
+    if !avatar.is_empty() {
+        v.url("avatar", avatar);
+    }
+}
+
+/// Filters on types, unspecified included as it matches nothing
+fn content_types(v: &mut Violations, types: &[i32]) {
+    for (i, content_type) in types.iter().enumerate() {
+        v.known::<ContentType>(&format!("content_types[{}]", i), *content_type);
+    }
+}
+
+/// The `next_page_token` of a previous response, i.e. an offset
+fn page_token(v: &mut Violations, token: &str) {
+    v.check(
+        "page_token",
+        token.is_empty() || token.parse::<u32>().is_ok(),
+        "is not a page token",
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_core::ErrorDetails;
+
+    use super::*;
+
+    fn fields<T: Validate>(message: T) -> Vec<String> {
+        let status = message.validated("crm-metadata").err().unwrap();
+        let details = ErrorDetails::from_status(&status);
+        details.fields().into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn content_requests_should_be_validated() {
+        let req = CreateContentRequest {
+            name: "Learning Rust".to_string(),
+            description: "".to_string(),
+            url: "https://example.com/rust".to_string(),
+            image: "https://example.com/rust.png".to_string(),
+            content_type: ContentType::Vlog as i32,
+            publisher_ids: vec![1],
+        };
+        assert!(req.is_valid());
+        let req = CreateContentRequest {
+            url: "ftp://example.com/rust".to_string(),
+            content_type: 0,
+            publisher_ids: vec![1, u32::MAX],
+            ..req
+        };
+        assert_eq!(fields(req), vec!["url", "content_type", "publisher_ids[1]"]);
+        let req = UpdateContentRequest::default();
+        assert_eq!(
+            fields(req),
+            vec![
+                "id",
+                "name",
+                "url",
+                "image",
+                "content_type",
+                "publisher_ids"
+            ]
+        );
+    }
+
+    #[test]
+    fn list_requests_should_be_validated() {
+        let req = ListContentsRequest {
+            content_types: vec![ContentType::Short as i32, 9],
+            order_by: 5,
+            page_token: "abc".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            fields(req),
+            vec!["content_types[1]", "order_by", "page_token"]
+        );
+        let req = BatchGetRequest {
+            ids: vec![1; MAX_BATCH_SIZE + 1],
+        };
+        assert_eq!(fields(req), vec!["ids"]);
+        assert_eq!(fields(SearchContentsRequest::default()), vec!["query"]);
+    }
+}
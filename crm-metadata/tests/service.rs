@@ -1,20 +1,41 @@
 use std::{net::SocketAddr, time::Duration};
 
 use anyhow::Result;
+use crm_core::{
+    metrics,
+    pb::grpc::{
+        health::v1::{
+            health_check_response::ServingStatus, health_client::HealthClient, HealthCheckRequest,
+        },
+        reflection::v1::{
+            server_reflection_client::ServerReflectionClient,
+            server_reflection_request::MessageRequest, server_reflection_response::MessageResponse,
+            ServerReflectionRequest,
+        },
+    },
+    Config, ReflectionService,
+};
 use crm_metadata::{
     config::AppConfig,
-    pb::metadata::{metadata_client::MetadataClient, MaterializeRequest},
+    pb::{
+        metadata::{metadata_client::MetadataClient, MaterializeRequest},
+        FILE_DESCRIPTOR_SET,
+    },
     MetadataService,
 };
+use sqlx_db_tester::TestPg;
 use tokio::time;
 use tokio_stream::StreamExt;
 use tracing::info;
 
 #[tokio::test]
 async fn main() -> anyhow::Result<()> {
-    let addr = start_server().await?;
+    let (_tdb, addr) = start_server().await?;
     info!("Starting server at {}", addr);
-    let requests = vec![MaterializeRequest { id: 1 }];
+    let requests = vec![
+        MaterializeRequest { id: 1 },
+        MaterializeRequest { id: 10000 },
+    ];
     let stream = tokio_stream::iter(requests);
     let mut client = MetadataClient::connect(format!("http://{}", addr)).await?;
     let res = client.materialize(stream).await?;
@@ -27,15 +48,57 @@ async fn main() -> anyhow::Result<()> {
         .await;
 
     println!("{:?}", contents);
+    // the missing id doesn't end the stream
+    assert_eq!(contents.len(), 2);
+
+    // the db probe passed, and the service is discoverable through reflection
+    let req = HealthCheckRequest {
+        service: "metadata.Metadata".to_string(),
+    };
+    let res = HealthClient::connect(format!("http://{}", addr))
+        .await?
+        .check(req)
+        .await?;
+    assert_eq!(res.into_inner().status(), ServingStatus::Serving);
+
+    let req = ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(MessageRequest::ListServices(String::new())),
+    };
+    let mut responses = ServerReflectionClient::connect(format!("http://{}", addr))
+        .await?
+        .server_reflection_info(tokio_stream::once(req))
+        .await?
+        .into_inner();
+    let res = responses.next().await.unwrap()?;
+    let Some(MessageResponse::ListServicesResponse(res)) = res.message_response else {
+        panic!("unexpected response {:?}", res);
+    };
+    assert!(res.service.iter().any(|s| s.name == "metadata.Metadata"));
+
+    let metrics = metrics::render();
+    assert!(metrics.contains(
+        r#"grpc_server_handled_total{grpc_service="metadata.Metadata",grpc_method="Materialize",grpc_code="Ok"} 1"#
+    ));
+    assert!(metrics.contains(r#"metadata_cache_requests_total{result="miss"} 2"#));
     Ok(())
 }
 
-async fn start_server() -> Result<SocketAddr> {
+async fn start_server() -> Result<(TestPg, SocketAddr)> {
     let config = AppConfig::load().unwrap();
     let addr = format!("[::1]:{}", config.server.port).parse().unwrap();
-    let svc = MetadataService::new(config).into_server();
+    let (tdb, svc) = MetadataService::new_for_test().await;
+    let health = svc.health().into_server();
+    let reflection = ReflectionService::builder()
+        .register(FILE_DESCRIPTOR_SET)
+        .build()?
+        .into_server();
+    let svc = svc.into_server();
     tokio::spawn(async move {
         tonic::transport::Server::builder()
+            .layer(metrics::layer())
+            .add_service(health)
+            .add_service(reflection)
             .add_service(svc)
             .serve(addr)
             .await
@@ -43,5 +106,5 @@ async fn start_server() -> Result<SocketAddr> {
     });
 
     time::sleep(Duration::from_secs(1)).await;
-    Ok(addr)
+    Ok((tdb, addr))
 }
@@ -1,10 +1,12 @@
-use std::fs;
+use std::{env, fs, path::PathBuf};
 fn main() -> anyhow::Result<()> {
     fs::create_dir_all("src/pb")?;
+    let descriptor = PathBuf::from(env::var("OUT_DIR")?).join("crm_send_descriptor.bin");
     let builder = tonic_build::configure();
 
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(&descriptor)
         .compile_protos(
             &["../protos/send/messages.proto", "../protos/send/rpc.proto"],
             &["../protos"],
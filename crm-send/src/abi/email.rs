@@ -1,23 +1,23 @@
-use super::{to_timestamp, Sender};
+use super::Sender;
 use crate::{
     pb::send::{send_request::Msg, EmailMessage, SendRequest, SendResponse},
-    NotificationService,
+    Error, NotificationService,
 };
+use crm_core::convert;
 use fake::{faker::internet::zh_cn::SafeEmail, Fake};
-use tonic::Status;
-use tracing::warn;
+use tracing::Span;
 use uuid::Uuid;
 
 impl Sender for EmailMessage {
-    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Status> {
+    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Error> {
         let response = SendResponse {
             message_id: id,
-            timestamp: Some(to_timestamp()),
+            timestamp: Some(convert::now()),
         };
-        svc.sender.send(Msg::Email(self)).await.map_err(|e| {
-            warn!("failed to send email: {}", e);
-            Status::internal("failed to send email")
-        })?;
+        svc.sender
+            .send((Msg::Email(self), Span::current()))
+            .await
+            .map_err(|_| Error::ProviderUnavailable("email"))?;
         Ok(response)
     }
 }
@@ -38,6 +38,7 @@ impl From<EmailMessage> for SendRequest {
         Self {
             message_id: Uuid::new_v4().to_string(),
             msg: Some(Msg::Email(msg)),
+            user: String::new(),
         }
     }
 }
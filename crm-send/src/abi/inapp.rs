@@ -1,24 +1,24 @@
+use crm_core::convert;
 use fake::{faker::lorem::en::Sentence, Fake};
-use tonic::Status;
-use tracing::warn;
+use tracing::Span;
 use uuid::Uuid;
 
-use super::{to_timestamp, Sender};
+use super::Sender;
 use crate::{
     pb::send::{send_request::Msg, InAppMessage, SendRequest, SendResponse},
-    NotificationService,
+    Error, NotificationService,
 };
 
 impl Sender for InAppMessage {
-    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Status> {
+    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Error> {
         let response = SendResponse {
             message_id: id,
-            timestamp: Some(to_timestamp()),
+            timestamp: Some(convert::now()),
         };
-        svc.sender.send(Msg::InApp(self)).await.map_err(|e| {
-            warn!("failed to send in-app message: {}", e);
-            Status::internal("failed to send in-app message")
-        })?;
+        svc.sender
+            .send((Msg::InApp(self), Span::current()))
+            .await
+            .map_err(|_| Error::ProviderUnavailable("in-app message"))?;
         Ok(response)
     }
 }
@@ -38,6 +38,7 @@ impl From<InAppMessage> for SendRequest {
         Self {
             message_id: Uuid::new_v4().to_string(),
             msg: Some(Msg::InApp(msg)),
+            user: String::new(),
         }
     }
 }
@@ -2,23 +2,56 @@ mod email;
 mod inapp;
 mod sms;
 
-use crate::pb::send::{send_request::Msg, SendResponse};
+use crate::pb::send::{
+    send_request::Msg, Channel, ForgetRecipientResponse, RecipientExport, RecipientRequest,
+    SendResponse,
+};
 use crate::{
     config::AppConfig,
     dummy_send,
-    pb::send::{notification_server::NotificationServer, SendRequest},
-    NotificationService, NotificationServiceInner, ResponseStream, ServiceResult,
+    pb::send::{
+        notification_server::{self, NotificationServer},
+        SendRequest,
+    },
+    Error, NotificationService, NotificationServiceInner, ResponseStream, ServiceResult,
+};
+use crm_core::{
+    config::{SharedConfig, WATCH_INTERVAL},
+    convert,
+    metrics::{self, Counter},
+    HealthService, ServiceError, Validate,
 };
-use chrono::Utc;
-use prost_types::Timestamp;
-use std::{ops::Deref, sync::Arc};
+use std::{
+    future::Future,
+    ops::Deref,
+    sync::{Arc, LazyLock, Mutex},
+};
+use tokio::task::JoinHandle;
 use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
 use tonic::{Response, Status};
+use tracing::{info_span, warn, Instrument};
+
+static SENT: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "send_messages_total",
+        "Messages handed to the providers, by channel",
+        &["channel", "result"],
+    )
+});
 
 impl NotificationService {
     pub async fn new(config: AppConfig) -> Self {
-        let sender = dummy_send().await;
-        let inner = NotificationServiceInner { config, sender };
+        Self::with_config(Arc::new(SharedConfig::new(config))).await
+    }
+
+    async fn with_config(config: Arc<SharedConfig<AppConfig>>) -> Self {
+        let (sender, worker) = dummy_send().await;
+        let inner = NotificationServiceInner {
+            config,
+            sender,
+            worker: Mutex::new(Some(worker)),
+            deliveries: Default::default(),
+        };
         Self {
             inner: Arc::new(inner),
         }
@@ -28,41 +61,128 @@ impl NotificationService {
         NotificationServer::new(self)
     }
 
+    /// Snapshot of the current config, later reloads leave it untouched
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.config.get()
+    }
+
+    /// Pick up changes to the config file
+    pub fn watch_config(&self) -> JoinHandle<()> {
+        self.config.watch(WATCH_INTERVAL)
+    }
+
+    /// The providers are dummies for now, nothing to probe
+    pub fn health(&self) -> HealthService {
+        HealthService::builder()
+            .service(notification_server::SERVICE_NAME)
+            .build()
+    }
+
+    /// Completes once the in-flight `Send` streams ended and the provider
+    /// delivered every queued message. It only can once all the clones of
+    /// the service are dropped, i.e. after the server stopped.
+    pub fn drained(&self) -> impl Future<Output = ()> {
+        let worker = self.worker.lock().unwrap().take();
+        async move {
+            if let Some(worker) = worker {
+                if let Err(e) = worker.await {
+                    warn!("provider worker failed: {}", e);
+                }
+            }
+        }
+    }
+
     pub async fn send(
         &self,
         mut stream: impl Stream<Item = Result<SendRequest, Status>> + Send + 'static + Unpin,
     ) -> ServiceResult<ResponseStream> {
         let (tx, rx) = tokio::sync::mpsc::channel(1024);
         let notif_clone = self.clone();
-        tokio::spawn(async move {
-            while let Some(Ok(req)) = stream.next().await {
-                // Clone the sender to be moved into the async block
-                // 不能直接clone，不然self会被move进来然后报错
-                let notif_clone = notif_clone.clone();
-                let response = match req.msg {
-                    Some(Msg::Email(e)) => e.send(req.message_id, notif_clone).await,
-                    Some(Msg::Sms(e)) => e.send(req.message_id, notif_clone).await,
-                    Some(Msg::InApp(e)) => e.send(req.message_id, notif_clone).await,
-                    None => Err(Status::invalid_argument("missing message")),
-                };
-                tx.send(response).await.unwrap();
+        tokio::spawn(
+            async move {
+                while let Some(Ok(req)) = stream.next().await {
+                    let channel = channel_label(req.msg.as_ref());
+                    // an invalid message fails on its own, the rest of the stream goes on
+                    let req = match req.validated(Error::DOMAIN) {
+                        Ok(req) => req,
+                        Err(status) => {
+                            SENT.inc(&[channel, "error"]);
+                            if tx.send(Err(status)).await.is_err() {
+                                break;
+                            }
+                            continue;
+                        }
+                    };
+                    // Clone the sender to be moved into the async block
+                    // 不能直接clone，不然self会被move进来然后报错
+                    let notif_clone = notif_clone.clone();
+                    let records = req
+                        .msg
+                        .as_ref()
+                        .map(|msg| msg.delivery_records(&req.message_id, &req.user, convert::now()))
+                        .unwrap_or_default();
+                    let deliveries = notif_clone.deliveries.clone();
+                    let span = info_span!(
+                        "send",
+                        otel.kind = "producer",
+                        channel,
+                        message_id = req.message_id.as_str(),
+                    );
+                    let response = async move {
+                        match req.msg {
+                            Some(Msg::Email(e)) => e.send(req.message_id, notif_clone).await,
+                            Some(Msg::Sms(e)) => e.send(req.message_id, notif_clone).await,
+                            Some(Msg::InApp(e)) => e.send(req.message_id, notif_clone).await,
+                            None => Err(Error::invalid("msg", "missing message")),
+                        }
+                    }
+                    .instrument(span)
+                    .await
+                    .map_err(Status::from);
+                    let result = if response.is_ok() { "ok" } else { "error" };
+                    SENT.inc(&[channel, result]);
+                    if response.is_ok() {
+                        deliveries.record(records);
+                    }
+                    if tx.send(response).await.is_err() {
+                        // the client went away, stop sending
+                        break;
+                    }
+                }
             }
-        });
+            .in_current_span(),
+        );
         Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
     }
+
+    pub async fn export_recipient(&self, req: RecipientRequest) -> ServiceResult<RecipientExport> {
+        let records = self.deliveries.export(&req.recipient);
+        Ok(Response::new(RecipientExport { records }))
+    }
+
+    pub async fn forget_recipient(
+        &self,
+        req: RecipientRequest,
+    ) -> ServiceResult<ForgetRecipientResponse> {
+        let records = self.deliveries.forget(&req.recipient) as u64;
+        Ok(Response::new(ForgetRecipientResponse { records }))
+    }
 }
 
-fn to_timestamp() -> Timestamp {
-    let now = Utc::now();
-    Timestamp {
-        seconds: now.timestamp(),
-        nanos: now.timestamp_subsec_nanos() as i32,
+crm_core::grpc_server!(NotificationServer);
+
+fn channel_label(msg: Option<&Msg>) -> &'static str {
+    match msg.map(Msg::channel) {
+        Some(Channel::Email) => "email",
+        Some(Channel::Sms) => "sms",
+        Some(Channel::InApp) => "in_app",
+        Some(Channel::Unspecified) | None => "unspecified",
     }
 }
 
 #[allow(async_fn_in_trait)]
 pub trait Sender {
-    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Status>;
+    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Error>;
 }
 
 impl Deref for NotificationService {
@@ -75,12 +195,40 @@ impl Deref for NotificationService {
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::HashMap, fs, time::Duration};
+
+    use crm_core::Config;
+    use tokio::time::{timeout, Instant};
+
     use super::*;
     use crate::{
         config::AppConfig,
         pb::send::{EmailMessage, InAppMessage, SmsMessage},
     };
 
+    #[tokio::test]
+    async fn invalid_config_file_should_keep_the_current_config() {
+        let path = std::env::temp_dir().join(format!("crm-send-{}.yml", std::process::id()));
+        let yaml = include_str!("../../send.yml");
+        let env = HashMap::from([("SEND_CONFIG".to_string(), path.display().to_string())]);
+        let config = SharedConfig::with_sources(AppConfig::load().unwrap(), env, vec![]);
+        let svc = NotificationService::with_config(Arc::new(config)).await;
+
+        fs::write(
+            &path,
+            yaml.replace("metrics_port: 9103", "metrics_port: 9104"),
+        )
+        .unwrap();
+        svc.config.reload().unwrap();
+        assert_eq!(svc.config().server.metrics_port, 9104);
+
+        fs::write(&path, yaml.replace("port: 50003", "port: 0")).unwrap();
+        assert!(svc.config.reload().is_err());
+        assert_eq!(svc.config().server.port, 50003);
+        assert_eq!(svc.config().server.metrics_port, 9104);
+        fs::remove_file(&path).unwrap();
+    }
+
     #[tokio::test]
     async fn test_send_should_work() {
         let config = AppConfig::load().unwrap();
@@ -95,4 +243,75 @@ mod tests {
         let email_response = stream.next().await.unwrap().unwrap();
         println!("Email response: {:?}", email_response);
     }
+
+    #[tokio::test]
+    async fn send_without_message_should_be_rejected() {
+        let config = AppConfig::load().unwrap();
+        let svc = NotificationService::new(config).await;
+        let request = SendRequest {
+            message_id: "1".to_string(),
+            msg: None,
+            user: String::new(),
+        };
+        let stream = tokio_stream::iter(vec![Ok(request)]);
+        let response = svc.send(stream).await.unwrap();
+        let err = response.into_inner().next().await.unwrap().unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let details = crm_core::ErrorDetails::from_status(&err);
+        assert_eq!(details.fields(), vec!["msg"]);
+    }
+
+    #[tokio::test]
+    async fn export_and_forget_recipient_should_work() {
+        let config = AppConfig::load().unwrap();
+        let svc = NotificationService::new(config).await;
+        let msg = EmailMessage::fake();
+        let recipient = RecipientRequest {
+            recipient: msg.to[0].clone(),
+        };
+        let request: SendRequest = msg.into();
+        let message_id = request.message_id.clone();
+        let stream = tokio_stream::iter(vec![Ok(request)]);
+        let response = svc.send(stream).await.unwrap();
+        let _ = response.into_inner().collect::<Vec<_>>().await;
+
+        let export = svc
+            .export_recipient(recipient.clone())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(export.records.len(), 1);
+        assert_eq!(export.records[0].message_id, message_id);
+
+        let res = svc
+            .forget_recipient(recipient.clone())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.records, 1);
+        let export = svc.export_recipient(recipient).await.unwrap().into_inner();
+        assert!(export.records.is_empty());
+    }
+
+    #[tokio::test]
+    async fn drained_should_wait_for_queued_messages() {
+        let config = AppConfig::load().unwrap();
+        let svc = NotificationService::new(config).await;
+        let drained = svc.drained();
+        let stream = tokio_stream::iter(vec![
+            Ok(EmailMessage::fake().into()),
+            Ok(SmsMessage::fake().into()),
+            Ok(InAppMessage::fake().into()),
+        ]);
+        let response = svc.send(stream).await.unwrap();
+        let responses = response.into_inner().collect::<Vec<_>>().await;
+        assert_eq!(responses.len(), 3);
+        assert!(SENT.get(&["sms", "ok"]) >= 1);
+        drop(svc);
+
+        // the responses only mean queued, the provider takes 300ms per message
+        let start = Instant::now();
+        timeout(Duration::from_secs(5), drained).await.unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(500));
+    }
 }
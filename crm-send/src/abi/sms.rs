@@ -1,26 +1,26 @@
-use super::{to_timestamp, Sender};
+use super::Sender;
 use crate::{
     pb::send::{send_request::Msg, SendRequest, SendResponse, SmsMessage},
-    NotificationService,
+    Error, NotificationService,
 };
+use crm_core::convert;
 use fake::{
     faker::{internet::zh_cn::SafeEmail, name::en::Name},
     Fake,
 };
-use tonic::Status;
-use tracing::warn;
+use tracing::Span;
 use uuid::Uuid;
 
 impl Sender for SmsMessage {
-    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Status> {
+    async fn send(self, id: String, svc: NotificationService) -> Result<SendResponse, Error> {
         let response = SendResponse {
             message_id: id,
-            timestamp: Some(to_timestamp()),
+            timestamp: Some(convert::now()),
         };
-        svc.sender.send(Msg::Sms(self)).await.map_err(|e| {
-            warn!("failed to send sms: {}", e);
-            Status::internal("failed to send sms")
-        })?;
+        svc.sender
+            .send((Msg::Sms(self), Span::current()))
+            .await
+            .map_err(|_| Error::ProviderUnavailable("sms"))?;
         Ok(response)
     }
 }
@@ -30,7 +30,7 @@ impl SmsMessage {
     pub fn fake() -> Self {
         Self {
             sender: SafeEmail().fake(),
-            recipients: vec![SafeEmail().fake()],
+            recipients: vec![format!("+86138{:08}", (0..100_000_000).fake::<u32>())],
             body: format!("Sms Message: hello {}", Name().fake::<String>()),
         }
     }
@@ -41,6 +41,7 @@ impl From<SmsMessage> for SendRequest {
         Self {
             message_id: Uuid::new_v4().to_string(),
             msg: Some(Msg::Sms(msg)),
+            user: String::new(),
         }
     }
 }
@@ -1,40 +1,35 @@
-use anyhow::{bail, Result};
+use crm_core::{AuthConfig, Checks, Config, ServerConfig, TelemetryConfig};
 use serde::{Deserialize, Serialize};
-use std::fs;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub auth: AuthConfig,
+    /// span export, traces stay local when unset
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ServerConfig {
-    // pub host: String,
-    pub port: u16,
-}
-#[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AuthConfig {
-    pub pk: String,
-}
-
-impl AppConfig {
-    pub fn load() -> Result<Self> {
-        let ret: Result<AppConfig, _> = match (
-            fs::File::open("send.yml"),
-            fs::File::open("/etc/config/send.yml"),
-            std::env::var("SEND_CONFIG"),
-        ) {
-            (Ok(reader), _, _) => serde_yaml::from_reader(reader),
-            (Err(_), Ok(reader), _) => serde_yaml::from_reader(reader),
-            (Err(_), Err(_), Ok(reader)) => serde_yaml::from_reader(fs::File::open(reader)?),
-            _ => bail!("Config file not found"),
-        };
+impl Config for AppConfig {
+    const NAME: &'static str = "send";
+    const ENV_PREFIX: &'static str = "SEND";
+    const DEFAULTS: &'static str = r#"
+server:
+    port: 50003
+    metrics_port: 9103
+    shutdown_timeout_secs: 30
+"#;
+    const SECRETS: &'static [&'static str] = &["server.tls.key"];
 
-        Ok(ret?)
+    fn validate(&self, checks: &mut Checks) {
+        self.server.validate("server", checks);
+        self.auth.validate("auth", checks);
+        self.telemetry.validate(checks);
     }
 }
 
+crm_core::service_config!(AppConfig);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,225 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use prost_types::Timestamp;
+
+use crate::pb::send::{send_request::Msg, Channel, DeliveryRecord};
+
+/// max number of records kept, the oldest are dropped first
+const MAX_RECORDS: usize = 100_000;
+
+/// In-memory log of what was delivered to whom, so it can be exported or purged on request.
+/// The records are looked up by the user of their message and by their recipient,
+/// and lost on restart.
+#[derive(Debug, Clone)]
+pub struct DeliveryLog {
+    records: Arc<Mutex<Records>>,
+}
+
+/// The records in the order they were logged, indexed by user and recipient
+#[derive(Debug)]
+struct Records {
+    capacity: usize,
+    next: u64,
+    records: BTreeMap<u64, DeliveryRecord>,
+    index: HashMap<String, BTreeSet<u64>>,
+}
+
+impl Default for DeliveryLog {
+    fn default() -> Self {
+        Self::with_capacity(MAX_RECORDS)
+    }
+}
+
+impl DeliveryLog {
+    /// A log keeping at most `capacity` records
+    pub fn with_capacity(capacity: usize) -> Self {
+        let records = Records {
+            capacity,
+            next: 0,
+            records: BTreeMap::new(),
+            index: HashMap::new(),
+        };
+        Self {
+            records: Arc::new(Mutex::new(records)),
+        }
+    }
+
+    pub fn record(&self, records: Vec<DeliveryRecord>) {
+        let mut log = self.records.lock().unwrap();
+        for record in records {
+            log.push(record);
+        }
+    }
+
+    /// Records of the user or recipient `id`, oldest first
+    pub fn export(&self, id: &str) -> Vec<DeliveryRecord> {
+        let log = self.records.lock().unwrap();
+        let Some(seqs) = log.index.get(id) else {
+            return vec![];
+        };
+        let mut records = seqs
+            .iter()
+            .filter_map(|seq| log.records.get(seq))
+            .cloned()
+            .collect::<Vec<_>>();
+        records.sort_by_key(|record| record.sent_at.map(|t| (t.seconds, t.nanos)));
+        records
+    }
+
+    /// Drop every record of the user or recipient `id`, returns how many were dropped
+    pub fn forget(&self, id: &str) -> usize {
+        let mut log = self.records.lock().unwrap();
+        let Some(seqs) = log.index.remove(id) else {
+            return 0;
+        };
+        for seq in &seqs {
+            log.remove(*seq);
+        }
+        seqs.len()
+    }
+}
+
+impl Records {
+    fn push(&mut self, record: DeliveryRecord) {
+        if self.records.len() >= self.capacity {
+            if let Some((&oldest, _)) = self.records.first_key_value() {
+                self.remove(oldest);
+            }
+        }
+        let seq = self.next;
+        self.next += 1;
+        for key in record.keys() {
+            self.index.entry(key.to_string()).or_default().insert(seq);
+        }
+        self.records.insert(seq, record);
+    }
+
+    /// Drop the record `seq` and its index entries
+    fn remove(&mut self, seq: u64) {
+        let Some(record) = self.records.remove(&seq) else {
+            return;
+        };
+        for key in record.keys() {
+            if let Some(seqs) = self.index.get_mut(key) {
+                seqs.remove(&seq);
+                if seqs.is_empty() {
+                    self.index.remove(key);
+                }
+            }
+        }
+    }
+}
+
+impl DeliveryRecord {
+    /// What the record is looked up by: the user of its message, if any, and its recipient
+    fn keys(&self) -> impl Iterator<Item = &str> {
+        [self.user.as_str(), self.recipient.as_str()]
+            .into_iter()
+            .filter(|key| !key.is_empty())
+    }
+}
+
+impl Msg {
+    pub fn channel(&self) -> Channel {
+        match self {
+            Msg::Email(_) => Channel::Email,
+            Msg::Sms(_) => Channel::Sms,
+            Msg::InApp(_) => Channel::InApp,
+        }
+    }
+
+    pub fn recipients(&self) -> Vec<&str> {
+        match self {
+            Msg::Email(e) => e.to.iter().map(String::as_str).collect(),
+            Msg::Sms(s) => s.recipients.iter().map(String::as_str).collect(),
+            Msg::InApp(i) => vec![i.device_id.as_str()],
+        }
+    }
+
+    /// One record per recipient of the message sent to `user`, if any
+    pub fn delivery_records(
+        &self,
+        message_id: &str,
+        user: &str,
+        sent_at: Timestamp,
+    ) -> Vec<DeliveryRecord> {
+        self.recipients()
+            .into_iter()
+            .map(|recipient| DeliveryRecord {
+                message_id: message_id.to_string(),
+                recipient: recipient.to_string(),
+                channel: self.channel() as i32,
+                sent_at: Some(sent_at),
+                user: user.to_string(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::send::{EmailMessage, InAppMessage, SmsMessage};
+
+    #[test]
+    fn delivery_log_should_export_and_forget() {
+        let log = DeliveryLog::with_capacity(4);
+        let msg = Msg::Email(EmailMessage {
+            subject: "Hello".to_string(),
+            from: "crm@example.com".to_string(),
+            to: vec!["a@example.com".to_string(), "b@example.com".to_string()],
+            body: "hello world".to_string(),
+        });
+        // the records of the first message are dropped to keep 4 of them
+        for i in 0..3 {
+            log.record(msg.delivery_records(&i.to_string(), "", Timestamp::default()));
+        }
+
+        let records = log.export("a@example.com");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].message_id, "1");
+        assert_eq!(records[0].channel, Channel::Email as i32);
+
+        assert_eq!(log.forget("a@example.com"), 2);
+        assert!(log.export("a@example.com").is_empty());
+        assert_eq!(log.forget("a@example.com"), 0);
+        assert_eq!(log.export("b@example.com").len(), 2);
+        assert_eq!(log.records.lock().unwrap().records.len(), 2);
+    }
+
+    #[test]
+    fn delivery_log_should_file_every_channel_under_the_user() {
+        let log = DeliveryLog::default();
+        let user = "alice@example.com";
+        let sms = Msg::Sms(SmsMessage {
+            sender: "crm".to_string(),
+            recipients: vec!["+8613800138000".to_string()],
+            body: "hello".to_string(),
+        });
+        let in_app = Msg::InApp(InAppMessage {
+            title: "Hello".to_string(),
+            body: "hello".to_string(),
+            device_id: "device-1".to_string(),
+        });
+        let at = |seconds| Timestamp { seconds, nanos: 0 };
+        log.record(in_app.delivery_records("2", user, at(2)));
+        log.record(sms.delivery_records("1", user, at(1)));
+        log.record(sms.delivery_records("3", "", at(3)));
+
+        let records = log.export(user);
+        let ids = records
+            .iter()
+            .map(|r| r.message_id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec!["1", "2"]);
+        assert_eq!(log.export("+8613800138000").len(), 2);
+
+        assert_eq!(log.forget(user), 2);
+        assert!(log.export(user).is_empty());
+        assert!(log.export("device-1").is_empty());
+        assert_eq!(log.export("+8613800138000").len(), 1);
+    }
+}
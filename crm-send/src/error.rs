@@ -0,0 +1,54 @@
+use crm_core::{FieldViolation, ServiceError};
+use tonic::{Code, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// `field` is the path of the invalid field, e.g. `msg.email.to`
+    #[error("{description}")]
+    InvalidArgument { field: String, description: String },
+    /// the provider worker stopped taking messages, e.g. while shutting down
+    #[error("failed to send {0}")]
+    ProviderUnavailable(&'static str),
+}
+
+impl Error {
+    pub fn invalid(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Error::InvalidArgument {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+impl ServiceError for Error {
+    const DOMAIN: &'static str = "crm-send";
+
+    fn code(&self) -> Code {
+        match self {
+            Error::InvalidArgument { .. } => Code::InvalidArgument,
+            Error::ProviderUnavailable(_) => Code::Unavailable,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Error::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            Error::ProviderUnavailable(_) => "PROVIDER_UNAVAILABLE",
+        }
+    }
+
+    fn violations(&self) -> Vec<FieldViolation> {
+        match self {
+            Error::InvalidArgument { field, description } => {
+                vec![FieldViolation::new(field, description)]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        e.into_status()
+    }
+}
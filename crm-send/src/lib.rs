@@ -1,14 +1,27 @@
 pub mod abi;
 pub mod config;
+pub mod delivery;
+pub mod error;
 pub mod pb;
+mod validate;
 
 use config::AppConfig;
-use pb::send::{notification_server::Notification, send_request::Msg, SendRequest, SendResponse};
-use std::{pin::Pin, sync::Arc, time::Duration};
-use tokio::{sync::mpsc, time::sleep};
+use crm_core::{config::SharedConfig, ServiceError};
+use delivery::DeliveryLog;
+pub use error::Error;
+use pb::send::{
+    notification_server::Notification, send_request::Msg, ForgetRecipientResponse, RecipientExport,
+    RecipientRequest, SendRequest, SendResponse,
+};
+use std::{
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::{sync::mpsc, task::JoinHandle, time::sleep};
 use tokio_stream::Stream;
 use tonic::{Request, Response, Status, Streaming};
-use tracing::info;
+use tracing::{info, info_span, Instrument, Span};
 
 #[derive(Debug, Clone)]
 pub struct NotificationService {
@@ -16,10 +29,14 @@ pub struct NotificationService {
 }
 
 #[allow(unused)]
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct NotificationServiceInner {
-    config: AppConfig,
-    sender: mpsc::Sender<Msg>,
+    config: Arc<SharedConfig<AppConfig>>,
+    /// queued messages, with the span they were queued in
+    sender: mpsc::Sender<(Msg, Span)>,
+    /// the provider worker, done once every queued message went out
+    worker: Mutex<Option<JoinHandle<()>>>,
+    deliveries: DeliveryLog,
 }
 
 type ServiceResult<T> = std::result::Result<Response<T>, Status>;
@@ -35,17 +52,48 @@ impl Notification for NotificationService {
     ) -> ServiceResult<ResponseStream> {
         self.send(request.into_inner()).await
     }
+
+    async fn export_recipient(
+        &self,
+        request: Request<RecipientRequest>,
+    ) -> ServiceResult<RecipientExport> {
+        self.export_recipient(Error::valid(request)?).await
+    }
+
+    async fn forget_recipient(
+        &self,
+        request: Request<RecipientRequest>,
+    ) -> ServiceResult<ForgetRecipientResponse> {
+        self.forget_recipient(Error::valid(request)?).await
+    }
 }
 
-pub async fn dummy_send() -> mpsc::Sender<Msg> {
-    let (tx, mut rx) = mpsc::channel(1024);
+/// Queue of the dummy provider, the worker stops after the last sender is dropped
+/// and the queue is empty
+pub async fn dummy_send() -> (mpsc::Sender<(Msg, Span)>, JoinHandle<()>) {
+    let (tx, mut rx) = mpsc::channel::<(Msg, Span)>(1024);
 
-    tokio::spawn(async move {
-        while let Some(msg) = rx.recv().await {
-            info!("Sent message: {:?}", msg);
-            println!("Sent message: {:?}", msg);
-            sleep(Duration::from_millis(300)).await;
+    let worker = tokio::spawn(async move {
+        while let Some((msg, queued_in)) = rx.recv().await {
+            let span = info_span!(
+                parent: &queued_in,
+                "provider.send",
+                otel.kind = "client",
+                channel = msg.channel().as_str_name(),
+            );
+            async {
+                // recipients are personal data, keep them out of the logs
+                info!(
+                    "Sent {} message to {} recipient(s)",
+                    msg.channel().as_str_name(),
+                    msg.recipients().len()
+                );
+                sleep(Duration::from_millis(300)).await;
+            }
+            .instrument(span)
+            .await;
         }
+        info!("Provider queue drained");
     });
-    tx
+    (tx, worker)
 }
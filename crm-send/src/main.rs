@@ -1,20 +1,17 @@
-use crm_send::{config::AppConfig, NotificationService};
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{
-    fmt::Layer, layer::SubscriberExt as _, util::SubscriberInitExt as _, Layer as _,
-};
+use crm_core::Bootstrap;
+use crm_send::{config::AppConfig, pb::FILE_DESCRIPTOR_SET, NotificationService};
 
 #[tokio::main]
-async fn main() {
-    let layer = Layer::default().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
-    let config = AppConfig::load().unwrap();
-    let addr = format!("[::1]:{}", config.server.port).parse().unwrap();
-    let svc = NotificationService::new(config).await.into_server();
-    info!("Starting server at {}", addr);
-    tonic::transport::Server::builder()
-        .add_service(svc)
-        .serve(addr)
+async fn main() -> anyhow::Result<()> {
+    let (config, server) = Bootstrap::init::<AppConfig>("crm-send").await?;
+    let svc = NotificationService::new(config).await;
+    svc.watch_config();
+    let health = svc.health();
+    // the server returns once the in-flight Send streams ended,
+    // then the provider still has to deliver what they queued
+    server
+        .reflect(FILE_DESCRIPTOR_SET)
+        .drain(svc.drained())
+        .serve(svc.into_server(), health)
         .await
-        .unwrap();
 }
@@ -1 +1,6 @@
+#[rustfmt::skip]
 pub mod send;
+
+/// Descriptors of the compiled protos, served by the reflection service
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/crm_send_descriptor.bin"));
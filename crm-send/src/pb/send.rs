@@ -36,6 +36,10 @@ pub struct InAppMessage {
 pub struct SendRequest {
     #[prost(string, tag = "1")]
     pub message_id: ::prost::alloc::string::String,
+    /// / Email of the user the message is for, its delivery records are filed
+    /// / under it whatever the channel. Optional
+    #[prost(string, tag = "5")]
+    pub user: ::prost::alloc::string::String,
     /// / The message type in the request
     #[prost(oneof = "send_request::Msg", tags = "2, 3, 4")]
     pub msg: ::core::option::Option<send_request::Msg>,
@@ -60,6 +64,69 @@ pub struct SendResponse {
     #[prost(message, optional, tag = "2")]
     pub timestamp: ::core::option::Option<::prost_types::Timestamp>,
 }
+/// / A message delivered to a single recipient
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DeliveryRecord {
+    #[prost(string, tag = "1")]
+    pub message_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub recipient: ::prost::alloc::string::String,
+    #[prost(enumeration = "Channel", tag = "3")]
+    pub channel: i32,
+    #[prost(message, optional, tag = "4")]
+    pub sent_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// / The user of the SendRequest, empty if it had none
+    #[prost(string, tag = "5")]
+    pub user: ::prost::alloc::string::String,
+}
+/// / A user, or an email, phone number or device id messages were sent to
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecipientRequest {
+    #[prost(string, tag = "1")]
+    pub recipient: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RecipientExport {
+    #[prost(message, repeated, tag = "1")]
+    pub records: ::prost::alloc::vec::Vec<DeliveryRecord>,
+}
+#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+pub struct ForgetRecipientResponse {
+    #[prost(uint64, tag = "1")]
+    pub records: u64,
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum Channel {
+    Unspecified = 0,
+    Email = 1,
+    Sms = 2,
+    InApp = 3,
+}
+impl Channel {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            Self::Unspecified => "CHANNEL_UNSPECIFIED",
+            Self::Email => "CHANNEL_EMAIL",
+            Self::Sms => "CHANNEL_SMS",
+            Self::InApp => "CHANNEL_IN_APP",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "CHANNEL_UNSPECIFIED" => Some(Self::Unspecified),
+            "CHANNEL_EMAIL" => Some(Self::Email),
+            "CHANNEL_SMS" => Some(Self::Sms),
+            "CHANNEL_IN_APP" => Some(Self::InApp),
+            _ => None,
+        }
+    }
+}
 /// Generated client implementations.
 pub mod notification_client {
     #![allow(
@@ -67,10 +134,10 @@ pub mod notification_client {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
-    use tonic::codegen::http::Uri;
     use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
     #[derive(Debug, Clone)]
     pub struct NotificationClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -114,8 +181,9 @@ pub mod notification_client {
                     <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
                 >,
             >,
-            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
-                Into<StdError> + std::marker::Send + std::marker::Sync,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
         {
             NotificationClient::new(InterceptedService::new(inner, interceptor))
         }
@@ -157,16 +225,71 @@ pub mod notification_client {
             tonic::Response<tonic::codec::Streaming<super::SendResponse>>,
             tonic::Status,
         > {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
-            })?;
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static("/send.Notification/Send");
             let mut req = request.into_streaming_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("send.Notification", "Send"));
+            req.extensions_mut().insert(GrpcMethod::new("send.Notification", "Send"));
             self.inner.streaming(req, path, codec).await
         }
+        /// dump the delivery records of a user or recipient (GDPR). The records
+        /// are only kept in memory, an export after a restart comes back empty
+        pub async fn export_recipient(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RecipientRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::RecipientExport>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/send.Notification/ExportRecipient",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("send.Notification", "ExportRecipient"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// purge the delivery records of a user or recipient (GDPR)
+        pub async fn forget_recipient(
+            &mut self,
+            request: impl tonic::IntoRequest<super::RecipientRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ForgetRecipientResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/send.Notification/ForgetRecipient",
+            );
+            let mut req = request.into_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("send.Notification", "ForgetRecipient"));
+            self.inner.unary(req, path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -176,7 +299,7 @@ pub mod notification_server {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
     use tonic::codegen::*;
     /// Generated trait containing gRPC methods that should be implemented for use with NotificationServer.
@@ -185,12 +308,27 @@ pub mod notification_server {
         /// Server streaming response type for the Send method.
         type SendStream: tonic::codegen::tokio_stream::Stream<
                 Item = std::result::Result<super::SendResponse, tonic::Status>,
-            > + std::marker::Send
+            >
+            + std::marker::Send
             + 'static;
         async fn send(
             &self,
             request: tonic::Request<tonic::Streaming<super::SendRequest>>,
         ) -> std::result::Result<tonic::Response<Self::SendStream>, tonic::Status>;
+        /// dump the delivery records of a user or recipient (GDPR). The records
+        /// are only kept in memory, an export after a restart comes back empty
+        async fn export_recipient(
+            &self,
+            request: tonic::Request<super::RecipientRequest>,
+        ) -> std::result::Result<tonic::Response<super::RecipientExport>, tonic::Status>;
+        /// purge the delivery records of a user or recipient (GDPR)
+        async fn forget_recipient(
+            &self,
+            request: tonic::Request<super::RecipientRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ForgetRecipientResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct NotificationServer<T> {
@@ -213,7 +351,10 @@ pub mod notification_server {
                 max_encoding_message_size: None,
             }
         }
-        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
         where
             F: tonic::service::Interceptor,
         {
@@ -268,18 +409,24 @@ pub mod notification_server {
                 "/send.Notification/Send" => {
                     #[allow(non_camel_case_types)]
                     struct SendSvc<T: Notification>(pub Arc<T>);
-                    impl<T: Notification> tonic::server::StreamingService<super::SendRequest> for SendSvc<T> {
+                    impl<
+                        T: Notification,
+                    > tonic::server::StreamingService<super::SendRequest>
+                    for SendSvc<T> {
                         type Response = super::SendResponse;
                         type ResponseStream = T::SendStream;
-                        type Future =
-                            BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::ResponseStream>,
+                            tonic::Status,
+                        >;
                         fn call(
                             &mut self,
                             request: tonic::Request<tonic::Streaming<super::SendRequest>>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut =
-                                async move { <T as Notification>::send(&inner, request).await };
+                            let fut = async move {
+                                <T as Notification>::send(&inner, request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -305,19 +452,113 @@ pub mod notification_server {
                     };
                     Box::pin(fut)
                 }
-                _ => Box::pin(async move {
-                    let mut response = http::Response::new(empty_body());
-                    let headers = response.headers_mut();
-                    headers.insert(
-                        tonic::Status::GRPC_STATUS,
-                        (tonic::Code::Unimplemented as i32).into(),
-                    );
-                    headers.insert(
-                        http::header::CONTENT_TYPE,
-                        tonic::metadata::GRPC_CONTENT_TYPE,
-                    );
-                    Ok(response)
-                }),
+                "/send.Notification/ExportRecipient" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExportRecipientSvc<T: Notification>(pub Arc<T>);
+                    impl<
+                        T: Notification,
+                    > tonic::server::UnaryService<super::RecipientRequest>
+                    for ExportRecipientSvc<T> {
+                        type Response = super::RecipientExport;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RecipientRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::export_recipient(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ExportRecipientSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/send.Notification/ForgetRecipient" => {
+                    #[allow(non_camel_case_types)]
+                    struct ForgetRecipientSvc<T: Notification>(pub Arc<T>);
+                    impl<
+                        T: Notification,
+                    > tonic::server::UnaryService<super::RecipientRequest>
+                    for ForgetRecipientSvc<T> {
+                        type Response = super::ForgetRecipientResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::RecipientRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Notification>::forget_recipient(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ForgetRecipientSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
             }
         }
     }
@@ -0,0 +1,105 @@
+use crm_core::{Validate, Violations};
+
+use crate::pb::send::{
+    send_request::Msg, EmailMessage, InAppMessage, RecipientRequest, SendRequest, SmsMessage,
+};
+
+impl Validate for SendRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("message_id", &self.message_id);
+        match &self.msg {
+            Some(Msg::Email(msg)) => v.nested("email", msg),
+            Some(Msg::Sms(msg)) => v.nested("sms", msg),
+            Some(Msg::InApp(msg)) => v.nested("in_app", msg),
+            None => v.check("msg", false, "is required"),
+        }
+        if !self.user.is_empty() {
+            v.email("user", &self.user);
+        }
+    }
+}
+
+impl Validate for EmailMessage {
+    fn validate(&self, v: &mut Violations) {
+        v.email("from", &self.from);
+        v.not_empty("to", &self.to);
+        for (i, to) in self.to.iter().enumerate() {
+            v.email(&format!("to[{}]", i), to);
+        }
+        v.required("subject", &self.subject);
+    }
+}
+
+impl Validate for SmsMessage {
+    fn validate(&self, v: &mut Violations) {
+        v.required("sender", &self.sender);
+        v.not_empty("recipients", &self.recipients);
+        for (i, recipient) in self.recipients.iter().enumerate() {
+            v.phone(&format!("recipients[{}]", i), recipient);
+        }
+        v.required("body", &self.body);
+    }
+}
+
+impl Validate for InAppMessage {
+    fn validate(&self, v: &mut Violations) {
+        v.required("device_id", &self.device_id);
+        v.required("title", &self.title);
+    }
+}
+
+impl Validate for RecipientRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("recipient", &self.recipient);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_core::ErrorDetails;
+
+    use super::*;
+
+    fn fields<T: Validate>(message: T) -> Vec<String> {
+        let status = message.validated("crm-send").err().unwrap();
+        let details = ErrorDetails::from_status(&status);
+        details.fields().into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn fake_messages_should_be_valid() {
+        let requests: [SendRequest; 3] = [
+            EmailMessage::fake().into(),
+            SmsMessage::fake().into(),
+            InAppMessage::fake().into(),
+        ];
+        for req in requests {
+            assert!(req.is_valid(), "{:?}", req);
+        }
+    }
+
+    #[test]
+    fn invalid_recipients_should_be_reported() {
+        let msg = EmailMessage {
+            to: vec![],
+            ..EmailMessage::fake()
+        };
+        assert_eq!(fields(SendRequest::from(msg)), vec!["email.to"]);
+
+        let msg = EmailMessage {
+            from: "noreply".to_string(),
+            to: vec!["alice@example.com".to_string(), "bob".to_string()],
+            ..EmailMessage::fake()
+        };
+        assert_eq!(
+            fields(SendRequest::from(msg)),
+            vec!["email.from", "email.to[1]"]
+        );
+
+        let msg = SmsMessage {
+            recipients: vec!["13800138000".to_string()],
+            ..SmsMessage::fake()
+        };
+        assert_eq!(fields(SendRequest::from(msg)), vec!["sms.recipients[0]"]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{net::SocketAddr, time::Duration};
 
 use anyhow::Result;
+use crm_core::Config;
 use crm_send::{
     config::AppConfig,
     pb::send::{
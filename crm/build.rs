@@ -1,14 +1,18 @@
-use std::fs;
+use std::{env, fs, path::PathBuf};
 
 use proto_builder_trait::tonic::BuilderAttributes;
 
 fn main() -> anyhow::Result<()> {
     fs::create_dir_all("src/pb")?;
+    let descriptor = PathBuf::from(env::var("OUT_DIR")?).join("crm_descriptor.bin");
     let builder = tonic_build::configure();
 
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(&descriptor)
         .with_derive_builder(&["WelcomeRequest", "RecallRequest", "RemindRequest"], None)
+        .extern_path(".user_stats", "::user_stat::pb::user_stats")
+        .extern_path(".send", "::crm_send::pb::send")
         .compile_protos(
             &["../protos/crm/messages.proto", "../protos/crm/rpc.proto"],
             &["../protos"],
@@ -0,0 +1,200 @@
+use crm_core::convert;
+use crm_send::pb::send::RecipientRequest;
+use tonic::{Response, Status};
+use tracing::warn;
+use user_stat::pb::user_stats::{DeleteUserRequest, GetUserRequest};
+
+use crate::{
+    pb::{ExportUserRequest, ExportUserResponse, ForgetUserRequest, ForgetUserResponse},
+    CrmService,
+};
+
+// names of the downstreams in `ForgetUserResponse::failed`
+const USER_STATS: &str = "user-stat";
+const NOTIFICATION: &str = "crm-send";
+
+impl CrmService {
+    /// Erase the user from user-stat (row and events) and crm-send (delivery records
+    /// of every channel, filed under the user's email).
+    /// Both are attempted even if the other fails, the ones that failed are listed in
+    /// the response. Every step is idempotent, so a failed request can simply be retried.
+    pub async fn forget_user(
+        &self,
+        request: ForgetUserRequest,
+    ) -> Result<Response<ForgetUserResponse>, Status> {
+        let (user, deliveries) = tokio::join!(
+            self.user_stats.retry(
+                DeleteUserRequest {
+                    email: request.email.clone(),
+                },
+                |mut client, req| async move { client.delete_user(req).await },
+            ),
+            self.notification.retry(
+                RecipientRequest {
+                    recipient: request.email.clone(),
+                },
+                |mut client, req| async move { client.forget_recipient(req).await },
+            ),
+        );
+
+        let mut ret = ForgetUserResponse {
+            email: request.email,
+            ..Default::default()
+        };
+        match user {
+            Ok(user) => {
+                let user = user.into_inner();
+                ret.user_deleted = user.deleted;
+                ret.events_deleted = user.events;
+            }
+            Err(e) => ret.failed(USER_STATS, e),
+        }
+        match deliveries {
+            Ok(deliveries) => ret.deliveries_deleted = deliveries.into_inner().records,
+            Err(e) => ret.failed(NOTIFICATION, e),
+        }
+        Ok(Response::new(ret))
+    }
+
+    /// Collect what user-stat and crm-send store about the user into one bundle
+    pub async fn export_user(
+        &self,
+        request: ExportUserRequest,
+    ) -> Result<Response<ExportUserResponse>, Status> {
+        let user = self
+            .user_stats
+            .retry(
+                GetUserRequest {
+                    email: request.email.clone(),
+                },
+                |mut client, req| async move { client.export_user(req).await },
+            )
+            .await?
+            .into_inner();
+        let deliveries = self
+            .notification
+            .retry(
+                RecipientRequest {
+                    recipient: request.email.clone(),
+                },
+                |mut client, req| async move { client.export_recipient(req).await },
+            )
+            .await?
+            .into_inner();
+
+        Ok(Response::new(ExportUserResponse {
+            email: request.email,
+            exported_at: Some(convert::now()),
+            user: user.user,
+            events: user.events,
+            deliveries: deliveries.records,
+        }))
+    }
+}
+
+impl ForgetUserResponse {
+    fn failed(&mut self, downstream: &str, e: Status) {
+        warn!("failed to forget a user in {}: {}", downstream, e);
+        self.failed.push(downstream.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_send::pb::send::{Channel, EmailMessage, InAppMessage, SendRequest, SmsMessage};
+    use user_stat::pb::user_stats::{EventType, UserEvent};
+
+    use super::*;
+    use crate::test_utils::{user, MetadataStub, Stubbed, UserStatsStub};
+
+    const EMAIL: &str = "gone@acme.org";
+
+    fn sent_to(user: &str, msg: impl Into<SendRequest>) -> SendRequest {
+        SendRequest {
+            user: user.to_string(),
+            ..msg.into()
+        }
+    }
+
+    async fn stubbed() -> Stubbed {
+        stubbed_with(vec![]).await
+    }
+
+    /// Stubs where the user-stat methods `failing` answer with an error
+    async fn stubbed_with(failing: Vec<&'static str>) -> Stubbed {
+        let event = UserEvent {
+            email: EMAIL.to_string(),
+            event_type: EventType::Visit as i32,
+            ..Default::default()
+        };
+        let user_stats = UserStatsStub {
+            users: vec![user(EMAIL)],
+            events: vec![event],
+            failing,
+            ..Default::default()
+        };
+        let stubbed = Stubbed::start(user_stats, MetadataStub::default()).await;
+        let messages = vec![
+            sent_to(EMAIL, EmailMessage::fake()),
+            sent_to(EMAIL, SmsMessage::fake()),
+            sent_to(EMAIL, InAppMessage::fake()),
+            sent_to("other@acme.org", SmsMessage::fake()),
+        ];
+        stubbed
+            .notification
+            .clone()
+            .send(tokio_stream::iter(messages))
+            .await
+            .unwrap();
+        stubbed.deliveries("other@acme.org", 1).await;
+        stubbed
+    }
+
+    #[tokio::test]
+    async fn export_user_should_collect_every_channel() {
+        let stubbed = stubbed().await;
+        let req = ExportUserRequest {
+            email: EMAIL.to_string(),
+        };
+        let export = stubbed.crm.export_user(req).await.unwrap().into_inner();
+        assert_eq!(export.user.unwrap().email, EMAIL);
+        assert_eq!(export.events.len(), 1);
+        let mut channels = export
+            .deliveries
+            .iter()
+            .map(|record| record.channel())
+            .collect::<Vec<_>>();
+        channels.sort();
+        assert_eq!(channels, [Channel::Email, Channel::Sms, Channel::InApp]);
+    }
+
+    #[tokio::test]
+    async fn forget_user_should_erase_every_channel() {
+        let stubbed = stubbed().await;
+        let req = ForgetUserRequest {
+            email: EMAIL.to_string(),
+        };
+        let res = stubbed.crm.forget_user(req).await.unwrap().into_inner();
+        assert!(res.user_deleted);
+        assert_eq!(res.events_deleted, 1);
+        assert_eq!(res.deliveries_deleted, 3);
+        assert!(res.failed.is_empty());
+
+        assert!(stubbed.deliveries(EMAIL, 0).await.is_empty());
+        assert_eq!(stubbed.deliveries("other@acme.org", 1).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn forget_user_should_report_failed_downstreams() {
+        let stubbed = stubbed_with(vec!["delete_user"]).await;
+        let req = ForgetUserRequest {
+            email: EMAIL.to_string(),
+        };
+        let res = stubbed.crm.forget_user(req).await.unwrap().into_inner();
+        assert_eq!(res.failed, ["user-stat"]);
+        assert!(!res.user_deleted);
+        // crm-send is erased all the same
+        assert_eq!(res.deliveries_deleted, 3);
+        assert!(stubbed.deliveries(EMAIL, 0).await.is_empty());
+    }
+}
@@ -1,18 +1,18 @@
+mod gdpr;
+mod recall;
+
 use chrono::{Duration, Utc};
-use crm_metadata::pb::metadata::{Content, MaterializeRequest};
+use crm_core::{convert, ToTimestamp};
+use crm_metadata::pb::metadata::BatchGetRequest;
 use crm_send::pb::send::{send_request::Msg, EmailMessage, SendRequest};
 use prost_types::Timestamp;
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::{Response, Status};
-use user_stat::{
-    pb::user_stats::{QueryRequest, QueryRequestBuilder},
-    test_utils::new_timequery,
+use user_stat::pb::user_stats::{
+    QueryRequest, QueryRequestBuilder, QueryRequestBuilderError, TimeQuery,
 };
 
-use crate::{
-    CrmService, RecallRequest, RecallResponse, RemindRequest, RemindResponse, WelcomeRequest,
-    WelcomeResponse,
-};
+use crate::{CrmService, Error, WelcomeRequest, WelcomeResponse};
 
 impl CrmService {
     // 整个逻辑是，
@@ -24,38 +24,31 @@ impl CrmService {
         request: WelcomeRequest,
     ) -> Result<Response<WelcomeResponse>, Status> {
         let id = request.id.clone();
-        let date = Utc::now() - Duration::days(request.interval as _);
-        let before = Timestamp {
-            seconds: date.timestamp(),
-            nanos: date.timestamp_subsec_nanos() as i32,
-        };
+        let before = (Utc::now() - Duration::days(request.interval as _)).to_timestamp();
+        let after = convert::now();
 
-        let after = Timestamp {
-            seconds: Utc::now().timestamp(),
-            nanos: Utc::now().timestamp_subsec_nanos() as i32,
-        };
-
-        let user_stat_req = get_user_stats_req("created_at", before, after);
+        let user_stat_req = get_user_stats_req("created_at", before, after).map_err(Error::from)?;
         let mut users = self
             .user_stats
-            .clone()
-            .query(user_stat_req)
+            .retry(user_stat_req, |mut client, req| async move {
+                client.query(req).await
+            })
             .await?
             .into_inner();
 
-        let materialize_req = MaterializeRequest::new_with_ids(&request.content_ids);
-        let contents: Vec<Content> = self
+        let contents = self
             .metadata
-            .clone()
-            .materialize(materialize_req)
-            .await
-            .unwrap()
+            .retry(
+                BatchGetRequest {
+                    ids: request.content_ids,
+                },
+                |mut client, req| async move { client.batch_get(req).await },
+            )
+            .await?
             .into_inner()
-            .filter_map(|v| v.ok())
-            .collect()
-            .await;
+            .contents;
 
-        let sender = self.config.server.sender.clone();
+        let sender = self.config().server.extra.sender.clone();
         let (tx, rx) = tokio::sync::mpsc::channel(1024);
         tokio::spawn(async move {
             while let Some(Ok(user)) = users.next().await {
@@ -65,36 +58,42 @@ impl CrmService {
                 let msg = EmailMessage {
                     subject: format!("Welcome to our platform, {}", user.name),
                     from: sender,
-                    to: vec![user.email],
+                    to: vec![user.email.clone()],
                     body: format!("{:?}", contents),
                 };
                 let send_req = SendRequest {
                     message_id: id.clone(),
                     msg: Some(Msg::Email(msg)),
+                    user: user.email,
                 };
-                tx.send(send_req).await.unwrap();
+                // the send call failed and dropped the stream
+                if tx.send(send_req).await.is_err() {
+                    break;
+                }
             }
         });
         let send_reqs = ReceiverStream::from(rx);
-        self.notification.clone().send(send_reqs).await?;
+        self.notification
+            .call(send_reqs, |mut client, reqs| async move {
+                client.send(reqs).await
+            })
+            .await?;
 
         let ret = WelcomeResponse { id: request.id };
         Ok(Response::new(ret))
     }
-
-    pub fn remind(&self, _request: RemindRequest) -> Result<RemindResponse, Status> {
-        todo!()
-    }
-
-    pub fn recall(&self, _request: RecallRequest) -> Result<RecallResponse, Status> {
-        todo!()
-    }
 }
 
-fn get_user_stats_req(name: &str, before: Timestamp, after: Timestamp) -> QueryRequest {
-    let req = QueryRequestBuilder::default()
-        .timestamp((name.to_string(), new_timequery(after, before)))
+fn get_user_stats_req(
+    name: &str,
+    before: Timestamp,
+    after: Timestamp,
+) -> Result<QueryRequest, QueryRequestBuilderError> {
+    let query = TimeQuery {
+        after: Some(after),
+        before: Some(before),
+    };
+    QueryRequestBuilder::default()
+        .timestamp((name.to_string(), query))
         .build()
-        .unwrap();
-    req
 }
@@ -0,0 +1,413 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Duration, Utc};
+use crm_core::ToTimestamp;
+use crm_metadata::pb::metadata::{
+    BatchGetRequest, Content, ContentOrder, ListContentsRequest, TrendingRequest,
+};
+use crm_send::pb::send::{send_request::Msg, EmailMessage, SendRequest};
+use tokio_stream::StreamExt;
+use tonic::{Response, Status};
+use user_stat::pb::user_stats::{
+    BatchGetUsersRequest, QueryRequestBuilder, TimeQuery, User, UserStat,
+};
+
+use crate::{
+    recommend::Profile, CrmService, Error, RecallRequest, RecallResponse, RemindRequest,
+    RemindResponse,
+};
+
+/// Max number of ids or emails the batch gets of crm-metadata and user-stat take
+const MAX_BATCH_SIZE: usize = 1000;
+
+/// Contents materialized during one campaign, so a content shared by the
+/// history of many users is fetched once
+#[derive(Default)]
+struct Catalog {
+    contents: HashMap<u32, Content>,
+}
+
+impl Catalog {
+    /// The contents of `ids` fetched so far, in order
+    fn get(&self, ids: &[u32]) -> Vec<Content> {
+        ids.iter()
+            .filter_map(|id| self.contents.get(id).cloned())
+            .collect()
+    }
+}
+
+impl CrmService {
+    /// Bring back users who last visited `last_visit_interval` days ago with
+    /// the given contents, or with contents picked from their history
+    pub async fn recall(&self, request: RecallRequest) -> Result<Response<RecallResponse>, Status> {
+        let timestamps = vec![(
+            "last_visited_at".to_string(),
+            day_window(request.last_visit_interval),
+        )];
+        let users = self.query_users(timestamps).await?;
+
+        let mut catalog = Catalog::default();
+        let fixed = self.materialize(&mut catalog, &request.content_ids).await?;
+        let (stats, candidates) = if request.content_ids.is_empty() {
+            let stats = self.user_stats(&users).await?;
+            let history = stats
+                .values()
+                .flat_map(Profile::history_ids)
+                .collect::<Vec<_>>();
+            self.materialize(&mut catalog, &history).await?;
+            (stats, self.popular_contents().await?)
+        } else {
+            Default::default()
+        };
+
+        let mut messages = Vec::new();
+        for user in users {
+            let contents = if request.content_ids.is_empty() {
+                let Some(stat) = stats.get(&user.email) else {
+                    continue;
+                };
+                let profile = Profile::new(stat, &catalog.contents);
+                profile.recommend(&candidates, self.config().recommend.limit)
+            } else {
+                fixed.clone()
+            };
+            if let Some(msg) = self.email(&request.id, "We miss you", user, contents) {
+                messages.push(msg);
+            }
+        }
+        self.send_all(messages).await?;
+
+        Ok(Response::new(RecallResponse {
+            request_id: request.id,
+            ..Default::default()
+        }))
+    }
+
+    /// Remind users who last watched something `last_watched_interval` days ago
+    /// of the given contents, or of the contents they left unfinished
+    pub async fn remind(&self, request: RemindRequest) -> Result<Response<RemindResponse>, Status> {
+        let mut timestamps = vec![(
+            "last_watched_at".to_string(),
+            day_window(request.last_watched_interval),
+        )];
+        if request.last_visit_interval > 0 {
+            timestamps.push((
+                "last_visited_at".to_string(),
+                day_window(request.last_visit_interval),
+            ));
+        }
+        let users = self.query_users(timestamps).await?;
+
+        let mut catalog = Catalog::default();
+        let fixed = self.materialize(&mut catalog, &request.content_ids).await?;
+        let stats = if request.content_ids.is_empty() {
+            let stats = self.user_stats(&users).await?;
+            let ids = stats
+                .values()
+                .flat_map(|stat| [Profile::history_ids(stat), unfinished(stat)].concat())
+                .collect::<Vec<_>>();
+            self.materialize(&mut catalog, &ids).await?;
+            stats
+        } else {
+            HashMap::new()
+        };
+
+        let mut messages = Vec::new();
+        for user in users {
+            let contents = if request.content_ids.is_empty() {
+                let Some(stat) = stats.get(&user.email) else {
+                    continue;
+                };
+                let profile = Profile::new(stat, &catalog.contents);
+                let candidates = catalog.get(&unfinished(stat));
+                profile.recommend(&candidates, self.config().recommend.limit)
+            } else {
+                fixed.clone()
+            };
+            if let Some(msg) = self.email(&request.id, "Continue watching", user, contents) {
+                messages.push(msg);
+            }
+        }
+        self.send_all(messages).await?;
+
+        Ok(Response::new(RemindResponse {
+            id: request.id,
+            interval: request.last_watched_interval,
+        }))
+    }
+
+    async fn query_users(&self, timestamps: Vec<(String, TimeQuery)>) -> Result<Vec<User>, Status> {
+        let mut builder = QueryRequestBuilder::default();
+        for timestamp in timestamps {
+            builder.timestamp(timestamp);
+        }
+        let req = builder.build().map_err(Error::from)?;
+        let users = self
+            .user_stats
+            .retry(
+                req,
+                |mut client, req| async move { client.query(req).await },
+            )
+            .await?
+            .into_inner()
+            .filter_map(Result::ok)
+            .collect()
+            .await;
+        Ok(users)
+    }
+
+    /// Stats of `users` by email, the ones user-stat doesn't know anymore left out
+    async fn user_stats(&self, users: &[User]) -> Result<HashMap<String, UserStat>, Status> {
+        let mut stats = HashMap::with_capacity(users.len());
+        for users in users.chunks(MAX_BATCH_SIZE) {
+            let req = BatchGetUsersRequest {
+                emails: users.iter().map(|user| user.email.clone()).collect(),
+            };
+            let res = self
+                .user_stats
+                .retry(req, |mut client, req| async move {
+                    client.batch_get_users(req).await
+                })
+                .await?;
+            stats.extend(
+                res.into_inner()
+                    .users
+                    .into_iter()
+                    .map(|stat| (stat.email.clone(), stat)),
+            );
+        }
+        Ok(stats)
+    }
+
+    /// Contents of `ids` in order, fetching only the ones not in the catalog yet
+    async fn materialize(
+        &self,
+        catalog: &mut Catalog,
+        ids: &[u32],
+    ) -> Result<Vec<Content>, Status> {
+        let missing = ids
+            .iter()
+            .copied()
+            .filter(|id| !catalog.contents.contains_key(id))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        for ids in missing.chunks(MAX_BATCH_SIZE) {
+            let contents = self
+                .metadata
+                .retry(
+                    BatchGetRequest { ids: ids.to_vec() },
+                    |mut client, req| async move { client.batch_get(req).await },
+                )
+                .await?
+                .into_inner()
+                .contents;
+            catalog
+                .contents
+                .extend(contents.into_iter().map(|c| (c.id, c)));
+        }
+        Ok(catalog.get(ids))
+    }
+
+    /// Contents of every type trending over the last `recommend.trending_days`,
+    /// or the most liked ones if nothing was watched in that window
+    async fn popular_contents(&self) -> Result<Vec<Content>, Status> {
+        let recommend = &self.config().recommend;
+        let req = TrendingRequest {
+            days: recommend.trending_days,
+            limit: recommend.candidates,
+            ..Default::default()
+        };
+        let trending = self
+            .metadata
+            .retry(
+                req,
+                |mut client, req| async move { client.trending(req).await },
+            )
+            .await?
+            .into_inner();
+        let contents = trending
+            .groups
+            .into_iter()
+            .flat_map(|group| group.contents)
+            .filter_map(|trending| trending.content)
+            .collect::<Vec<_>>();
+        if !contents.is_empty() {
+            return Ok(contents);
+        }
+
+        let req = ListContentsRequest {
+            order_by: ContentOrder::Likes as i32,
+            page_size: recommend.candidates,
+            ..Default::default()
+        };
+        let res = self
+            .metadata
+            .retry(req, |mut client, req| async move {
+                client.list_contents(req).await
+            })
+            .await?;
+        Ok(res.into_inner().contents)
+    }
+
+    fn email(
+        &self,
+        id: &str,
+        subject: &str,
+        user: User,
+        contents: Vec<Content>,
+    ) -> Option<SendRequest> {
+        if contents.is_empty() {
+            return None;
+        }
+        let msg = EmailMessage {
+            subject: format!("{}, {}", subject, user.name),
+            from: self.config().server.extra.sender.clone(),
+            to: vec![user.email.clone()],
+            body: format!("{:?}", contents),
+        };
+        Some(SendRequest {
+            message_id: id.to_string(),
+            msg: Some(Msg::Email(msg)),
+            user: user.email,
+        })
+    }
+
+    async fn send_all(&self, messages: Vec<SendRequest>) -> Result<(), Status> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        self.notification
+            .call(
+                tokio_stream::iter(messages),
+                |mut client, reqs| async move { client.send(reqs).await },
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Contents the user started or viewed, but didn't finish
+fn unfinished(stat: &UserStat) -> Vec<u32> {
+    stat.started_but_not_finished
+        .iter()
+        .chain(&stat.viewed_but_not_started)
+        .copied()
+        .collect()
+}
+
+/// The 24 hours starting `days` days ago
+fn day_window(days: u32) -> TimeQuery {
+    let after = Utc::now() - Duration::days(days as _);
+    let before = after + Duration::days(1);
+    TimeQuery {
+        after: Some(after.to_timestamp()),
+        before: Some(before.to_timestamp()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{content, user, MetadataStub, Stubbed, UserStatsStub};
+
+    fn recall_request() -> RecallRequest {
+        RecallRequest {
+            id: "recall".to_string(),
+            last_visit_interval: 30,
+            content_ids: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn recall_should_batch_the_user_stats() {
+        let done = UserStat {
+            finished: vec![1, 2],
+            ..user("done@acme.org")
+        };
+        let watcher = UserStat {
+            recent_watched: vec![3],
+            ..user("watcher@acme.org")
+        };
+        let user_stats = UserStatsStub {
+            users: vec![done, watcher, user("new@acme.org")],
+            ..Default::default()
+        };
+        let metadata = MetadataStub {
+            contents: vec![content(1, 10), content(2, 20), content(3, 30)],
+            trending: vec![1, 2],
+            ..Default::default()
+        };
+        let stubbed = Stubbed::start(user_stats.clone(), metadata.clone()).await;
+
+        let res = stubbed.crm.recall(recall_request()).await.unwrap();
+        assert_eq!(res.into_inner().request_id, "recall");
+
+        for email in ["watcher@acme.org", "new@acme.org"] {
+            let records = stubbed.deliveries(email, 1).await;
+            assert_eq!(records.len(), 1);
+            assert_eq!(records[0].message_id, "recall");
+        }
+        assert!(stubbed.deliveries("done@acme.org", 0).await.is_empty());
+        assert_eq!(user_stats.calls.count("batch_get_users"), 1);
+        assert_eq!(user_stats.calls.count("get_user"), 0);
+        assert_eq!(metadata.calls.count("trending"), 1);
+        assert_eq!(metadata.calls.count("list_contents"), 0);
+        // the history of every user in one call
+        assert_eq!(metadata.calls.count("batch_get"), 1);
+    }
+
+    #[tokio::test]
+    async fn recall_should_fall_back_to_the_most_liked() {
+        let metadata = MetadataStub {
+            contents: vec![content(1, 10)],
+            ..Default::default()
+        };
+        let user_stats = UserStatsStub {
+            users: vec![user("new@acme.org")],
+            ..Default::default()
+        };
+        let stubbed = Stubbed::start(user_stats, metadata.clone()).await;
+
+        stubbed.crm.recall(recall_request()).await.unwrap();
+        assert_eq!(stubbed.deliveries("new@acme.org", 1).await.len(), 1);
+        assert_eq!(metadata.calls.count("list_contents"), 1);
+    }
+
+    #[tokio::test]
+    async fn remind_should_recommend_unfinished_contents() {
+        let done = UserStat {
+            finished: vec![1],
+            ..user("done@acme.org")
+        };
+        let started = UserStat {
+            started_but_not_finished: vec![2],
+            viewed_but_not_started: vec![1],
+            ..user("started@acme.org")
+        };
+        let user_stats = UserStatsStub {
+            users: vec![done, started],
+            ..Default::default()
+        };
+        let metadata = MetadataStub {
+            contents: vec![content(1, 10), content(2, 20)],
+            trending: vec![1, 2],
+            ..Default::default()
+        };
+        let stubbed = Stubbed::start(user_stats.clone(), metadata.clone()).await;
+
+        let req = RemindRequest {
+            id: "remind".to_string(),
+            last_watched_interval: 7,
+            ..Default::default()
+        };
+        let res = stubbed.crm.remind(req).await.unwrap().into_inner();
+        assert_eq!(res.id, "remind");
+
+        let records = stubbed.deliveries("started@acme.org", 1).await;
+        assert_eq!(records[0].message_id, "remind");
+        assert!(stubbed.deliveries("done@acme.org", 0).await.is_empty());
+        assert_eq!(user_stats.calls.count("batch_get_users"), 1);
+        assert_eq!(metadata.calls.count("trending"), 0);
+    }
+}
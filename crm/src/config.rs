@@ -1,44 +1,89 @@
-use anyhow::{bail, Result};
+use crm_core::{AuthConfig, Checks, ClientConfig, Config, ServerConfig, TelemetryConfig, Upstream};
 use serde::{Deserialize, Serialize};
-use std::fs;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
-    pub server: ServerConfig,
+    pub server: ServerConfig<Services>,
     pub auth: AuthConfig,
+    pub recommend: RecommendConfig,
+    /// deadlines, retries and circuit breaking of the downstream calls
+    #[serde(default)]
+    pub client: ClientConfig,
+    /// span export, traces stay local when unset
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
 }
 
+/// The services crm calls, and the sender of its emails
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ServerConfig {
-    // pub host: String,
-    pub port: u16,
-    pub metadata: String,
-    pub user_stats: String,
-    pub notification: String,
+pub struct Services {
+    /// a url, a list of urls or `dns: <url>`, see `Upstream`
+    pub metadata: Upstream,
+    pub user_stats: Upstream,
+    pub notification: Upstream,
     pub sender: String,
 }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AuthConfig {
-    pub pk: String,
+pub struct RecommendConfig {
+    /// number of trending contents of each type ranked for each user when recall
+    /// has no content_ids, at most 100
+    pub candidates: u32,
+    /// days over which the contents trend, at most 90
+    pub trending_days: u32,
+    /// max number of contents picked for each user
+    pub limit: usize,
 }
 
-impl AppConfig {
-    pub fn load() -> Result<Self> {
-        let ret: Result<AppConfig, _> = match (
-            fs::File::open("crm.yml"),
-            fs::File::open("/etc/config/crm.yml"),
-            std::env::var("CRM_CONFIG"),
-        ) {
-            (Ok(reader), _, _) => serde_yaml::from_reader(reader),
-            (Err(_), Ok(reader), _) => serde_yaml::from_reader(reader),
-            (Err(_), Err(_), Ok(reader)) => serde_yaml::from_reader(fs::File::open(reader)?),
-            _ => bail!("Config file not found"),
-        };
-
-        Ok(ret?)
+impl Config for AppConfig {
+    const NAME: &'static str = "crm";
+    const ENV_PREFIX: &'static str = "CRM";
+    const DEFAULTS: &'static str = r#"
+server:
+    port: 50000
+    metrics_port: 9100
+    shutdown_timeout_secs: 30
+recommend:
+    candidates: 100
+    trending_days: 7
+    limit: 5
+"#;
+    const SECRETS: &'static [&'static str] = &["server.tls.key"];
+
+    fn validate(&self, checks: &mut Checks) {
+        let server = &self.server;
+        server.validate("server", checks);
+        for (path, upstream) in [
+            ("server.user_stats", &server.extra.user_stats),
+            ("server.metadata", &server.extra.metadata),
+            ("server.notification", &server.extra.notification),
+        ] {
+            upstream.validate(path, checks);
+        }
+        checks.check(
+            "server.sender",
+            !server.extra.sender.is_empty(),
+            "must not be empty",
+        );
+        self.auth.validate("auth", checks);
+        checks.check("recommend.limit", self.recommend.limit > 0, "must not be 0");
+        checks.check(
+            "recommend.candidates",
+            (1..=100).contains(&self.recommend.candidates),
+            "must be between 1 and 100",
+        );
+        checks.check(
+            "recommend.trending_days",
+            (1..=90).contains(&self.recommend.trending_days),
+            "must be between 1 and 90",
+        );
+        self.client.validate("client", checks);
+        self.telemetry.validate(checks);
     }
 }
 
+crm_core::service_config!(AppConfig, Services);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,56 @@
+use crm_core::{FieldViolation, ServiceError};
+use tonic::{Code, Status};
+use user_stat::pb::user_stats::QueryRequestBuilderError;
+
+/// Errors of crm itself, those of the downstream services are passed through
+/// as they are
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// `field` is the path of the invalid field, e.g. `email`
+    #[error("{description}")]
+    InvalidArgument { field: String, description: String },
+    #[error("failed to build the user-stat query")]
+    Query(#[from] QueryRequestBuilderError),
+}
+
+impl Error {
+    pub fn invalid(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Error::InvalidArgument {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+impl ServiceError for Error {
+    const DOMAIN: &'static str = "crm";
+
+    fn code(&self) -> Code {
+        match self {
+            Error::InvalidArgument { .. } => Code::InvalidArgument,
+            Error::Query(_) => Code::Internal,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Error::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            Error::Query(_) => "INTERNAL",
+        }
+    }
+
+    fn violations(&self) -> Vec<FieldViolation> {
+        match self {
+            Error::InvalidArgument { field, description } => {
+                vec![FieldViolation::new(field, description)]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        e.into_status()
+    }
+}
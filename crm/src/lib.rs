@@ -1,22 +1,39 @@
 pub mod abi;
 pub mod config;
+pub mod error;
 pub mod pb;
+pub mod recommend;
+#[cfg(test)]
+mod test_utils;
+mod validate;
+
+use std::sync::Arc;
 
 use config::AppConfig;
 use crm_server::CrmServer;
+pub use error::Error;
 use pb::{crm_server::Crm, *};
 
+use crm_core::{
+    config::{SharedConfig, WATCH_INTERVAL},
+    health::check_remote,
+    telemetry::{TraceInterceptor, TracedChannel},
+    Downstream, HealthService, ServiceError,
+};
 use crm_metadata::pb::metadata::metadata_client::MetadataClient;
 use crm_send::pb::send::notification_client::NotificationClient;
+use tokio::task::JoinHandle;
 use tonic::{transport::Channel, Request, Response, Status};
 use user_stat::pb::user_stats::user_stats_client::UserStatsClient;
 
 #[allow(unused)]
 pub struct CrmService {
-    config: AppConfig,
-    user_stats: UserStatsClient<Channel>,
-    notification: NotificationClient<Channel>,
-    metadata: MetadataClient<Channel>,
+    config: Arc<SharedConfig<AppConfig>>,
+    user_stats: Downstream<UserStatsClient<TracedChannel>>,
+    notification: Downstream<NotificationClient<TracedChannel>>,
+    metadata: Downstream<MetadataClient<TracedChannel>>,
+    /// channels of the downstream services, probed by the health service
+    downstreams: Vec<(&'static str, Channel)>,
 }
 
 #[tonic::async_trait]
@@ -26,52 +43,105 @@ impl Crm for CrmService {
         &self,
         request: Request<WelcomeRequest>,
     ) -> Result<Response<WelcomeResponse>, Status> {
-        self.welcome(request.into_inner()).await
+        self.welcome(Error::valid(request)?).await
     }
     /// last visited in x days, and given them something to watch
     async fn recall(
         &self,
-        _request: tonic::Request<RecallRequest>,
-    ) -> Result<tonic::Response<RecallResponse>, tonic::Status> {
-        todo!()
+        request: Request<RecallRequest>,
+    ) -> Result<Response<RecallResponse>, Status> {
+        self.recall(Error::valid(request)?).await
     }
     /// last watched in x days, and user still have unfinished contents
     async fn remind(
         &self,
-        _request: tonic::Request<RemindRequest>,
-    ) -> Result<tonic::Response<RemindResponse>, Status> {
-        todo!()
+        request: Request<RemindRequest>,
+    ) -> Result<Response<RemindResponse>, Status> {
+        self.remind(Error::valid(request)?).await
+    }
+    /// erase the user from every downstream service (GDPR)
+    async fn forget_user(
+        &self,
+        request: Request<ForgetUserRequest>,
+    ) -> Result<Response<ForgetUserResponse>, Status> {
+        self.forget_user(Error::valid(request)?).await
+    }
+    /// collect what every downstream service stores about the user (GDPR)
+    async fn export_user(
+        &self,
+        request: Request<ExportUserRequest>,
+    ) -> Result<Response<ExportUserResponse>, Status> {
+        self.export_user(Error::valid(request)?).await
     }
 }
 
 impl CrmService {
-    pub async fn new(config: AppConfig) -> Result<Self, tonic::transport::Error> {
-        let user_stats = UserStatsClient::connect(config.server.user_stats.clone())
-            .await
-            .unwrap();
-        let notification = NotificationClient::connect(config.server.notification.clone())
-            .await
-            .unwrap();
-        let metadata = MetadataClient::connect(config.server.metadata.clone())
-            .await
-            .unwrap();
+    /// Channels connect on first use, a downstream service being down fails
+    /// the calls that need it with `unavailable` rather than the startup
+    pub fn new(config: AppConfig) -> anyhow::Result<Self> {
+        let tls = config.server.tls.as_ref();
+        let client = &config.client;
+        let user_stats = client.channel(&config.server.extra.user_stats, tls)?;
+        let notification = client.channel(&config.server.extra.notification, tls)?;
+        let metadata = client.channel(&config.server.extra.metadata, tls)?;
         Ok(Self {
-            config,
-            user_stats,
-            notification,
-            metadata,
+            user_stats: Downstream::new(
+                "user-stat",
+                UserStatsClient::with_interceptor(user_stats.clone(), TraceInterceptor),
+                client,
+            ),
+            notification: Downstream::new(
+                "crm-send",
+                NotificationClient::with_interceptor(notification.clone(), TraceInterceptor),
+                client,
+            ),
+            metadata: Downstream::new(
+                "crm-metadata",
+                MetadataClient::with_interceptor(metadata.clone(), TraceInterceptor),
+                client,
+            ),
+            downstreams: vec![
+                ("user-stat", user_stats),
+                ("crm-send", notification),
+                ("crm-metadata", metadata),
+            ],
+            config: Arc::new(SharedConfig::new(config)),
         })
     }
 
+    /// Snapshot of the current config, later reloads leave it untouched
+    pub fn config(&self) -> Arc<AppConfig> {
+        self.config.get()
+    }
+
+    /// Pick up changes to the config file, e.g. the recommend settings
+    pub fn watch_config(&self) -> JoinHandle<()> {
+        self.config.watch(WATCH_INTERVAL)
+    }
+
+    /// Serving only while every downstream service reports serving
+    pub fn health(&self) -> HealthService {
+        let mut builder = HealthService::builder().service(crm_server::SERVICE_NAME);
+        for (name, channel) in &self.downstreams {
+            let channel = channel.clone();
+            builder = builder.probe(*name, move || check_remote(channel.clone()));
+        }
+        builder.build()
+    }
+
     pub fn into_server(self) -> CrmServer<Self> {
         CrmServer::new(self)
     }
 }
 
+crm_core::grpc_server!(CrmServer);
+
 #[cfg(test)]
 mod tests {
     use std::net::SocketAddr;
 
+    use crm_core::Config;
+
     use super::*;
     use crm_client::CrmClient;
     #[tokio::test]
@@ -94,7 +164,7 @@ mod tests {
     async fn start_server() -> SocketAddr {
         let config = AppConfig::load().unwrap();
         let addr = format!("[::1]:{}", config.server.port).parse().unwrap();
-        let service = CrmService::new(config).await.unwrap();
+        let service = CrmService::new(config).unwrap();
         let svc = service.into_server();
         tokio::spawn(async move {
             tonic::transport::Server::builder()
@@ -22,15 +22,21 @@ pub struct WelcomeResponse {
 pub struct RecallRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    /// users who last visited this many days ago
     #[prost(uint32, tag = "2")]
     pub last_visit_interval: u32,
+    /// picked for each user from its history if empty
     #[prost(uint32, repeated, tag = "3")]
     pub content_ids: ::prost::alloc::vec::Vec<u32>,
 }
-#[derive(Clone, Copy, PartialEq, ::prost::Message)]
+#[derive(Clone, PartialEq, ::prost::Message)]
 pub struct RecallResponse {
+    /// unused, kept for the wire format
     #[prost(uint32, tag = "1")]
     pub id: u32,
+    /// id of the RecallRequest
+    #[prost(string, tag = "2")]
+    pub request_id: ::prost::alloc::string::String,
 }
 #[derive(derive_builder::Builder)]
 #[builder(setter(into, strip_option), default)]
@@ -38,10 +44,13 @@ pub struct RecallResponse {
 pub struct RemindRequest {
     #[prost(string, tag = "1")]
     pub id: ::prost::alloc::string::String,
+    /// users who last visited this many days ago, ignored if 0
     #[prost(uint32, tag = "2")]
     pub last_visit_interval: u32,
+    /// users who last watched something this many days ago
     #[prost(uint32, tag = "3")]
     pub last_watched_interval: u32,
+    /// picked for each user among its unfinished contents if empty
     #[prost(uint32, repeated, tag = "4")]
     pub content_ids: ::prost::alloc::vec::Vec<u32>,
 }
@@ -52,6 +61,47 @@ pub struct RemindResponse {
     #[prost(uint32, tag = "2")]
     pub interval: u32,
 }
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ForgetUserRequest {
+    #[prost(string, tag = "1")]
+    pub email: ::prost::alloc::string::String,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ForgetUserResponse {
+    #[prost(string, tag = "1")]
+    pub email: ::prost::alloc::string::String,
+    /// whether user-stat had a row for the user
+    #[prost(bool, tag = "2")]
+    pub user_deleted: bool,
+    #[prost(uint64, tag = "3")]
+    pub events_deleted: u64,
+    #[prost(uint64, tag = "4")]
+    pub deliveries_deleted: u64,
+    /// downstreams the user could not be erased from, e.g. "user-stat", to retry
+    #[prost(string, repeated, tag = "5")]
+    pub failed: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+}
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportUserRequest {
+    #[prost(string, tag = "1")]
+    pub email: ::prost::alloc::string::String,
+}
+/// everything the crm services store about a user
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ExportUserResponse {
+    #[prost(string, tag = "1")]
+    pub email: ::prost::alloc::string::String,
+    #[prost(message, optional, tag = "2")]
+    pub exported_at: ::core::option::Option<::prost_types::Timestamp>,
+    /// unset if user-stat has no row for the user
+    #[prost(message, optional, tag = "3")]
+    pub user: ::core::option::Option<::user_stat::pb::user_stats::UserStat>,
+    #[prost(message, repeated, tag = "4")]
+    pub events: ::prost::alloc::vec::Vec<::user_stat::pb::user_stats::UserEvent>,
+    /// crm-send keeps them in memory, only the ones since its last restart
+    #[prost(message, repeated, tag = "5")]
+    pub deliveries: ::prost::alloc::vec::Vec<::crm_send::pb::send::DeliveryRecord>,
+}
 /// Generated client implementations.
 pub mod crm_client {
     #![allow(
@@ -59,10 +109,10 @@ pub mod crm_client {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
-    use tonic::codegen::http::Uri;
     use tonic::codegen::*;
+    use tonic::codegen::http::Uri;
     #[derive(Debug, Clone)]
     pub struct CrmClient<T> {
         inner: tonic::client::Grpc<T>,
@@ -93,7 +143,10 @@ pub mod crm_client {
             let inner = tonic::client::Grpc::with_origin(inner, origin);
             Self { inner }
         }
-        pub fn with_interceptor<F>(inner: T, interceptor: F) -> CrmClient<InterceptedService<T, F>>
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> CrmClient<InterceptedService<T, F>>
         where
             F: tonic::service::Interceptor,
             T::ResponseBody: Default,
@@ -103,8 +156,9 @@ pub mod crm_client {
                     <T as tonic::client::GrpcService<tonic::body::BoxBody>>::ResponseBody,
                 >,
             >,
-            <T as tonic::codegen::Service<http::Request<tonic::body::BoxBody>>>::Error:
-                Into<StdError> + std::marker::Send + std::marker::Sync,
+            <T as tonic::codegen::Service<
+                http::Request<tonic::body::BoxBody>,
+            >>::Error: Into<StdError> + std::marker::Send + std::marker::Sync,
         {
             CrmClient::new(InterceptedService::new(inner, interceptor))
         }
@@ -143,15 +197,22 @@ pub mod crm_client {
         pub async fn welcome(
             &mut self,
             request: impl tonic::IntoRequest<super::WelcomeRequest>,
-        ) -> std::result::Result<tonic::Response<super::WelcomeResponse>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
-            })?;
+        ) -> std::result::Result<
+            tonic::Response<super::WelcomeResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static("/crm.Crm/Welcome");
             let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("crm.Crm", "Welcome"));
+            req.extensions_mut().insert(GrpcMethod::new("crm.Crm", "Welcome"));
             self.inner.unary(req, path, codec).await
         }
         /// last visited in x days, and given them something to watch
@@ -159,14 +220,18 @@ pub mod crm_client {
             &mut self,
             request: impl tonic::IntoRequest<super::RecallRequest>,
         ) -> std::result::Result<tonic::Response<super::RecallResponse>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
-            })?;
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static("/crm.Crm/Recall");
             let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("crm.Crm", "Recall"));
+            req.extensions_mut().insert(GrpcMethod::new("crm.Crm", "Recall"));
             self.inner.unary(req, path, codec).await
         }
         /// last watched in x days, and user still have unfinished contents
@@ -174,14 +239,62 @@ pub mod crm_client {
             &mut self,
             request: impl tonic::IntoRequest<super::RemindRequest>,
         ) -> std::result::Result<tonic::Response<super::RemindResponse>, tonic::Status> {
-            self.inner.ready().await.map_err(|e| {
-                tonic::Status::unknown(format!("Service was not ready: {}", e.into()))
-            })?;
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
             let codec = tonic::codec::ProstCodec::default();
             let path = http::uri::PathAndQuery::from_static("/crm.Crm/Remind");
             let mut req = request.into_request();
-            req.extensions_mut()
-                .insert(GrpcMethod::new("crm.Crm", "Remind"));
+            req.extensions_mut().insert(GrpcMethod::new("crm.Crm", "Remind"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// erase the user from every downstream service (GDPR)
+        pub async fn forget_user(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ForgetUserRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ForgetUserResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/crm.Crm/ForgetUser");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("crm.Crm", "ForgetUser"));
+            self.inner.unary(req, path, codec).await
+        }
+        /// collect what every downstream service stores about the user (GDPR)
+        pub async fn export_user(
+            &mut self,
+            request: impl tonic::IntoRequest<super::ExportUserRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExportUserResponse>,
+            tonic::Status,
+        > {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::unknown(
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static("/crm.Crm/ExportUser");
+            let mut req = request.into_request();
+            req.extensions_mut().insert(GrpcMethod::new("crm.Crm", "ExportUser"));
             self.inner.unary(req, path, codec).await
         }
     }
@@ -193,7 +306,7 @@ pub mod crm_server {
         dead_code,
         missing_docs,
         clippy::wildcard_imports,
-        clippy::let_unit_value
+        clippy::let_unit_value,
     )]
     use tonic::codegen::*;
     /// Generated trait containing gRPC methods that should be implemented for use with CrmServer.
@@ -214,6 +327,22 @@ pub mod crm_server {
             &self,
             request: tonic::Request<super::RemindRequest>,
         ) -> std::result::Result<tonic::Response<super::RemindResponse>, tonic::Status>;
+        /// erase the user from every downstream service (GDPR)
+        async fn forget_user(
+            &self,
+            request: tonic::Request<super::ForgetUserRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ForgetUserResponse>,
+            tonic::Status,
+        >;
+        /// collect what every downstream service stores about the user (GDPR)
+        async fn export_user(
+            &self,
+            request: tonic::Request<super::ExportUserRequest>,
+        ) -> std::result::Result<
+            tonic::Response<super::ExportUserResponse>,
+            tonic::Status,
+        >;
     }
     #[derive(Debug)]
     pub struct CrmServer<T> {
@@ -236,7 +365,10 @@ pub mod crm_server {
                 max_encoding_message_size: None,
             }
         }
-        pub fn with_interceptor<F>(inner: T, interceptor: F) -> InterceptedService<Self, F>
+        pub fn with_interceptor<F>(
+            inner: T,
+            interceptor: F,
+        ) -> InterceptedService<Self, F>
         where
             F: tonic::service::Interceptor,
         {
@@ -291,15 +423,21 @@ pub mod crm_server {
                 "/crm.Crm/Welcome" => {
                     #[allow(non_camel_case_types)]
                     struct WelcomeSvc<T: Crm>(pub Arc<T>);
-                    impl<T: Crm> tonic::server::UnaryService<super::WelcomeRequest> for WelcomeSvc<T> {
+                    impl<T: Crm> tonic::server::UnaryService<super::WelcomeRequest>
+                    for WelcomeSvc<T> {
                         type Response = super::WelcomeResponse;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
                         fn call(
                             &mut self,
                             request: tonic::Request<super::WelcomeRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { <T as Crm>::welcome(&inner, request).await };
+                            let fut = async move {
+                                <T as Crm>::welcome(&inner, request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -328,15 +466,21 @@ pub mod crm_server {
                 "/crm.Crm/Recall" => {
                     #[allow(non_camel_case_types)]
                     struct RecallSvc<T: Crm>(pub Arc<T>);
-                    impl<T: Crm> tonic::server::UnaryService<super::RecallRequest> for RecallSvc<T> {
+                    impl<T: Crm> tonic::server::UnaryService<super::RecallRequest>
+                    for RecallSvc<T> {
                         type Response = super::RecallResponse;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
                         fn call(
                             &mut self,
                             request: tonic::Request<super::RecallRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { <T as Crm>::recall(&inner, request).await };
+                            let fut = async move {
+                                <T as Crm>::recall(&inner, request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -365,15 +509,21 @@ pub mod crm_server {
                 "/crm.Crm/Remind" => {
                     #[allow(non_camel_case_types)]
                     struct RemindSvc<T: Crm>(pub Arc<T>);
-                    impl<T: Crm> tonic::server::UnaryService<super::RemindRequest> for RemindSvc<T> {
+                    impl<T: Crm> tonic::server::UnaryService<super::RemindRequest>
+                    for RemindSvc<T> {
                         type Response = super::RemindResponse;
-                        type Future = BoxFuture<tonic::Response<Self::Response>, tonic::Status>;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
                         fn call(
                             &mut self,
                             request: tonic::Request<super::RemindRequest>,
                         ) -> Self::Future {
                             let inner = Arc::clone(&self.0);
-                            let fut = async move { <T as Crm>::remind(&inner, request).await };
+                            let fut = async move {
+                                <T as Crm>::remind(&inner, request).await
+                            };
                             Box::pin(fut)
                         }
                     }
@@ -399,19 +549,109 @@ pub mod crm_server {
                     };
                     Box::pin(fut)
                 }
-                _ => Box::pin(async move {
-                    let mut response = http::Response::new(empty_body());
-                    let headers = response.headers_mut();
-                    headers.insert(
-                        tonic::Status::GRPC_STATUS,
-                        (tonic::Code::Unimplemented as i32).into(),
-                    );
-                    headers.insert(
-                        http::header::CONTENT_TYPE,
-                        tonic::metadata::GRPC_CONTENT_TYPE,
-                    );
-                    Ok(response)
-                }),
+                "/crm.Crm/ForgetUser" => {
+                    #[allow(non_camel_case_types)]
+                    struct ForgetUserSvc<T: Crm>(pub Arc<T>);
+                    impl<T: Crm> tonic::server::UnaryService<super::ForgetUserRequest>
+                    for ForgetUserSvc<T> {
+                        type Response = super::ForgetUserResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ForgetUserRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Crm>::forget_user(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ForgetUserSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/crm.Crm/ExportUser" => {
+                    #[allow(non_camel_case_types)]
+                    struct ExportUserSvc<T: Crm>(pub Arc<T>);
+                    impl<T: Crm> tonic::server::UnaryService<super::ExportUserRequest>
+                    for ExportUserSvc<T> {
+                        type Response = super::ExportUserResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::ExportUserRequest>,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                <T as Crm>::export_user(&inner, request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let method = ExportUserSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                _ => {
+                    Box::pin(async move {
+                        let mut response = http::Response::new(empty_body());
+                        let headers = response.headers_mut();
+                        headers
+                            .insert(
+                                tonic::Status::GRPC_STATUS,
+                                (tonic::Code::Unimplemented as i32).into(),
+                            );
+                        headers
+                            .insert(
+                                http::header::CONTENT_TYPE,
+                                tonic::metadata::GRPC_CONTENT_TYPE,
+                            );
+                        Ok(response)
+                    })
+                }
             }
         }
     }
@@ -1,2 +1,7 @@
+#[rustfmt::skip]
 pub mod crm;
-pub use crm::*;
+pub use self::crm::*;
+
+/// Descriptors of the compiled protos, served by the reflection service
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/crm_descriptor.bin"));
@@ -0,0 +1,198 @@
+use std::collections::{HashMap, HashSet};
+
+use crm_metadata::pb::metadata::Content;
+use user_stat::pb::user_stats::UserStat;
+
+/// how much a content type the user watched weighs in the score
+const TYPE_WEIGHT: f64 = 0.3;
+/// how much a publisher the user watched weighs in the score
+const PUBLISHER_WEIGHT: f64 = 0.4;
+/// how much the popularity of the content weighs in the score
+const POPULARITY_WEIGHT: f64 = 0.3;
+/// a finished content says more about the user's taste than a recently watched one
+const FINISHED_WEIGHT: f64 = 2.0;
+const RECENT_WEIGHT: f64 = 1.0;
+
+/// Taste of a user, built from the contents in its `recent_watched` and `finished` history
+#[derive(Debug, Default)]
+pub struct Profile {
+    /// share of the history per content type, sums up to 1
+    types: HashMap<i32, f64>,
+    /// share of the history per publisher
+    publishers: HashMap<u32, f64>,
+    finished: HashSet<u32>,
+}
+
+impl Profile {
+    /// `history` holds the materialized contents of the user's history,
+    /// ids missing from it are ignored
+    pub fn new(user: &UserStat, history: &HashMap<u32, Content>) -> Self {
+        let mut profile = Self {
+            finished: user.finished.iter().copied().collect(),
+            ..Default::default()
+        };
+        let watched = user
+            .recent_watched
+            .iter()
+            .map(|id| (id, RECENT_WEIGHT))
+            .chain(user.finished.iter().map(|id| (id, FINISHED_WEIGHT)));
+        let mut total = 0.0;
+        for (id, weight) in watched {
+            let Some(content) = history.get(id) else {
+                continue;
+            };
+            total += weight;
+            *profile.types.entry(content.content_type).or_default() += weight;
+            for publisher in &content.publishers {
+                *profile.publishers.entry(publisher.id).or_default() += weight;
+            }
+        }
+        if total > 0.0 {
+            profile.types.values_mut().for_each(|v| *v /= total);
+            profile.publishers.values_mut().for_each(|v| *v /= total);
+        }
+        profile
+    }
+
+    /// Ids of the user's history to materialize before building the profile
+    pub fn history_ids(user: &UserStat) -> Vec<u32> {
+        let mut seen = HashSet::new();
+        user.recent_watched
+            .iter()
+            .chain(&user.finished)
+            .copied()
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    /// Score in [0, 1], `max_likes` is the max likes among the candidates
+    fn score(&self, content: &Content, max_likes: u64) -> f64 {
+        let type_affinity = self
+            .types
+            .get(&content.content_type)
+            .copied()
+            .unwrap_or_default();
+        let publisher_affinity = content
+            .publishers
+            .iter()
+            .filter_map(|p| self.publishers.get(&p.id))
+            .fold(0.0, |acc: f64, v| acc.max(*v));
+        TYPE_WEIGHT * type_affinity
+            + PUBLISHER_WEIGHT * publisher_affinity
+            + POPULARITY_WEIGHT * popularity(content, max_likes)
+    }
+
+    /// Best `limit` candidates for the user, finished contents excluded.
+    /// Without history this falls back to the most popular candidates
+    pub fn recommend(&self, candidates: &[Content], limit: usize) -> Vec<Content> {
+        let max_likes = candidates.iter().map(|c| c.likes).max().unwrap_or_default();
+        let mut seen = HashSet::new();
+        let mut scored = candidates
+            .iter()
+            .filter(|c| !self.finished.contains(&c.id) && seen.insert(c.id))
+            .map(|c| (self.score(c, max_likes), c))
+            .collect::<Vec<_>>();
+        scored.sort_by(|(a, c1), (b, c2)| b.total_cmp(a).then(c1.id.cmp(&c2.id)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(_, c)| c.clone())
+            .collect()
+    }
+}
+
+/// Likes on a log scale relative to the most liked candidate, damped by the dislike ratio
+fn popularity(content: &Content, max_likes: u64) -> f64 {
+    if max_likes == 0 {
+        return 0.0;
+    }
+    let likes = content.likes as f64;
+    let reach = (1.0 + likes).ln() / (1.0 + max_likes as f64).ln();
+    let approval = (likes + 1.0) / (likes + content.dislikes as f64 + 2.0);
+    reach * approval
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_metadata::pb::metadata::{ContentType, Publisher};
+
+    use super::*;
+
+    fn content(id: u32, content_type: ContentType, publisher: u32, likes: u64) -> Content {
+        Content {
+            id,
+            content_type: content_type as i32,
+            publishers: vec![Publisher {
+                id: publisher,
+                ..Default::default()
+            }],
+            likes,
+            ..Default::default()
+        }
+    }
+
+    fn library() -> Vec<Content> {
+        vec![
+            content(1, ContentType::Movie, 1, 10),
+            content(2, ContentType::Movie, 1, 20),
+            content(3, ContentType::Short, 2, 1000),
+            content(4, ContentType::Movie, 1, 5),
+            content(5, ContentType::Vlog, 3, 500),
+        ]
+    }
+
+    fn history(user: &UserStat) -> HashMap<u32, Content> {
+        library()
+            .into_iter()
+            .filter(|c| Profile::history_ids(user).contains(&c.id))
+            .map(|c| (c.id, c))
+            .collect()
+    }
+
+    #[test]
+    fn recommend_should_follow_history() {
+        let user = UserStat {
+            recent_watched: vec![2],
+            finished: vec![1],
+            ..Default::default()
+        };
+        let profile = Profile::new(&user, &history(&user));
+        let ids = profile
+            .recommend(&library(), 3)
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        // same type and publisher as the history first, finished one excluded
+        assert_eq!(ids, vec![2, 4, 3]);
+    }
+
+    #[test]
+    fn recommend_without_history_should_rank_by_popularity() {
+        let profile = Profile::new(&UserStat::default(), &HashMap::new());
+        let ids = profile
+            .recommend(&library(), 2)
+            .iter()
+            .map(|c| c.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 5]);
+    }
+
+    #[test]
+    fn recommend_should_skip_duplicates() {
+        let profile = Profile::default();
+        let mut candidates = library();
+        candidates.extend(library());
+        assert_eq!(profile.recommend(&candidates, 10).len(), 5);
+    }
+
+    #[test]
+    fn popularity_should_be_damped_by_dislikes() {
+        let liked = content(1, ContentType::Movie, 1, 100);
+        let disliked = Content {
+            dislikes: 300,
+            ..liked.clone()
+        };
+        assert!(popularity(&liked, 100) > popularity(&disliked, 100));
+        assert_eq!(popularity(&liked, 0), 0.0);
+    }
+}
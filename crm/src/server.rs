@@ -1,22 +1,14 @@
-use crm::{config::AppConfig, CrmService};
-use tonic::transport::Server;
-use tracing::{info, level_filters::LevelFilter};
-use tracing_subscriber::{fmt::Layer, layer::SubscriberExt, util::SubscriberInitExt, Layer as _};
+use crm::{config::AppConfig, pb::FILE_DESCRIPTOR_SET, CrmService};
+use crm_core::Bootstrap;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let layer = Layer::default().with_filter(LevelFilter::INFO);
-    tracing_subscriber::registry().with(layer).init();
-
-    let config = AppConfig::load().unwrap();
-    let addr = format!("[::1]:{}", config.server.port).parse().unwrap();
-    info!("Starting server on {}", addr);
-    let svc = CrmService::new(config).await?.into_server();
-    Server::builder()
-        .add_service(svc)
-        .serve(addr)
+    let (config, server) = Bootstrap::init::<AppConfig>("crm").await?;
+    let svc = CrmService::new(config)?;
+    svc.watch_config();
+    let health = svc.health();
+    server
+        .reflect(FILE_DESCRIPTOR_SET)
+        .serve(svc.into_server(), health)
         .await
-        .unwrap();
-
-    Ok(())
 }
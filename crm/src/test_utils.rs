@@ -0,0 +1,377 @@
+//! In-process stand-ins of the services crm calls: user-stat and crm-metadata
+//! are stubbed with fixed data, crm-send is the real service.
+
+use std::{
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+};
+
+use crm_core::{Config, Upstream};
+use crm_metadata::pb::metadata::{
+    metadata_server::{Metadata, MetadataServer},
+    BatchGetRequest, BatchGetResponse, Content, ContentEvent, CreateContentRequest,
+    CreatePublisherRequest, DeleteContentRequest, DeletePublisherRequest, DeleteResponse,
+    ImportContentsRequest, ImportContentsResponse, IngestResponse as ContentIngestResponse,
+    ListContentsRequest, ListContentsResponse, MaterializeRequest, MaterializeResponse, Publisher,
+    SearchContentsRequest, TrendingContent, TrendingGroup, TrendingRequest, TrendingResponse,
+    UpdateContentRequest,
+};
+use crm_send::{
+    pb::send::{notification_client::NotificationClient, DeliveryRecord, RecipientRequest},
+    NotificationService,
+};
+use tokio::{
+    net::TcpListener,
+    time::{sleep, Duration},
+};
+use tokio_stream::{wrappers::TcpListenerStream, Stream};
+use tonic::{
+    transport::{server::Router, Channel, Server},
+    Request, Response, Status, Streaming,
+};
+use user_stat::pb::user_stats::{
+    user_stats_server::{UserStats, UserStatsServer},
+    BatchGetUsersRequest, BatchGetUsersResponse, BatchUpsertUsersRequest, BatchUpsertUsersResponse,
+    DeleteUserRequest, DeleteUserResponse, GetUserRequest, IngestResponse, QueryRequest,
+    RawQueryRequest, RebuildRequest, RebuildResponse, User, UserEvent, UserExport, UserStat,
+};
+
+use crate::{config::AppConfig, CrmService};
+
+type UserStream = Pin<Box<dyn Stream<Item = Result<User, Status>> + Send>>;
+type MaterializeStream = Pin<Box<dyn Stream<Item = Result<MaterializeResponse, Status>> + Send>>;
+
+/// Methods called on the stubs, in order
+#[derive(Debug, Clone, Default)]
+pub struct Calls(Arc<Mutex<Vec<&'static str>>>);
+
+impl Calls {
+    fn push(&self, method: &'static str) {
+        self.0.lock().unwrap().push(method);
+    }
+
+    pub fn count(&self, method: &str) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|m| **m == method)
+            .count()
+    }
+}
+
+/// user-stat knowing `users`, every one of them matching any query
+#[derive(Debug, Clone, Default)]
+pub struct UserStatsStub {
+    pub users: Vec<UserStat>,
+    pub events: Vec<UserEvent>,
+    /// methods answering with an internal error
+    pub failing: Vec<&'static str>,
+    pub calls: Calls,
+}
+
+/// crm-metadata knowing `contents`, the ones of `trending` trending in that order
+#[derive(Debug, Clone, Default)]
+pub struct MetadataStub {
+    pub contents: Vec<Content>,
+    pub trending: Vec<u32>,
+    pub calls: Calls,
+}
+
+/// A `CrmService` calling the stubs, and a client of the crm-send it sends through
+pub struct Stubbed {
+    pub crm: CrmService,
+    pub notification: NotificationClient<Channel>,
+}
+
+impl Stubbed {
+    pub async fn start(user_stats: UserStatsStub, metadata: MetadataStub) -> Self {
+        let send_config = serde_yaml::from_str(include_str!("../../crm-send/send.yml")).unwrap();
+        let notification = NotificationService::new(send_config).await.into_server();
+        let notification = serve(Server::builder().add_service(notification)).await;
+
+        let mut config = AppConfig::load().unwrap();
+        let services = &mut config.server.extra;
+        let user_stats = Server::builder().add_service(UserStatsServer::new(user_stats));
+        services.user_stats = Upstream::Url(serve(user_stats).await);
+        let metadata = Server::builder().add_service(MetadataServer::new(metadata));
+        services.metadata = Upstream::Url(serve(metadata).await);
+        services.notification = Upstream::Url(notification.clone());
+        let crm = CrmService::new(config).unwrap();
+        let notification = NotificationClient::connect(notification).await.unwrap();
+        Self { crm, notification }
+    }
+
+    /// Deliveries crm-send filed under `user`, waiting for at least `min`
+    /// of them since crm doesn't wait for the messages to go out
+    pub async fn deliveries(&self, user: &str, min: usize) -> Vec<DeliveryRecord> {
+        for _ in 0..50 {
+            let records = self
+                .notification
+                .clone()
+                .export_recipient(RecipientRequest {
+                    recipient: user.to_string(),
+                })
+                .await
+                .unwrap()
+                .into_inner()
+                .records;
+            if records.len() >= min {
+                return records;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        panic!("less than {} deliveries for {}", min, user);
+    }
+}
+
+/// Serve `router` on a free local port, its url returned
+async fn serve(router: Router) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr: SocketAddr = listener.local_addr().unwrap();
+    tokio::spawn(router.serve_with_incoming(TcpListenerStream::new(listener)));
+    format!("http://{}", addr)
+}
+
+pub fn user(email: &str) -> UserStat {
+    UserStat {
+        email: email.to_string(),
+        name: email.to_string(),
+        ..Default::default()
+    }
+}
+
+pub fn content(id: u32, likes: u64) -> Content {
+    Content {
+        id,
+        name: format!("content {}", id),
+        likes,
+        ..Default::default()
+    }
+}
+
+#[tonic::async_trait]
+impl UserStats for UserStatsStub {
+    type QueryStream = UserStream;
+    async fn query(&self, _: Request<QueryRequest>) -> Result<Response<UserStream>, Status> {
+        self.calls.push("query");
+        let users = self
+            .users
+            .iter()
+            .map(|u| User {
+                email: u.email.clone(),
+                name: u.name.clone(),
+            })
+            .collect::<Vec<_>>();
+        let users = tokio_stream::iter(users.into_iter().map(Ok));
+        Ok(Response::new(Box::pin(users)))
+    }
+
+    type RawQueryStream = UserStream;
+    async fn raw_query(&self, _: Request<RawQueryRequest>) -> Result<Response<UserStream>, Status> {
+        Err(Status::unimplemented("raw_query"))
+    }
+
+    async fn ingest(
+        &self,
+        _: Request<Streaming<UserEvent>>,
+    ) -> Result<Response<IngestResponse>, Status> {
+        Err(Status::unimplemented("ingest"))
+    }
+
+    async fn rebuild(
+        &self,
+        _: Request<RebuildRequest>,
+    ) -> Result<Response<RebuildResponse>, Status> {
+        Err(Status::unimplemented("rebuild"))
+    }
+
+    async fn get_user(&self, _: Request<GetUserRequest>) -> Result<Response<UserStat>, Status> {
+        self.calls.push("get_user");
+        Err(Status::unimplemented("get_user"))
+    }
+
+    async fn batch_get_users(
+        &self,
+        request: Request<BatchGetUsersRequest>,
+    ) -> Result<Response<BatchGetUsersResponse>, Status> {
+        self.calls.push("batch_get_users");
+        let emails = request.into_inner().emails;
+        let users = self
+            .users
+            .iter()
+            .filter(|u| emails.contains(&u.email))
+            .cloned()
+            .collect();
+        Ok(Response::new(BatchGetUsersResponse { users }))
+    }
+
+    async fn upsert_user(&self, _: Request<UserStat>) -> Result<Response<UserStat>, Status> {
+        Err(Status::unimplemented("upsert_user"))
+    }
+
+    async fn delete_user(
+        &self,
+        request: Request<DeleteUserRequest>,
+    ) -> Result<Response<DeleteUserResponse>, Status> {
+        self.calls.push("delete_user");
+        if self.failing.contains(&"delete_user") {
+            return Err(Status::internal("delete_user failed"));
+        }
+        let email = request.into_inner().email;
+        Ok(Response::new(DeleteUserResponse {
+            deleted: self.users.iter().any(|u| u.email == email),
+            events: self.events.iter().filter(|e| e.email == email).count() as u64,
+        }))
+    }
+
+    async fn export_user(
+        &self,
+        request: Request<GetUserRequest>,
+    ) -> Result<Response<UserExport>, Status> {
+        self.calls.push("export_user");
+        let email = request.into_inner().email;
+        Ok(Response::new(UserExport {
+            user: self.users.iter().find(|u| u.email == email).cloned(),
+            events: self
+                .events
+                .iter()
+                .filter(|e| e.email == email)
+                .cloned()
+                .collect(),
+        }))
+    }
+
+    async fn batch_upsert_users(
+        &self,
+        _: Request<BatchUpsertUsersRequest>,
+    ) -> Result<Response<BatchUpsertUsersResponse>, Status> {
+        Err(Status::unimplemented("batch_upsert_users"))
+    }
+}
+
+#[tonic::async_trait]
+impl Metadata for MetadataStub {
+    type MaterializeStream = MaterializeStream;
+    async fn materialize(
+        &self,
+        _: Request<Streaming<MaterializeRequest>>,
+    ) -> Result<Response<MaterializeStream>, Status> {
+        Err(Status::unimplemented("materialize"))
+    }
+
+    async fn batch_get(
+        &self,
+        request: Request<BatchGetRequest>,
+    ) -> Result<Response<BatchGetResponse>, Status> {
+        self.calls.push("batch_get");
+        let ids = request.into_inner().ids;
+        let contents = ids
+            .iter()
+            .filter_map(|id| self.contents.iter().find(|c| c.id == *id).cloned())
+            .collect();
+        Ok(Response::new(BatchGetResponse {
+            contents,
+            ..Default::default()
+        }))
+    }
+
+    async fn list_contents(
+        &self,
+        _: Request<ListContentsRequest>,
+    ) -> Result<Response<ListContentsResponse>, Status> {
+        self.calls.push("list_contents");
+        let mut contents = self.contents.clone();
+        contents.sort_by_key(|c| std::cmp::Reverse(c.likes));
+        Ok(Response::new(ListContentsResponse {
+            contents,
+            ..Default::default()
+        }))
+    }
+
+    async fn search_contents(
+        &self,
+        _: Request<SearchContentsRequest>,
+    ) -> Result<Response<ListContentsResponse>, Status> {
+        Err(Status::unimplemented("search_contents"))
+    }
+
+    async fn create_content(
+        &self,
+        _: Request<CreateContentRequest>,
+    ) -> Result<Response<Content>, Status> {
+        Err(Status::unimplemented("create_content"))
+    }
+
+    async fn update_content(
+        &self,
+        _: Request<UpdateContentRequest>,
+    ) -> Result<Response<Content>, Status> {
+        Err(Status::unimplemented("update_content"))
+    }
+
+    async fn delete_content(
+        &self,
+        _: Request<DeleteContentRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        Err(Status::unimplemented("delete_content"))
+    }
+
+    async fn create_publisher(
+        &self,
+        _: Request<CreatePublisherRequest>,
+    ) -> Result<Response<Publisher>, Status> {
+        Err(Status::unimplemented("create_publisher"))
+    }
+
+    async fn update_publisher(&self, _: Request<Publisher>) -> Result<Response<Publisher>, Status> {
+        Err(Status::unimplemented("update_publisher"))
+    }
+
+    async fn delete_publisher(
+        &self,
+        _: Request<DeletePublisherRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        Err(Status::unimplemented("delete_publisher"))
+    }
+
+    async fn import_contents(
+        &self,
+        _: Request<ImportContentsRequest>,
+    ) -> Result<Response<ImportContentsResponse>, Status> {
+        Err(Status::unimplemented("import_contents"))
+    }
+
+    async fn ingest(
+        &self,
+        _: Request<Streaming<ContentEvent>>,
+    ) -> Result<Response<ContentIngestResponse>, Status> {
+        Err(Status::unimplemented("ingest"))
+    }
+
+    async fn trending(
+        &self,
+        _: Request<TrendingRequest>,
+    ) -> Result<Response<TrendingResponse>, Status> {
+        self.calls.push("trending");
+        let contents = self
+            .trending
+            .iter()
+            .filter_map(|id| self.contents.iter().find(|c| c.id == *id).cloned())
+            .map(|content| TrendingContent {
+                content: Some(content),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        let groups = if contents.is_empty() {
+            vec![]
+        } else {
+            vec![TrendingGroup {
+                contents,
+                ..Default::default()
+            }]
+        };
+        Ok(Response::new(TrendingResponse { groups }))
+    }
+}
@@ -0,0 +1,98 @@
+use crm_core::{Validate, Violations};
+
+use crate::pb::{
+    ExportUserRequest, ForgetUserRequest, RecallRequest, RemindRequest, WelcomeRequest,
+};
+
+impl Validate for WelcomeRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("id", &self.id);
+        v.positive("interval", self.interval);
+        content_ids(v, &self.content_ids);
+    }
+}
+
+impl Validate for RecallRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("id", &self.id);
+        v.positive("last_visit_interval", self.last_visit_interval);
+        content_ids(v, &self.content_ids);
+    }
+}
+
+impl Validate for RemindRequest {
+    /// `last_visit_interval` is optional, 0 doesn't filter on the last visit
+    fn validate(&self, v: &mut Violations) {
+        v.required("id", &self.id);
+        v.positive("last_watched_interval", self.last_watched_interval);
+        content_ids(v, &self.content_ids);
+    }
+}
+
+impl Validate for ForgetUserRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+    }
+}
+
+impl Validate for ExportUserRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+    }
+}
+
+/// Ids of the contents to send, looked up in crm-metadata
+fn content_ids(v: &mut Violations, ids: &[u32]) {
+    for (i, id) in ids.iter().enumerate() {
+        let field = format!("content_ids[{}]", i);
+        v.positive(&field, *id);
+        v.db_id(&field, *id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_core::ErrorDetails;
+
+    use super::*;
+
+    fn fields<T: Validate>(message: T) -> Vec<String> {
+        let status = message.validated("crm").err().unwrap();
+        let details = ErrorDetails::from_status(&status);
+        details.fields().into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn welcome_request_should_be_validated() {
+        let req = WelcomeRequest {
+            id: "1".to_string(),
+            interval: 100,
+            content_ids: vec![2, 3],
+        };
+        assert!(req.is_valid());
+        let req = WelcomeRequest {
+            id: "".to_string(),
+            interval: 0,
+            content_ids: vec![2, 0],
+        };
+        assert_eq!(fields(req), vec!["id", "interval", "content_ids[1]"]);
+    }
+
+    #[test]
+    fn remind_request_should_have_a_watched_interval() {
+        let req = RemindRequest {
+            id: "1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(fields(req.clone()), vec!["last_watched_interval"]);
+        let req = RemindRequest {
+            last_watched_interval: 10,
+            ..req
+        };
+        assert!(req.is_valid());
+        let req = ForgetUserRequest {
+            email: "alice".to_string(),
+        };
+        assert_eq!(fields(req), vec!["email"]);
+    }
+}
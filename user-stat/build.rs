@@ -1,7 +1,8 @@
 use proto_builder_trait::tonic::BuilderAttributes;
-use std::fs;
+use std::{env, fs, path::PathBuf};
 fn main() -> anyhow::Result<()> {
     fs::create_dir_all("src/pb")?;
+    let descriptor = PathBuf::from(env::var("OUT_DIR")?).join("user_stat_descriptor.bin");
     let builder = tonic_build::configure();
 
     // builder.out_dir("src/pb").compile(
@@ -14,6 +15,7 @@ fn main() -> anyhow::Result<()> {
 
     builder
         .out_dir("src/pb")
+        .file_descriptor_set_path(&descriptor)
         .with_serde(
             &["User"],
             true,
@@ -27,11 +29,18 @@ fn main() -> anyhow::Result<()> {
                 "RawQueryRequest",
                 "TimeQuery",
                 "IdQuery",
+                "UserStat",
             ],
             None,
         )
         .with_field_attributes(
-            &["User.email", "User.name", "RawQueryRequest.Query"],
+            &[
+                "User.email",
+                "User.name",
+                "UserStat.email",
+                "UserStat.name",
+                "RawQueryRequest.Query",
+            ],
             &[r#"#[builder(setter(into))]"#],
         )
         .with_field_attributes(
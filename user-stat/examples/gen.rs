@@ -5,6 +5,7 @@ use std::{
 
 use anyhow::Result;
 use chrono::{DateTime, Days, Utc};
+use crm_core::ToTimestamp;
 use fake::{
     faker::{chrono::en::DateTimeBetween, internet::en::SafeEmail, name::zh_cn::Name},
     Dummy, Fake, Faker,
@@ -12,6 +13,7 @@ use fake::{
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgHasArrayType, PgPool};
+use user_stat::{abi::upsert_users, pb::user_stats as pb};
 
 // generate 10000 users and run them in a tx, repeat 500 times
 
@@ -127,82 +129,41 @@ async fn bulk_insert(users: HashSet<UserStat>, pool: PgPool) -> Result<()> {
 }
 
 async fn efficient_bulk_insert(users: HashSet<UserStat>, pool: PgPool) -> Result<()> {
-    fn to_string_list(list: Vec<Vec<i32>>) -> Vec<String> {
-        let mut ret = vec![];
-        for vec in list {
-            let s = vec
-                .iter()
-                .map(|v| v.to_string())
-                .collect::<Vec<_>>()
-                .join(",");
-            ret.push(format!("{{{}}}", s));
-        }
-        ret
-    }
     let mut tx = pool.begin().await?;
-    let emails = users.iter().map(|u| u.email.clone()).collect::<Vec<_>>();
-    let names = users.iter().map(|u| u.name.clone()).collect::<Vec<_>>();
-    let genders = users.iter().map(|u| u.gender.clone()).collect::<Vec<_>>();
-    let created_ats = users.iter().map(|u| u.created_at).collect::<Vec<_>>();
-    let last_visited_ats = users.iter().map(|u| u.last_visited_at).collect::<Vec<_>>();
-    let last_watched_ats = users.iter().map(|u| u.last_watched_at).collect::<Vec<_>>();
-    let recent_watcheds = users
-        .iter()
-        .map(|u| u.recent_watched.clone())
-        .collect::<Vec<_>>();
-    let viewed_but_not_starteds = users
-        .iter()
-        .map(|u| u.viewed_but_not_started.clone())
-        .collect::<Vec<_>>();
-    let started_but_not_finisheds = users
-        .iter()
-        .map(|u| u.started_but_not_finished.clone())
-        .collect::<Vec<_>>();
-    let finisheds = users.iter().map(|u| u.finished.clone()).collect::<Vec<_>>();
-    let last_email_notifications = users
-        .iter()
-        .map(|u| u.last_email_notification)
-        .collect::<Vec<_>>();
-    let last_in_app_notifications = users
-        .iter()
-        .map(|u| u.last_in_app_notification)
-        .collect::<Vec<_>>();
-    let last_sms_notifications = users
-        .iter()
-        .map(|u| u.last_sms_notification)
-        .collect::<Vec<_>>();
-
-    let recent_watcheds = to_string_list(recent_watcheds);
-    let viewed_but_not_starteds = to_string_list(viewed_but_not_starteds);
-    let started_but_not_finisheds = to_string_list(started_but_not_finisheds);
-    let finisheds = to_string_list(finisheds);
-    let affect_rows = sqlx::query(
-        r#"
-        INSERT INTO user_stats(email,name,gender,created_at,last_visited_at,last_watched_at,recent_watched
-        ,viewed_but_not_started,started_but_not_finished,finished,last_email_notification,last_in_app_notification,last_sms_notification
-        )select unnest($1::text[]),unnest($2::text[]),unnest($3::gender[]),
-        unnest($4::timestamptz[]),unnest($5::timestamptz[]),unnest($6::timestamptz[]),unnest($7::text[])::int[],
-        unnest($8::text[])::int[],unnest($9::text[])::int[],unnest($10::text[])::int[],unnest($11::timestamptz[]),unnest($12::timestamptz[]),unnest($13::timestamptz[])
-        "#).bind(emails)
-        .bind(names)
-        .bind(genders)
-        .bind(created_ats)
-        .bind(last_visited_ats)
-        .bind(last_watched_ats)
-        .bind(recent_watcheds)
-        .bind(viewed_but_not_starteds)
-        .bind(started_but_not_finisheds)
-        .bind(finisheds)
-        .bind(last_email_notifications)
-        .bind(last_in_app_notifications)
-        .bind(last_sms_notifications)
-        .execute(&mut *tx).await?.rows_affected()
-        ;
+    let affect_rows = upsert_users(&mut *tx, users.into_iter().map(Into::into)).await?;
     tx.commit().await?;
     println!("{} rows affected", affect_rows);
     Ok(())
 }
 
+impl From<UserStat> for pb::UserStat {
+    fn from(user: UserStat) -> Self {
+        fn to_ids(ids: Vec<i32>) -> Vec<u32> {
+            ids.into_iter().map(|id| id as u32).collect()
+        }
+        let gender = match user.gender {
+            Gender::Female => pb::Gender::Female,
+            Gender::Male => pb::Gender::Male,
+            Gender::Unknown => pb::Gender::Unknown,
+        };
+        Self {
+            email: user.email,
+            name: user.name,
+            gender: gender as i32,
+            created_at: Some(user.created_at.to_timestamp()),
+            last_visited_at: Some(user.last_visited_at.to_timestamp()),
+            last_watched_at: Some(user.last_watched_at.to_timestamp()),
+            recent_watched: to_ids(user.recent_watched),
+            viewed_but_not_started: to_ids(user.viewed_but_not_started),
+            started_but_not_finished: to_ids(user.started_but_not_finished),
+            finished: to_ids(user.finished),
+            last_email_notification: Some(user.last_email_notification.to_timestamp()),
+            last_in_app_notification: Some(user.last_in_app_notification.to_timestamp()),
+            last_sms_notification: Some(user.last_sms_notification.to_timestamp()),
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     for i in 1..=2 {
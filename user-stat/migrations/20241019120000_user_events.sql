@@ -0,0 +1,31 @@
+-- Add migration script here
+CREATE TYPE event_type AS ENUM(
+  'visit',
+  'view',
+  'start',
+  'finish'
+);
+
+-- append-only log of user activity, user_stats is derived from it
+CREATE TABLE user_events(
+  id bigserial,
+  email varchar(128) NOT NULL,
+  event_type event_type NOT NULL,
+  content_id int,
+  created_at timestamptz NOT NULL,
+  PRIMARY KEY (id, created_at)
+)
+PARTITION BY RANGE (created_at);
+
+CREATE INDEX user_events_email_created_at_idx ON user_events(email, created_at);
+
+-- create the partition holding the events of the given (UTC) day if it does not exist
+CREATE OR REPLACE FUNCTION create_user_events_partition(day date)
+  RETURNS void
+  AS $$
+BEGIN
+  EXECUTE format('CREATE TABLE IF NOT EXISTS %I PARTITION OF user_events FOR VALUES FROM (%L) TO (%L)',
+    'user_events_' || to_char(day, 'YYYYMMDD'), day::text || ' 00:00:00+00',(day + 1)::text || ' 00:00:00+00');
+END;
+$$
+LANGUAGE plpgsql;
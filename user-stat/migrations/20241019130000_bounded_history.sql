@@ -0,0 +1,12 @@
+-- Add migration script here
+-- move id to the front of arr, dropping its older occurrences, and keep at most max_len ids
+CREATE OR REPLACE FUNCTION push_history(arr int[], id int, max_len int)
+  RETURNS int[]
+  AS $$
+  SELECT
+    (ARRAY[id] || array_remove(COALESCE(arr, '{}'), id))[1:max_len]
+$$
+LANGUAGE sql
+IMMUTABLE;
+
+CREATE INDEX user_stats_finished_idx ON user_stats USING GIN(finished);
@@ -0,0 +1,396 @@
+use std::{
+    collections::BTreeSet,
+    ops::RangeInclusive,
+    sync::{Arc, LazyLock},
+};
+
+use chrono::{DateTime, Utc};
+use crm_core::{
+    batch::batches,
+    metrics::{self, Counter},
+    ToUtc, Validate,
+};
+use sqlx::{Postgres, Transaction};
+use tokio_stream::{Stream, StreamExt};
+use tonic::{Response, Status};
+
+use super::sql;
+use crate::{
+    error::db,
+    pb::user_stats::{EventType, IngestResponse, UserEvent},
+    Error, ServiceResult, UserStatsService,
+};
+
+static INGESTED: LazyLock<Arc<Counter>> = LazyLock::new(|| {
+    metrics::counter(
+        "user_stat_ingested_events_total",
+        "User events ingested, by accepted or rejected as malformed",
+        &["result"],
+    )
+});
+
+// every statement takes ($1 email, $2 event time, $3 content id, $4 max history length)
+// and inserts the user with an empty name if it does not exist yet.
+// id arrays are kept most-recent-first, deduped and capped by push_history()
+const VISIT_SQL: &str = r#"
+    INSERT INTO user_stats(email, name, last_visited_at)
+    VALUES ($1, '', $2)
+    ON CONFLICT (email) DO UPDATE SET
+      last_visited_at = GREATEST(user_stats.last_visited_at, EXCLUDED.last_visited_at)
+    "#;
+
+const VIEW_SQL: &str = r#"
+    INSERT INTO user_stats(email, name, last_visited_at, viewed_but_not_started)
+    VALUES ($1, '', $2, ARRAY[$3]::int[])
+    ON CONFLICT (email) DO UPDATE SET
+      last_visited_at = GREATEST(user_stats.last_visited_at, EXCLUDED.last_visited_at),
+      viewed_but_not_started = CASE
+        WHEN $3 = ANY(user_stats.started_but_not_finished) OR $3 = ANY(user_stats.finished)
+        THEN user_stats.viewed_but_not_started
+        ELSE push_history(user_stats.viewed_but_not_started, $3, $4)
+      END
+    "#;
+
+const START_SQL: &str = r#"
+    INSERT INTO user_stats(email, name, last_visited_at, last_watched_at, recent_watched,
+      started_but_not_finished)
+    VALUES ($1, '', $2, $2, ARRAY[$3]::int[], ARRAY[$3]::int[])
+    ON CONFLICT (email) DO UPDATE SET
+      last_visited_at = GREATEST(user_stats.last_visited_at, EXCLUDED.last_visited_at),
+      last_watched_at = GREATEST(user_stats.last_watched_at, EXCLUDED.last_watched_at),
+      recent_watched = push_history(user_stats.recent_watched, $3, $4),
+      viewed_but_not_started = array_remove(user_stats.viewed_but_not_started, $3),
+      started_but_not_finished = CASE
+        WHEN $3 = ANY(user_stats.finished) THEN user_stats.started_but_not_finished
+        ELSE push_history(user_stats.started_but_not_finished, $3, $4)
+      END
+    "#;
+
+const FINISH_SQL: &str = r#"
+    INSERT INTO user_stats(email, name, last_visited_at, last_watched_at, recent_watched, finished)
+    VALUES ($1, '', $2, $2, ARRAY[$3]::int[], ARRAY[$3]::int[])
+    ON CONFLICT (email) DO UPDATE SET
+      last_visited_at = GREATEST(user_stats.last_visited_at, EXCLUDED.last_visited_at),
+      last_watched_at = GREATEST(user_stats.last_watched_at, EXCLUDED.last_watched_at),
+      recent_watched = push_history(user_stats.recent_watched, $3, $4),
+      viewed_but_not_started = array_remove(user_stats.viewed_but_not_started, $3),
+      started_but_not_finished = array_remove(user_stats.started_but_not_finished, $3),
+      finished = push_history(user_stats.finished, $3, $4)
+    "#;
+
+const INSERT_EVENT_SQL: &str = r#"
+    INSERT INTO user_events(email, event_type, content_id, created_at)
+    VALUES ($1, $2::event_type, $3, $4)
+    "#;
+
+/// A validated event, ready to be written to the db
+pub(super) struct Event {
+    email: String,
+    event_type: EventType,
+    content_id: i32,
+    timestamp: DateTime<Utc>,
+}
+
+impl UserStatsService {
+    /// Consume a stream of user events, append them to `user_events` and fold them into `user_stats`.
+    /// Events are applied in batches of `ingest.batch_size`, each batch in its own transaction.
+    /// Events dated outside of the `ingest` window are rejected, so that a client cannot
+    /// make us create a partition for any day.
+    pub async fn ingest(
+        &self,
+        stream: impl Stream<Item = Result<UserEvent, Status>> + Unpin + Send,
+    ) -> ServiceResult<IngestResponse> {
+        let ingest = &self.config.ingest;
+        let max_history = ingest.max_history;
+        let mut ret = IngestResponse::default();
+        let mut rejected = 0;
+        let events = stream.filter_map(|event| {
+            let window = ingest.window(Utc::now());
+            let event = event
+                .map(|event| Event::try_from_pb(event, &window))
+                .transpose();
+            if event.is_none() {
+                INGESTED.inc(&["rejected"]);
+                rejected += 1;
+            }
+            event
+        });
+
+        let mut batches = batches(events, ingest.batch_size);
+        while let Some(batch) = batches.next().await {
+            ret.accepted += self.apply_batch(&batch?, max_history).await?;
+        }
+        ret.rejected = rejected;
+
+        Ok(Response::new(ret))
+    }
+
+    async fn apply_batch(&self, events: &[Event], max_history: i32) -> Result<u64, Error> {
+        let query = self.apply_events(events, max_history);
+        let accepted = sql("ingest_batch", query).await?;
+        INGESTED.inc_by(&["accepted"], accepted);
+        Ok(accepted)
+    }
+
+    async fn apply_events(&self, events: &[Event], max_history: i32) -> Result<u64, Error> {
+        let failed = db("ingest events");
+        let mut tx = self.pool.begin().await.map_err(failed)?;
+        let days: BTreeSet<_> = events.iter().map(|e| e.timestamp.date_naive()).collect();
+        for day in days {
+            sqlx::query("SELECT create_user_events_partition($1)")
+                .bind(day)
+                .execute(&mut *tx)
+                .await
+                .map_err(failed)?;
+        }
+        for event in events {
+            // update the aggregates first, so that a concurrent rebuild holding the row lock
+            // either sees this event or runs before it
+            apply_event(&mut tx, event, max_history)
+                .await
+                .map_err(failed)?;
+            record_event(&mut tx, event).await.map_err(failed)?;
+        }
+        tx.commit().await.map_err(failed)?;
+        Ok(events.len() as u64)
+    }
+}
+
+/// Fold a single event into the user's row of `user_stats`
+pub(super) async fn apply_event(
+    tx: &mut Transaction<'_, Postgres>,
+    event: &Event,
+    max_history: i32,
+) -> sqlx::Result<()> {
+    let query = sqlx::query(event.sql())
+        .bind(&event.email)
+        .bind(event.timestamp);
+    // VISIT_SQL does not reference $3 and $4
+    let query = match event.event_type {
+        EventType::Visit => query,
+        _ => query.bind(event.content_id).bind(max_history),
+    };
+    query.execute(&mut **tx).await?;
+    Ok(())
+}
+
+async fn record_event(tx: &mut Transaction<'_, Postgres>, event: &Event) -> sqlx::Result<()> {
+    let content_id = match event.event_type {
+        EventType::Visit => None,
+        _ => Some(event.content_id),
+    };
+    sqlx::query(INSERT_EVENT_SQL)
+        .bind(&event.email)
+        .bind(event.db_name())
+        .bind(content_id)
+        .bind(event.timestamp)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+impl Event {
+    /// Build an event from a row of `user_events`
+    pub(super) fn from_row(
+        email: String,
+        event_type: &str,
+        content_id: Option<i32>,
+        timestamp: DateTime<Utc>,
+    ) -> Option<Self> {
+        Some(Self {
+            email,
+            event_type: EventType::from_db_name(event_type)?,
+            content_id: content_id.unwrap_or_default(),
+            timestamp,
+        })
+    }
+
+    /// Streamed events are not rejected as a whole request, the invalid ones
+    /// and the ones dated outside of `window` are only counted
+    fn try_from_pb(event: UserEvent, window: &RangeInclusive<DateTime<Utc>>) -> Option<Self> {
+        if !event.is_valid() {
+            return None;
+        }
+        let event_type = EventType::try_from(event.event_type).ok()?;
+        let content_id = i32::try_from(event.content_id).ok()?;
+        let timestamp = match event.timestamp.as_ref() {
+            Some(timestamp) => timestamp.to_utc()?,
+            None => Utc::now(),
+        };
+        if !window.contains(&timestamp) {
+            return None;
+        }
+        Some(Self {
+            email: event.email,
+            event_type,
+            content_id,
+            timestamp,
+        })
+    }
+
+    fn sql(&self) -> &'static str {
+        match self.event_type {
+            EventType::Visit | EventType::Unspecified => VISIT_SQL,
+            EventType::View => VIEW_SQL,
+            EventType::Start => START_SQL,
+            EventType::Finish => FINISH_SQL,
+        }
+    }
+
+    /// Name of the event in the `event_type` db enum
+    fn db_name(&self) -> &'static str {
+        match self.event_type {
+            EventType::Visit | EventType::Unspecified => "visit",
+            EventType::View => "view",
+            EventType::Start => "start",
+            EventType::Finish => "finish",
+        }
+    }
+}
+
+impl EventType {
+    /// Parse a value of the `event_type` db enum
+    pub(super) fn from_db_name(name: &str) -> Option<Self> {
+        match name {
+            "visit" => Some(EventType::Visit),
+            "view" => Some(EventType::View),
+            "start" => Some(EventType::Start),
+            "finish" => Some(EventType::Finish),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_core::ToTimestamp;
+
+    use super::*;
+    use crate::test_utils::days_ago;
+
+    #[derive(Debug, sqlx::FromRow)]
+    struct Stat {
+        recent_watched: Option<Vec<i32>>,
+        viewed_but_not_started: Option<Vec<i32>>,
+        started_but_not_finished: Option<Vec<i32>>,
+        finished: Option<Vec<i32>>,
+    }
+
+    fn event(email: &str, event_type: EventType, content_id: u32) -> UserEvent {
+        UserEvent {
+            email: email.to_string(),
+            event_type: event_type as i32,
+            content_id,
+            timestamp: Some(days_ago(0)),
+        }
+    }
+
+    async fn get_stat(svc: &UserStatsService, email: &str) -> Stat {
+        sqlx::query_as(
+            "select recent_watched, viewed_but_not_started, started_but_not_finished, finished from user_stats where email = $1",
+        )
+        .bind(email)
+        .fetch_one(&svc.pool)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn ingest_should_move_content_between_states() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "new.user@example.com";
+        let events = vec![
+            event(email, EventType::Visit, 0),
+            event(email, EventType::View, 1),
+            event(email, EventType::View, 2),
+            event(email, EventType::View, 3),
+            event(email, EventType::Start, 1),
+            event(email, EventType::Start, 2),
+            event(email, EventType::Finish, 1),
+            // finished content should not go back to viewed
+            event(email, EventType::View, 1),
+        ];
+        let res = svc
+            .ingest(tokio_stream::iter(events.into_iter().map(Ok)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.accepted, 8);
+        assert_eq!(res.rejected, 0);
+
+        let stat = get_stat(&svc, email).await;
+        assert_eq!(stat.recent_watched, Some(vec![1, 2]));
+        assert_eq!(stat.viewed_but_not_started, Some(vec![3]));
+        assert_eq!(stat.started_but_not_finished, Some(vec![2]));
+        assert_eq!(stat.finished, Some(vec![1]));
+    }
+
+    #[tokio::test]
+    async fn ingest_should_reject_invalid_events() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let events = vec![
+            event("", EventType::Visit, 0),
+            event("a@example.com", EventType::Unspecified, 1),
+            event("a@example.com", EventType::View, 0),
+            event("brenna.elx4os2u@example.net", EventType::Finish, 252790),
+        ];
+        let res = svc
+            .ingest(tokio_stream::iter(events.into_iter().map(Ok)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.accepted, 1);
+        assert_eq!(res.rejected, 3);
+
+        let stat = get_stat(&svc, "brenna.elx4os2u@example.net").await;
+        assert!(stat.finished.unwrap().contains(&252790));
+    }
+
+    #[tokio::test]
+    async fn ingest_should_reject_events_outside_of_the_window() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "new.user@example.com";
+        let too_old = days_ago(svc.config.ingest.max_event_age_days as i64 + 1);
+        let too_new = (Utc::now() + chrono::Duration::days(1)).to_timestamp();
+        let events = [too_old, too_new, days_ago(0)].map(|timestamp| UserEvent {
+            timestamp: Some(timestamp),
+            ..event(email, EventType::Visit, 0)
+        });
+        let res = svc
+            .ingest(tokio_stream::iter(events.map(Ok)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.accepted, 1);
+        assert_eq!(res.rejected, 2);
+
+        let partitions: i64 = sqlx::query_scalar(
+            "select count(*) from pg_inherits where inhparent = 'user_events'::regclass",
+        )
+        .fetch_one(&svc.pool)
+        .await
+        .unwrap();
+        assert_eq!(partitions, 1);
+    }
+
+    #[tokio::test]
+    async fn ingest_should_keep_history_bounded_and_most_recent_first() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "new.user@example.com";
+        let max_history = svc.config.ingest.max_history as u32;
+        let events = (1..=max_history + 20)
+            .chain([1, 2, 2])
+            .map(|id| event(email, EventType::Start, id));
+        svc.ingest(tokio_stream::iter(events.map(Ok)))
+            .await
+            .unwrap();
+
+        let stat = get_stat(&svc, email).await;
+        let recent_watched = stat.recent_watched.unwrap();
+        assert_eq!(recent_watched.len(), max_history as usize);
+        assert_eq!(recent_watched[..3], [2, 1, (max_history + 20) as i32]);
+        let started = stat.started_but_not_finished.unwrap();
+        assert_eq!(started.len(), max_history as usize);
+        assert_eq!(started[..2], [2, 1]);
+    }
+}
@@ -1,41 +1,65 @@
-use chrono::{TimeZone, Utc};
+mod ingest;
+mod rebuild;
+mod user;
+
+pub use user::upsert_users;
+pub(crate) use user::MAX_BATCH_SIZE;
+
+use std::{
+    future::Future,
+    sync::{Arc, LazyLock},
+};
+
+use crm_core::{
+    metrics::{self, Histogram},
+    telemetry::sql_span,
+    ToUtc,
+};
 use itertools::Itertools;
-use tonic::{Response, Status};
+use tonic::Response;
+use tracing::Instrument;
 
 use crate::{
+    error::db,
     pb::user_stats::{QueryRequest, RawQueryRequest, TimeQuery, User},
-    ResponseStream, ServiceResult, UserStatsService,
+    Error, ResponseStream, ServiceResult, UserStatsService,
 };
 
+/// Labelled by a name of the query, never by the sql itself
+static SQL_DURATION: LazyLock<Arc<Histogram>> = LazyLock::new(|| {
+    metrics::histogram(
+        "user_stat_sql_duration_seconds",
+        "Time spent running SQL against postgres, by query",
+        &["query"],
+    )
+});
+
 impl UserStatsService {
     pub async fn query(&self, req: QueryRequest) -> ServiceResult<ResponseStream> {
         let time_conditions = req
             .timestamps
             .iter()
-            .map(|(col_name, query)| cast_timequery(col_name, query));
+            .map(|(col_name, query)| cast_timequery(col_name, query))
+            .collect::<Result<Vec<_>, _>>()?;
 
         let ids_conditions = req
             .ids
             .iter()
             .map(|(col_name, query)| cast_idquery(col_name, query.ids.as_slice()));
 
-        let conditions = Itertools::merge(time_conditions, ids_conditions).join(" AND ");
+        let conditions =
+            Itertools::merge(time_conditions.into_iter(), ids_conditions).join(" AND ");
 
         let sql = format!("select email, name from user_stats where {}", conditions);
-        println!("UserStatsService SQL: {}", &sql);
-
         self.raw_query(RawQueryRequest { query: sql }).await
     }
 
     pub async fn raw_query(&self, req: RawQueryRequest) -> ServiceResult<ResponseStream> {
-        println!("sql: {}", &req.query);
-        let Ok(ret) = sqlx::query_as::<_, User>(&req.query)
-            .fetch_all(&self.pool)
-            .await
-        else {
-            let err_msg = format!("Failed to fetch data, Query: {}", &req.query);
-            return ServiceResult::Err(Status::internal(err_msg));
-        };
+        let span = sql_span("raw_query");
+        span.record("db.statement", req.query.as_str());
+        let query = sqlx::query_as::<_, User>(&req.query).fetch_all(&self.pool);
+        let query = SQL_DURATION.time(&["raw_query"], query).instrument(span);
+        let ret = query.await.map_err(db("run query"))?;
 
         ServiceResult::Ok(Response::new(Box::pin(tokio_stream::iter(
             ret.into_iter().map(Ok),
@@ -43,27 +67,40 @@ impl UserStatsService {
     }
 }
 
-fn cast_timequery(col_name: &str, time_query: &TimeQuery) -> String {
-    match (time_query.after.as_ref(), time_query.before.as_ref()) {
+/// Run a query in its own span, timed under the same name
+async fn sql<F: Future>(query: &'static str, fut: F) -> F::Output {
+    SQL_DURATION
+        .time(&[query], fut)
+        .instrument(sql_span(query))
+        .await
+}
+
+fn cast_timequery(col_name: &str, time_query: &TimeQuery) -> Result<String, Error> {
+    let field = format!("timestamps[{}]", col_name);
+    let bound = |name: &str, t: &prost_types::Timestamp| {
+        t.to_utc().map(|t| t.to_rfc3339()).ok_or_else(|| {
+            let description = format!("{} of {} is out of range", name, col_name);
+            Error::invalid(format!("{}.{}", field, name), description)
+        })
+    };
+    let condition = match (time_query.after.as_ref(), time_query.before.as_ref()) {
         (Some(t_after), Some(t_before)) => {
-            let after = timestamp_to_utc(t_after).to_rfc3339();
-            let before = timestamp_to_utc(t_before).to_rfc3339();
+            let after = bound("after", t_after)?;
+            let before = bound("before", t_before)?;
             format!("{} between '{}' and '{}'", col_name, after, before)
         }
         (Some(t_after), None) => {
-            format!("{} >= {}", col_name, timestamp_to_utc(t_after).to_rfc3339())
+            format!("{} >= '{}'", col_name, bound("after", t_after)?)
         }
         (None, Some(t_before)) => {
-            format!(
-                "{} <= {}",
-                col_name,
-                timestamp_to_utc(t_before).to_rfc3339()
-            )
+            format!("{} <= '{}'", col_name, bound("before", t_before)?)
         }
-        _ => {
-            panic!("Invalid TimeQuery");
+        (None, None) => {
+            let description = format!("after or before of {} is required", col_name);
+            return Err(Error::invalid(field, description));
         }
-    }
+    };
+    Ok(condition)
 }
 
 fn cast_idquery(col_name: &str, ids: &[u32]) -> String {
@@ -73,14 +110,9 @@ fn cast_idquery(col_name: &str, ids: &[u32]) -> String {
     format!("array{:?} <@ {}", ids, col_name)
 }
 
-/// Cast prost_type::TimeStamp to chrono::UTC
-fn timestamp_to_utc(timestamp: &prost_types::Timestamp) -> chrono::DateTime<Utc> {
-    Utc.timestamp_opt(timestamp.seconds, timestamp.nanos as u32)
-        .unwrap()
-}
-
 #[cfg(test)]
 mod tests {
+    use crm_core::ErrorDetails;
     use tokio_stream::StreamExt;
 
     use crate::{
@@ -128,4 +160,31 @@ mod tests {
         assert_eq!(res.len(), 16);
         println!("res{:?}", res);
     }
+
+    #[tokio::test]
+    async fn query_without_bounds_should_be_rejected() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let query = QueryRequestBuilder::default()
+            .timestamp(("created_at".to_string(), TimeQuery::default()))
+            .build()
+            .unwrap();
+        let err = svc.query(query).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let details = ErrorDetails::from_status(&err);
+        assert_eq!(details.fields(), vec!["timestamps[created_at]"]);
+    }
+
+    #[tokio::test]
+    async fn failed_raw_query_should_not_leak_the_sql() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let req = RawQueryRequest {
+            query: "select secret from nowhere".to_string(),
+        };
+        let err = svc.raw_query(req).await.err().unwrap();
+        assert_eq!(err.code(), tonic::Code::Internal);
+        assert_eq!(err.message(), "failed to run query");
+        let details = ErrorDetails::from_status(&err);
+        assert_eq!(details.reason(), Some("INTERNAL"));
+        assert_eq!(details.info.unwrap().domain, "user-stat");
+    }
 }
@@ -0,0 +1,201 @@
+use chrono::{DateTime, Utc};
+use tonic::Response;
+use tracing::info;
+
+use super::ingest::{apply_event, Event};
+use crate::{
+    error::db,
+    pb::user_stats::{RebuildRequest, RebuildResponse},
+    ServiceResult, UserStatsService,
+};
+
+const RESET_SQL: &str = r#"
+    UPDATE user_stats SET
+      last_visited_at = NULL,
+      last_watched_at = NULL,
+      recent_watched = NULL,
+      viewed_but_not_started = NULL,
+      started_but_not_finished = NULL,
+      finished = NULL
+    WHERE email = $1
+    "#;
+
+/// The users left without events, e.g. once their partitions were dropped,
+/// derive nothing
+const RESET_WITHOUT_EVENTS_SQL: &str = r#"
+    UPDATE user_stats SET
+      last_visited_at = NULL,
+      last_watched_at = NULL,
+      recent_watched = NULL,
+      viewed_but_not_started = NULL,
+      started_but_not_finished = NULL,
+      finished = NULL
+    WHERE NOT EXISTS (SELECT 1 FROM user_events e WHERE e.email = user_stats.email)
+    "#;
+
+const USER_EVENTS_SQL: &str = r#"
+    SELECT email, event_type::text, content_id, created_at
+    FROM user_events
+    WHERE email = $1
+    ORDER BY created_at, id
+    "#;
+
+impl UserStatsService {
+    /// Recompute the aggregated columns of `user_stats` by replaying `user_events`.
+    /// Rebuilds a single user if `email` is set, otherwise every user: the ones
+    /// without events are reset, the others replayed.
+    pub async fn rebuild(&self, req: RebuildRequest) -> ServiceResult<RebuildResponse> {
+        let mut ret = RebuildResponse::default();
+        let emails: Vec<String> = if req.email.is_empty() {
+            ret.users = sqlx::query(RESET_WITHOUT_EVENTS_SQL)
+                .execute(&self.pool)
+                .await
+                .map_err(db("rebuild user stats"))?
+                .rows_affected();
+            sqlx::query_scalar("SELECT DISTINCT email FROM user_events")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(db("rebuild user stats"))?
+        } else {
+            vec![req.email]
+        };
+
+        let max_history = self.config.ingest.max_history;
+        for email in emails {
+            let Some(events) = self
+                .rebuild_user(&email, max_history)
+                .await
+                .map_err(db("rebuild user stats"))?
+            else {
+                continue;
+            };
+            ret.events += events;
+            ret.users += 1;
+        }
+        info!("rebuilt {} users from {} events", ret.users, ret.events);
+        Ok(Response::new(ret))
+    }
+
+    /// Reset and replay a single user in one transaction, returns the number of events replayed,
+    /// or None if the user has neither a row nor events
+    async fn rebuild_user(&self, email: &str, max_history: i32) -> sqlx::Result<Option<u64>> {
+        let mut tx = self.pool.begin().await?;
+        // locks the row, concurrent ingest for this user waits until the rebuild is committed
+        let reset = sqlx::query(RESET_SQL)
+            .bind(email)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        let rows: Vec<(String, String, Option<i32>, DateTime<Utc>)> =
+            sqlx::query_as(USER_EVENTS_SQL)
+                .bind(email)
+                .fetch_all(&mut *tx)
+                .await?;
+
+        let mut count = 0;
+        for (email, event_type, content_id, created_at) in rows {
+            let Some(event) = Event::from_row(email, &event_type, content_id, created_at) else {
+                continue;
+            };
+            apply_event(&mut tx, &event, max_history).await?;
+            count += 1;
+        }
+        tx.commit().await?;
+        Ok((reset > 0 || count > 0).then_some(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pb::user_stats::{EventType, UserEvent},
+        test_utils::days_ago,
+    };
+
+    fn event(email: &str, event_type: EventType, content_id: u32, days: i64) -> UserEvent {
+        UserEvent {
+            email: email.to_string(),
+            event_type: event_type as i32,
+            content_id,
+            timestamp: Some(days_ago(days)),
+        }
+    }
+
+    async fn get_finished(svc: &UserStatsService, email: &str) -> Option<Vec<i32>> {
+        sqlx::query_scalar("select finished from user_stats where email = $1")
+            .bind(email)
+            .fetch_one(&svc.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rebuild_should_recompute_stats_from_events() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "new.user@example.com";
+        // spans several days, so several partitions
+        let events = vec![
+            event(email, EventType::View, 1, 3),
+            event(email, EventType::Start, 1, 2),
+            event(email, EventType::Finish, 1, 1),
+            event("other@example.com", EventType::Visit, 0, 0),
+        ];
+        svc.ingest(tokio_stream::iter(events.into_iter().map(Ok)))
+            .await
+            .unwrap();
+
+        sqlx::query("update user_stats set finished = '{42}' where email = $1")
+            .bind(email)
+            .execute(&svc.pool)
+            .await
+            .unwrap();
+        assert_eq!(get_finished(&svc, email).await, Some(vec![42]));
+
+        let res = svc
+            .rebuild(RebuildRequest {
+                email: email.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.users, 1);
+        assert_eq!(res.events, 3);
+        assert_eq!(get_finished(&svc, email).await, Some(vec![1]));
+
+        let no_events = "brenna.elx4os2u@example.net";
+        sqlx::query("update user_stats set finished = '{42}' where email = $1")
+            .bind(no_events)
+            .execute(&svc.pool)
+            .await
+            .unwrap();
+        let without_events: i64 = sqlx::query_scalar(
+            "select count(*) from user_stats where email not in (select email from user_events)",
+        )
+        .fetch_one(&svc.pool)
+        .await
+        .unwrap();
+        let res = svc
+            .rebuild(RebuildRequest::default())
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.users, without_events as u64 + 2);
+        assert_eq!(res.events, 4);
+        assert_eq!(get_finished(&svc, no_events).await, None);
+    }
+
+    #[tokio::test]
+    async fn rebuild_should_not_count_unknown_users() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let res = svc
+            .rebuild(RebuildRequest {
+                email: "nobody@example.com".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.users, 0);
+        assert_eq!(res.events, 0);
+    }
+}
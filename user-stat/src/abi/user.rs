@@ -0,0 +1,508 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use crm_core::{ToTimestamp, ToUtc};
+use itertools::Itertools;
+use sqlx::PgExecutor;
+use tonic::Response;
+
+use super::sql;
+use crate::{
+    error::db,
+    pb::user_stats::{
+        BatchGetUsersRequest, BatchGetUsersResponse, BatchUpsertUsersRequest,
+        BatchUpsertUsersResponse, DeleteUserRequest, DeleteUserResponse, EventType, Gender,
+        GetUserRequest, UserEvent, UserExport, UserStat,
+    },
+    Error, ServiceResult, UserStatsService,
+};
+
+/// Max number of users fetched by one `BatchGetUsers`
+pub(crate) const MAX_BATCH_SIZE: usize = 1000;
+
+const USER_EVENTS_SQL: &str = r#"
+    SELECT event_type::text, content_id, created_at
+    FROM user_events
+    WHERE email = $1
+    ORDER BY created_at, id
+    "#;
+
+const GET_USERS_SQL: &str = r#"
+    SELECT email, name, gender::text AS gender, created_at, last_visited_at, last_watched_at,
+      recent_watched, viewed_but_not_started, started_but_not_finished, finished,
+      last_email_notification, last_in_app_notification, last_sms_notification
+    FROM user_stats
+    WHERE email = ANY($1)
+    "#;
+
+// same unnest strategy as examples/gen.rs, int[] columns are passed as their text form
+// since sqlx can't bind a 2d array. created_at is only set on insert
+const UPSERT_USERS_SQL: &str = r#"
+    INSERT INTO user_stats(email, name, gender, created_at, last_visited_at, last_watched_at,
+      recent_watched, viewed_but_not_started, started_but_not_finished, finished,
+      last_email_notification, last_in_app_notification, last_sms_notification)
+    SELECT t.email, t.name, t.gender, COALESCE(t.created_at, CURRENT_TIMESTAMP), t.last_visited_at,
+      t.last_watched_at, t.recent_watched::int[], t.viewed_but_not_started::int[],
+      t.started_but_not_finished::int[], t.finished::int[], t.last_email_notification,
+      t.last_in_app_notification, t.last_sms_notification
+    FROM unnest($1::text[], $2::text[], $3::text[]::gender[], $4::timestamptz[], $5::timestamptz[],
+      $6::timestamptz[], $7::text[], $8::text[], $9::text[], $10::text[], $11::timestamptz[],
+      $12::timestamptz[], $13::timestamptz[])
+      AS t(email, name, gender, created_at, last_visited_at, last_watched_at, recent_watched,
+        viewed_but_not_started, started_but_not_finished, finished, last_email_notification,
+        last_in_app_notification, last_sms_notification)
+    ON CONFLICT (email) DO UPDATE SET
+      name = EXCLUDED.name,
+      gender = EXCLUDED.gender,
+      last_visited_at = EXCLUDED.last_visited_at,
+      last_watched_at = EXCLUDED.last_watched_at,
+      recent_watched = EXCLUDED.recent_watched,
+      viewed_but_not_started = EXCLUDED.viewed_but_not_started,
+      started_but_not_finished = EXCLUDED.started_but_not_finished,
+      finished = EXCLUDED.finished,
+      last_email_notification = EXCLUDED.last_email_notification,
+      last_in_app_notification = EXCLUDED.last_in_app_notification,
+      last_sms_notification = EXCLUDED.last_sms_notification
+    "#;
+
+#[derive(Debug, sqlx::FromRow)]
+struct UserStatRow {
+    email: String,
+    name: String,
+    gender: Option<String>,
+    created_at: Option<DateTime<Utc>>,
+    last_visited_at: Option<DateTime<Utc>>,
+    last_watched_at: Option<DateTime<Utc>>,
+    recent_watched: Option<Vec<i32>>,
+    viewed_but_not_started: Option<Vec<i32>>,
+    started_but_not_finished: Option<Vec<i32>>,
+    finished: Option<Vec<i32>>,
+    last_email_notification: Option<DateTime<Utc>>,
+    last_in_app_notification: Option<DateTime<Utc>>,
+    last_sms_notification: Option<DateTime<Utc>>,
+}
+
+impl UserStatsService {
+    pub async fn get_user(&self, req: GetUserRequest) -> ServiceResult<UserStat> {
+        let Some(user) = self.fetch_user(&req.email).await? else {
+            return Err(Error::UserNotFound.into());
+        };
+        Ok(Response::new(user))
+    }
+
+    /// The users of `emails` with a row, in no particular order
+    pub async fn batch_get_users(
+        &self,
+        req: BatchGetUsersRequest,
+    ) -> ServiceResult<BatchGetUsersResponse> {
+        let query = sqlx::query_as(GET_USERS_SQL)
+            .bind(&req.emails)
+            .fetch_all(&self.pool);
+        let rows: Vec<UserStatRow> = sql("batch_get_users", query)
+            .await
+            .map_err(db("fetch users"))?;
+        let users = rows.into_iter().map(Into::into).collect();
+        Ok(Response::new(BatchGetUsersResponse { users }))
+    }
+
+    pub async fn upsert_user(&self, user: UserStat) -> ServiceResult<UserStat> {
+        let email = user.email.clone();
+        self.batch_upsert_users(BatchUpsertUsersRequest { users: vec![user] })
+            .await?;
+        let Some(user) = self.fetch_user(&email).await? else {
+            return Err(Error::UserLost.into());
+        };
+        Ok(Response::new(user))
+    }
+
+    /// Erase the user and its whole event log (GDPR)
+    pub async fn delete_user(&self, req: DeleteUserRequest) -> ServiceResult<DeleteUserResponse> {
+        let internal = db("delete user");
+        let mut tx = self.pool.begin().await.map_err(internal)?;
+        let query = sqlx::query("DELETE FROM user_stats WHERE email = $1")
+            .bind(&req.email)
+            .execute(&mut *tx);
+        let deleted = sql("delete_user", query)
+            .await
+            .map_err(internal)?
+            .rows_affected();
+        let query = sqlx::query("DELETE FROM user_events WHERE email = $1")
+            .bind(&req.email)
+            .execute(&mut *tx);
+        let events = sql("delete_user_events", query)
+            .await
+            .map_err(internal)?
+            .rows_affected();
+        tx.commit().await.map_err(internal)?;
+
+        Ok(Response::new(DeleteUserResponse {
+            deleted: deleted > 0,
+            events,
+        }))
+    }
+
+    /// Dump the user row and its whole event log (GDPR)
+    pub async fn export_user(&self, req: GetUserRequest) -> ServiceResult<UserExport> {
+        let user = self.fetch_user(&req.email).await?;
+        let query = sqlx::query_as(USER_EVENTS_SQL)
+            .bind(&req.email)
+            .fetch_all(&self.pool);
+        let rows: Vec<(String, Option<i32>, DateTime<Utc>)> =
+            sql("user_events", query).await.map_err(db("export user"))?;
+        let events = rows
+            .into_iter()
+            .map(|(event_type, content_id, created_at)| UserEvent {
+                email: req.email.clone(),
+                event_type: EventType::from_db_name(&event_type).unwrap_or_default() as i32,
+                content_id: content_id.unwrap_or_default() as u32,
+                timestamp: Some(created_at.to_timestamp()),
+            })
+            .collect();
+        Ok(Response::new(UserExport { user, events }))
+    }
+
+    pub async fn batch_upsert_users(
+        &self,
+        req: BatchUpsertUsersRequest,
+    ) -> ServiceResult<BatchUpsertUsersResponse> {
+        let affected = upsert_users(&self.pool, req.users)
+            .await
+            .map_err(db("upsert users"))?;
+        Ok(Response::new(BatchUpsertUsersResponse { affected }))
+    }
+
+    async fn fetch_user(&self, email: &str) -> Result<Option<UserStat>, Error> {
+        let query = sqlx::query_as(GET_USERS_SQL)
+            .bind([email])
+            .fetch_optional(&self.pool);
+        let row: Option<UserStatRow> = sql("get_user", query).await.map_err(db("fetch user"))?;
+        Ok(row.map(Into::into))
+    }
+}
+
+/// Insert or update `users` in a single statement.
+/// If an email shows up more than once, the last one wins.
+pub async fn upsert_users<'e>(
+    executor: impl PgExecutor<'e>,
+    users: impl IntoIterator<Item = UserStat>,
+) -> sqlx::Result<u64> {
+    fn to_string_list(list: Vec<Vec<u32>>) -> Vec<String> {
+        list.into_iter()
+            .map(|ids| format!("{{{}}}", ids.iter().map(|v| v.to_string()).join(",")))
+            .collect()
+    }
+    fn to_utc(ts: Option<prost_types::Timestamp>) -> Option<DateTime<Utc>> {
+        ts.as_ref().and_then(ToUtc::to_utc)
+    }
+
+    // ON CONFLICT DO UPDATE can't touch the same row twice in one statement
+    let mut index = HashMap::new();
+    let mut deduped = Vec::new();
+    for user in users {
+        match index.get(&user.email) {
+            Some(&i) => deduped[i] = user,
+            None => {
+                index.insert(user.email.clone(), deduped.len());
+                deduped.push(user);
+            }
+        }
+    }
+    let users = deduped;
+    if users.is_empty() {
+        return Ok(0);
+    }
+
+    let emails = users.iter().map(|u| u.email.clone()).collect::<Vec<_>>();
+    let names = users.iter().map(|u| u.name.clone()).collect::<Vec<_>>();
+    let genders = users
+        .iter()
+        .map(|u| u.gender().db_name())
+        .collect::<Vec<_>>();
+    let created_ats = users
+        .iter()
+        .map(|u| to_utc(u.created_at))
+        .collect::<Vec<_>>();
+    let last_visited_ats = users
+        .iter()
+        .map(|u| to_utc(u.last_visited_at))
+        .collect::<Vec<_>>();
+    let last_watched_ats = users
+        .iter()
+        .map(|u| to_utc(u.last_watched_at))
+        .collect::<Vec<_>>();
+    let recent_watcheds = users
+        .iter()
+        .map(|u| u.recent_watched.clone())
+        .collect::<Vec<_>>();
+    let viewed_but_not_starteds = users
+        .iter()
+        .map(|u| u.viewed_but_not_started.clone())
+        .collect::<Vec<_>>();
+    let started_but_not_finisheds = users
+        .iter()
+        .map(|u| u.started_but_not_finished.clone())
+        .collect::<Vec<_>>();
+    let finisheds = users.iter().map(|u| u.finished.clone()).collect::<Vec<_>>();
+    let last_email_notifications = users
+        .iter()
+        .map(|u| to_utc(u.last_email_notification))
+        .collect::<Vec<_>>();
+    let last_in_app_notifications = users
+        .iter()
+        .map(|u| to_utc(u.last_in_app_notification))
+        .collect::<Vec<_>>();
+    let last_sms_notifications = users
+        .iter()
+        .map(|u| to_utc(u.last_sms_notification))
+        .collect::<Vec<_>>();
+
+    let query = sqlx::query(UPSERT_USERS_SQL)
+        .bind(emails)
+        .bind(names)
+        .bind(genders)
+        .bind(created_ats)
+        .bind(last_visited_ats)
+        .bind(last_watched_ats)
+        .bind(to_string_list(recent_watcheds))
+        .bind(to_string_list(viewed_but_not_starteds))
+        .bind(to_string_list(started_but_not_finisheds))
+        .bind(to_string_list(finisheds))
+        .bind(last_email_notifications)
+        .bind(last_in_app_notifications)
+        .bind(last_sms_notifications)
+        .execute(executor);
+    let affected = sql("upsert_users", query).await?.rows_affected();
+    Ok(affected)
+}
+
+impl Gender {
+    /// Name of the gender in the `gender` db enum
+    fn db_name(&self) -> &'static str {
+        match self {
+            Gender::Female => "female",
+            Gender::Male => "male",
+            Gender::Unknown | Gender::Unspecified => "unknown",
+        }
+    }
+
+    fn from_db_name(name: &str) -> Self {
+        match name {
+            "female" => Gender::Female,
+            "male" => Gender::Male,
+            _ => Gender::Unknown,
+        }
+    }
+}
+
+impl From<UserStatRow> for UserStat {
+    fn from(row: UserStatRow) -> Self {
+        fn to_ids(ids: Option<Vec<i32>>) -> Vec<u32> {
+            ids.unwrap_or_default()
+                .into_iter()
+                .map(|id| id as u32)
+                .collect()
+        }
+        let gender = row
+            .gender
+            .as_deref()
+            .map(Gender::from_db_name)
+            .unwrap_or(Gender::Unknown);
+        Self {
+            email: row.email,
+            name: row.name,
+            gender: gender as i32,
+            created_at: row.created_at.map(|t| t.to_timestamp()),
+            last_visited_at: row.last_visited_at.map(|t| t.to_timestamp()),
+            last_watched_at: row.last_watched_at.map(|t| t.to_timestamp()),
+            recent_watched: to_ids(row.recent_watched),
+            viewed_but_not_started: to_ids(row.viewed_but_not_started),
+            started_but_not_finished: to_ids(row.started_but_not_finished),
+            finished: to_ids(row.finished),
+            last_email_notification: row.last_email_notification.map(|t| t.to_timestamp()),
+            last_in_app_notification: row.last_in_app_notification.map(|t| t.to_timestamp()),
+            last_sms_notification: row.last_sms_notification.map(|t| t.to_timestamp()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Request;
+
+    use crate::{
+        pb::user_stats::{user_stats_server::UserStats, EventType, UserEvent, UserStatBuilder},
+        test_utils::{days_ago, to_timestamp},
+    };
+
+    fn new_user(email: &str, name: &str) -> UserStat {
+        UserStatBuilder::default()
+            .email(email)
+            .name(name)
+            .gender(Gender::Female as i32)
+            .created_at(to_timestamp(10))
+            .recent_watched(vec![3, 2, 1])
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn get_user_should_work() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let user = svc
+            .get_user(GetUserRequest {
+                email: "brenna.elx4os2u@example.net".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(user.name, "高菲霞");
+        assert!(user.created_at.is_some());
+
+        let err = svc
+            .get_user(GetUserRequest {
+                email: "nobody@example.com".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
+        assert_eq!(err.message(), "user not found");
+        let info = crm_core::ErrorDetails::from_status(&err).info.unwrap();
+        assert_eq!(info.reason, "USER_NOT_FOUND");
+        assert!(info.metadata.is_empty());
+    }
+
+    #[tokio::test]
+    async fn batch_get_users_should_skip_unknown_emails() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let req = BatchGetUsersRequest {
+            emails: vec![
+                "brenna.elx4os2u@example.net".to_string(),
+                "nobody@example.com".to_string(),
+            ],
+        };
+        let users = svc.batch_get_users(req).await.unwrap().into_inner().users;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].name, "高菲霞");
+    }
+
+    #[tokio::test]
+    async fn upsert_user_should_insert_then_update() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let user = new_user("new.user@example.com", "Alice");
+        let ret = svc.upsert_user(user.clone()).await.unwrap().into_inner();
+        assert_eq!(ret, user);
+
+        let mut updated = new_user("new.user@example.com", "Bob");
+        updated.created_at = None;
+        updated.finished = vec![4];
+        let ret = svc.upsert_user(updated).await.unwrap().into_inner();
+        assert_eq!(ret.name, "Bob");
+        assert_eq!(ret.finished, vec![4]);
+        // created_at is kept
+        assert_eq!(ret.created_at, user.created_at);
+
+        let request = Request::new(UserStat::default());
+        let err = UserStats::upsert_user(&svc, request).await.unwrap_err();
+        assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        let details = crm_core::ErrorDetails::from_status(&err);
+        assert_eq!(details.fields(), vec!["email"]);
+    }
+
+    #[tokio::test]
+    async fn batch_upsert_users_should_work() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let users = vec![
+            new_user("a@example.com", "A"),
+            new_user("b@example.com", "B"),
+            new_user("a@example.com", "A2"),
+            new_user("brenna.elx4os2u@example.net", "Brenna"),
+        ];
+        let res = svc
+            .batch_upsert_users(BatchUpsertUsersRequest { users })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(res.affected, 3);
+
+        let user = svc.fetch_user("a@example.com").await.unwrap().unwrap();
+        assert_eq!(user.name, "A2");
+        let user = svc
+            .fetch_user("brenna.elx4os2u@example.net")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(user.name, "Brenna");
+        assert_eq!(user.recent_watched, vec![3, 2, 1]);
+    }
+
+    #[tokio::test]
+    async fn export_user_should_include_events() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "brenna.elx4os2u@example.net";
+        let events = vec![
+            UserEvent {
+                email: email.to_string(),
+                event_type: EventType::View as i32,
+                content_id: 1,
+                timestamp: Some(days_ago(2)),
+            },
+            UserEvent {
+                email: email.to_string(),
+                event_type: EventType::Visit as i32,
+                content_id: 0,
+                timestamp: Some(days_ago(1)),
+            },
+        ];
+        svc.ingest(tokio_stream::iter(events.clone().into_iter().map(Ok)))
+            .await
+            .unwrap();
+
+        let export = svc
+            .export_user(GetUserRequest {
+                email: email.to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(export.user.unwrap().name, "高菲霞");
+        assert_eq!(export.events, events);
+
+        let export = svc
+            .export_user(GetUserRequest {
+                email: "nobody@example.com".to_string(),
+            })
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(export.user.is_none());
+        assert!(export.events.is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_user_should_erase_stats_and_events() {
+        let (_tdb, svc) = UserStatsService::new_for_test().await;
+        let email = "brenna.elx4os2u@example.net";
+        let events = vec![UserEvent {
+            email: email.to_string(),
+            event_type: EventType::Start as i32,
+            content_id: 1,
+            timestamp: None,
+        }];
+        svc.ingest(tokio_stream::iter(events.into_iter().map(Ok)))
+            .await
+            .unwrap();
+
+        let req = DeleteUserRequest {
+            email: email.to_string(),
+        };
+        let res = svc.delete_user(req.clone()).await.unwrap().into_inner();
+        assert!(res.deleted);
+        assert_eq!(res.events, 1);
+        assert!(svc.fetch_user(email).await.unwrap().is_none());
+
+        let res = svc.delete_user(req).await.unwrap().into_inner();
+        assert!(!res.deleted);
+        assert_eq!(res.events, 0);
+    }
+}
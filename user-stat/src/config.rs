@@ -1,41 +1,92 @@
-use anyhow::{bail, Result};
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Duration, Utc};
+use crm_core::{AuthConfig, Checks, Config, ServerConfig, TelemetryConfig};
 use serde::{Deserialize, Serialize};
-use std::fs;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct AppConfig {
-    pub server: ServerConfig,
+    pub server: ServerConfig<Db>,
     pub auth: AuthConfig,
+    pub ingest: IngestConfig,
+    /// span export, traces stay local when unset
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
 }
 
+/// The database of the service
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct ServerConfig {
-    // pub host: String,
-    pub port: u16,
+pub struct Db {
     pub db_url: String,
 }
+
 #[derive(Debug, Deserialize, Serialize, Clone)]
-pub struct AuthConfig {
-    pub pk: String,
+pub struct IngestConfig {
+    /// max number of events applied in one transaction
+    pub batch_size: usize,
+    /// max number of content ids kept in each history array of `user_stats`,
+    /// an int as it is bound in sql
+    pub max_history: i32,
+    /// events older than this are rejected, so no partition is created for them
+    pub max_event_age_days: u32,
+    /// how far in the future an event may be dated, for the clocks of the clients
+    pub max_clock_skew_secs: u32,
 }
 
-impl AppConfig {
-    pub fn load() -> Result<Self> {
-        let ret: Result<AppConfig, _> = match (
-            fs::File::open("user_stat.yml"),
-            fs::File::open("/etc/config/user_stat.yml"),
-            std::env::var("USER_STAT_CONFIG"),
-        ) {
-            (Ok(reader), _, _) => serde_yaml::from_reader(reader),
-            (Err(_), Ok(reader), _) => serde_yaml::from_reader(reader),
-            (Err(_), Err(_), Ok(reader)) => serde_yaml::from_reader(fs::File::open(reader)?),
-            _ => bail!("Config file not found"),
-        };
-
-        Ok(ret?)
+impl IngestConfig {
+    /// Timestamps of the events accepted at `now`
+    pub fn window(&self, now: DateTime<Utc>) -> RangeInclusive<DateTime<Utc>> {
+        let oldest = now - Duration::days(self.max_event_age_days.into());
+        let newest = now + Duration::seconds(self.max_clock_skew_secs.into());
+        oldest..=newest
     }
 }
 
+impl Config for AppConfig {
+    const NAME: &'static str = "user_stat";
+    const ENV_PREFIX: &'static str = "USER_STAT";
+    const DEFAULTS: &'static str = r#"
+server:
+    port: 50001
+    metrics_port: 9101
+    shutdown_timeout_secs: 30
+ingest:
+    batch_size: 500
+    max_history: 100
+    max_event_age_days: 365
+    max_clock_skew_secs: 300
+"#;
+    const SECRETS: &'static [&'static str] = &["server.db_url", "server.tls.key"];
+
+    fn validate(&self, checks: &mut Checks) {
+        self.server.validate("server", checks);
+        checks.url(
+            "server.db_url",
+            &self.server.extra.db_url,
+            &["postgres", "postgresql"],
+        );
+        self.auth.validate("auth", checks);
+        checks.check(
+            "ingest.batch_size",
+            self.ingest.batch_size > 0,
+            "must not be 0",
+        );
+        checks.check(
+            "ingest.max_history",
+            self.ingest.max_history > 0,
+            "must be positive",
+        );
+        checks.check(
+            "ingest.max_event_age_days",
+            self.ingest.max_event_age_days > 0,
+            "must not be 0",
+        );
+        self.telemetry.validate(checks);
+    }
+}
+
+crm_core::service_config!(AppConfig, Db);
+
 #[cfg(test)]
 mod tests {
     use super::*;
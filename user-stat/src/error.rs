@@ -0,0 +1,70 @@
+use crm_core::{FieldViolation, ServiceError};
+use tonic::{Code, Status};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// `field` is the path of the invalid field, e.g. `users[0].email`
+    #[error("{description}")]
+    InvalidArgument { field: String, description: String },
+    /// emails are personal data, they stay out of the status and its details
+    #[error("user not found")]
+    UserNotFound,
+    #[error("user lost after upsert")]
+    UserLost,
+    /// the db error is logged, never sent to the caller
+    #[error("failed to {op}")]
+    Db {
+        op: &'static str,
+        #[source]
+        source: sqlx::Error,
+    },
+}
+
+impl Error {
+    pub fn invalid(field: impl Into<String>, description: impl Into<String>) -> Self {
+        Error::InvalidArgument {
+            field: field.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Maps the failure of a db call made to `op`
+pub(crate) fn db(op: &'static str) -> impl Fn(sqlx::Error) -> Error + Copy {
+    move |source| Error::Db { op, source }
+}
+
+impl ServiceError for Error {
+    const DOMAIN: &'static str = "user-stat";
+
+    fn code(&self) -> Code {
+        match self {
+            Error::InvalidArgument { .. } => Code::InvalidArgument,
+            Error::UserNotFound => Code::NotFound,
+            Error::UserLost | Error::Db { .. } => Code::Internal,
+        }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self {
+            Error::InvalidArgument { .. } => "INVALID_ARGUMENT",
+            Error::UserNotFound => "USER_NOT_FOUND",
+            Error::UserLost | Error::Db { .. } => "INTERNAL",
+        }
+    }
+
+    fn violations(&self) -> Vec<FieldViolation> {
+        match self {
+            Error::InvalidArgument { field, description } => {
+                vec![FieldViolation::new(field, description)]
+            }
+            _ => vec![],
+        }
+    }
+}
+
+impl From<Error> for Status {
+    fn from(e: Error) -> Self {
+        e.into_status()
+    }
+}
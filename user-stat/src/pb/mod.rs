@@ -1 +1,6 @@
+#[rustfmt::skip]
 pub mod user_stats;
+
+/// Descriptors of the compiled protos, served by the reflection service
+pub const FILE_DESCRIPTOR_SET: &[u8] =
+    include_bytes!(concat!(env!("OUT_DIR"), "/user_stat_descriptor.bin"));
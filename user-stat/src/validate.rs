@@ -0,0 +1,240 @@
+use crm_core::{Validate, Violations};
+use itertools::Itertools;
+
+use crate::{
+    abi::MAX_BATCH_SIZE,
+    pb::user_stats::{
+        BatchGetUsersRequest, BatchUpsertUsersRequest, DeleteUserRequest, EventType, Gender,
+        GetUserRequest, QueryRequest, RawQueryRequest, RebuildRequest, TimeQuery, UserEvent,
+        UserStat,
+    },
+};
+
+/// Columns of `user_stats` a `TimeQuery` can filter on
+const TIMESTAMP_COLUMNS: &[&str] = &[
+    "created_at",
+    "last_visited_at",
+    "last_watched_at",
+    "last_email_notification",
+    "last_in_app_notification",
+    "last_sms_notification",
+];
+
+/// Columns of `user_stats` an `IdQuery` can filter on
+const ID_COLUMNS: &[&str] = &[
+    "recent_watched",
+    "viewed_but_not_started",
+    "started_but_not_finished",
+    "finished",
+];
+
+impl Validate for QueryRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.check(
+            "timestamps",
+            !self.timestamps.is_empty() || !self.ids.is_empty(),
+            "a time or id query is required",
+        );
+        // the columns end up in the sql, only known ones are let through
+        for (column, query) in self.timestamps.iter().sorted_by_key(|(column, _)| *column) {
+            let field = format!("timestamps[{}]", column);
+            v.check(
+                &field,
+                TIMESTAMP_COLUMNS.contains(&column.as_str()),
+                "unknown column",
+            );
+            v.nested(&field, query);
+        }
+        for column in self.ids.keys().sorted() {
+            v.check(
+                &format!("ids[{}]", column),
+                ID_COLUMNS.contains(&column.as_str()),
+                "unknown column",
+            );
+        }
+    }
+}
+
+impl Validate for TimeQuery {
+    fn validate(&self, v: &mut Violations) {
+        v.check(
+            "",
+            self.after.is_some() || self.before.is_some(),
+            "after or before is required",
+        );
+        v.timestamp("after", self.after.as_ref());
+        v.timestamp("before", self.before.as_ref());
+    }
+}
+
+impl Validate for RawQueryRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.required("query", &self.query);
+    }
+}
+
+impl Validate for UserEvent {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+        v.specified::<EventType>("event_type", self.event_type);
+        if self.event_type != EventType::Visit as i32 {
+            v.positive("content_id", self.content_id);
+        }
+        v.db_id("content_id", self.content_id);
+        v.timestamp("timestamp", self.timestamp.as_ref());
+    }
+}
+
+impl Validate for RebuildRequest {
+    /// every user is rebuilt without an email
+    fn validate(&self, v: &mut Violations) {
+        if !self.email.is_empty() {
+            v.email("email", &self.email);
+        }
+    }
+}
+
+impl Validate for GetUserRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+    }
+}
+
+impl Validate for BatchGetUsersRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.max_items("emails", &self.emails, MAX_BATCH_SIZE);
+        for (i, email) in self.emails.iter().enumerate() {
+            v.email(&format!("emails[{}]", i), email);
+        }
+    }
+}
+
+impl Validate for DeleteUserRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+    }
+}
+
+impl Validate for UserStat {
+    fn validate(&self, v: &mut Violations) {
+        v.email("email", &self.email);
+        v.known::<Gender>("gender", self.gender);
+        let ids = [
+            ("recent_watched", &self.recent_watched),
+            ("viewed_but_not_started", &self.viewed_but_not_started),
+            ("started_but_not_finished", &self.started_but_not_finished),
+            ("finished", &self.finished),
+        ];
+        for (field, ids) in ids {
+            for (i, id) in ids.iter().enumerate() {
+                v.db_id(&format!("{}[{}]", field, i), *id);
+            }
+        }
+        let timestamps = [
+            ("created_at", &self.created_at),
+            ("last_visited_at", &self.last_visited_at),
+            ("last_watched_at", &self.last_watched_at),
+            ("last_email_notification", &self.last_email_notification),
+            ("last_in_app_notification", &self.last_in_app_notification),
+            ("last_sms_notification", &self.last_sms_notification),
+        ];
+        for (field, timestamp) in timestamps {
+            v.timestamp(field, timestamp.as_ref());
+        }
+    }
+}
+
+impl Validate for BatchUpsertUsersRequest {
+    fn validate(&self, v: &mut Violations) {
+        v.each("users", &self.users);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crm_core::ErrorDetails;
+    use prost_types::Timestamp;
+
+    use super::*;
+    use crate::{
+        pb::user_stats::IdQuery,
+        test_utils::{new_timequery, to_timestamp},
+    };
+
+    fn fields<T: Validate>(message: T) -> Vec<String> {
+        let status = message.validated("user-stat").err().unwrap();
+        let details = ErrorDetails::from_status(&status);
+        details.fields().into_iter().map(String::from).collect()
+    }
+
+    #[test]
+    fn query_should_only_filter_known_columns() {
+        let mut req = QueryRequest::default();
+        assert_eq!(fields(req.clone()), vec!["timestamps"]);
+
+        req.timestamps.insert(
+            "created_at".to_string(),
+            new_timequery(to_timestamp(10), to_timestamp(0)),
+        );
+        req.ids
+            .insert("finished".to_string(), IdQuery { ids: vec![1] });
+        assert!(req.is_valid());
+
+        req.timestamps.insert(
+            "1=1; drop table user_stats".to_string(),
+            TimeQuery::default(),
+        );
+        req.ids
+            .insert("email".to_string(), IdQuery { ids: vec![1] });
+        assert_eq!(
+            fields(req),
+            vec![
+                "timestamps[1=1; drop table user_stats]",
+                "timestamps[1=1; drop table user_stats]",
+                "ids[email]",
+            ]
+        );
+    }
+
+    #[test]
+    fn user_stat_should_be_validated() {
+        let user = UserStat {
+            email: "not an email".to_string(),
+            gender: 42,
+            finished: vec![1, u32::MAX],
+            last_visited_at: Some(Timestamp {
+                seconds: i64::MAX,
+                nanos: 0,
+            }),
+            ..Default::default()
+        };
+        let req = BatchUpsertUsersRequest { users: vec![user] };
+        assert_eq!(
+            fields(req),
+            vec![
+                "users[0].email",
+                "users[0].gender",
+                "users[0].finished[1]",
+                "users[0].last_visited_at",
+            ]
+        );
+    }
+
+    #[test]
+    fn user_event_should_be_validated() {
+        let event = UserEvent {
+            email: "a@example.com".to_string(),
+            event_type: EventType::Visit as i32,
+            content_id: 0,
+            timestamp: None,
+        };
+        assert!(event.is_valid());
+        let event = UserEvent {
+            event_type: EventType::View as i32,
+            ..event
+        };
+        assert_eq!(fields(event), vec!["content_id"]);
+        assert_eq!(fields(GetUserRequest::default()), vec!["email"]);
+        assert!(RebuildRequest::default().is_valid());
+    }
+}